@@ -1,9 +1,14 @@
+#[cfg(feature = "tokio")]
+extern crate bytes;
 extern crate byteorder;
 extern crate core;
 extern crate smoltcp;
+#[cfg(feature = "tokio")]
+extern crate tokio_util;
 
 use core::fmt;
 use std::error::Error as StdError;
+use std::io;
 
 mod field {
     use core::ops;
@@ -36,6 +41,12 @@ pub enum Error {
     BadOxmField,
     /// The the match type field in a flow match header is invalid
     BadMatchType,
+    /// A flow match contains the same OXM field more than once
+    DuplicateOxmField,
+    /// The two ends of a connection have no OpenFlow version in common.
+    IncompatibleVersion,
+    /// Reading or writing a message failed with an I/O error of the given kind.
+    Io(io::ErrorKind),
     #[doc(hidden)] __Nonexhaustive,
 }
 
@@ -44,7 +55,11 @@ pub type Result<T> = core::result::Result<T, Error>;
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.description())
+        write!(f, "{}", self.description())?;
+        if let Error::Io(kind) = *self {
+            write!(f, ": {}", kind)?;
+        }
+        Ok(())
     }
 }
 
@@ -59,6 +74,9 @@ impl StdError for Error {
             Error::UnsupportedOxmClass => "unsupported oxm class",
             Error::BadOxmField => "unknown oxm field",
             Error::BadMatchType => "unknown match type",
+            Error::DuplicateOxmField => "duplicate oxm field",
+            Error::IncompatibleVersion => "no common openflow version",
+            Error::Io(_) => "i/o error",
             Error::__Nonexhaustive => unreachable!(),
         }
     }
@@ -68,6 +86,12 @@ impl StdError for Error {
     }
 }
 
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err.kind())
+    }
+}
+
 pub trait Repr
 where
     Self: Sized,
@@ -85,8 +109,12 @@ where
 #[macro_use]
 mod macros;
 mod port;
+mod text;
 pub mod oxm;
+pub mod oxs;
 mod packets;
 
 pub use packets::openflow::{Packet, PacketRepr, PayloadRepr};
 pub use packets::*;
+pub use port::PortNumber;
+pub use text::{ParseError, ParseErrorKind};
@@ -0,0 +1,456 @@
+//! Set operations on flow matches.
+//!
+//! A flow match is seen as the set of packets it matches. Every field, masked or not, constrains
+//! some bits of the packet: a field without a mask constrains all of its bits, and a field with a
+//! mask only the bits set in the mask. Two flow matches can then be compared bit by bit, which is
+//! what switches do to detect overlapping flow entries (`FlowModFailedCode::Overlap`).
+//!
+//! The prerequisites of the fields are not taken into account: the flow matches are expected to
+//! be [valid](type.FlowMatch.html#method.validate), in which case they already contain the fields
+//! that their prerequisites require. NXM fields are compared bit by bit, like the other fields.
+//! Experimenter fields and unknown fields cannot be interpreted, so they are only compared for
+//! equality.
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use Repr;
+use super::fields::{self, consts};
+use super::{nxm, FlowMatch, FlowMatchField, Oxm, Packet, PacketRegisters, CLASS_NXM1,
+            CLASS_OPEN_FLOW_BASIC, CLASS_PACKET_REGISTERS};
+
+/// Return the mask that covers all the meaningful bits of a field. Most fields use all the bits
+/// of their value, but some are narrower than their wire format.
+fn full_mask(class: u16, field: u8, len: usize) -> Vec<u8> {
+    if class != CLASS_OPEN_FLOW_BASIC {
+        return vec![0xff; len];
+    }
+    match field {
+        consts::VLAN_ID => vec![0x1f, 0xff],
+        consts::IPV6_FLOW_LABEL => vec![0x00, 0x0f, 0xff, 0xff],
+        consts::IPV6_EXTENSION_HEADER => vec![0x01, 0xff],
+        consts::TCP_FLAGS => vec![0x0f, 0xff],
+        _ => vec![0xff; len],
+    }
+}
+
+/// A field of a flow match, with an explicit mask and a value that has no bits set outside of
+/// the mask.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Constraint {
+    pub class: u16,
+    pub field: u8,
+    pub value: Vec<u8>,
+    pub mask: Vec<u8>,
+}
+
+impl Constraint {
+    /// Return the constraint corresponding to a flow match field, a packet register or an NXM
+    /// field, or `None` for an experimenter field or an unknown field.
+    pub fn from_oxm<E>(oxm: &Oxm<E>) -> Option<Self> {
+        match *oxm {
+            Oxm::FlowMatchField(FlowMatchField::Unknown { .. }) => None,
+            Oxm::FlowMatchField(ref field) => {
+                fields::encode(field).ok().map(|bytes| Constraint::from_bytes(&bytes))
+            }
+            Oxm::PacketRegisters(ref registers) => {
+                let mut value = [0; 8];
+                let mut mask = [0; 8];
+                NetworkEndian::write_u64(&mut value, registers.value);
+                NetworkEndian::write_u64(&mut mask, registers.mask.unwrap_or(!0));
+                Some(Constraint::new(
+                    CLASS_PACKET_REGISTERS,
+                    registers.field,
+                    &value,
+                    Some(&mask),
+                ))
+            }
+            Oxm::Experimenter(_) | Oxm::Unknown { .. } => None,
+            Oxm::Nxm(ref field) => {
+                let mut bytes = vec![0; field.buffer_len()];
+                field
+                    .emit(&mut bytes)
+                    .expect("buffer_len() is large enough");
+                Some(Constraint::from_bytes(&bytes))
+            }
+        }
+    }
+
+    /// Return the constraint corresponding to the wire format of a field whose value and mask
+    /// have the same length.
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let packet = Packet::new(bytes);
+        let (value, mask) = if packet.has_mask() {
+            let (value, mask) = packet.value().split_at(packet.value().len() / 2);
+            (value, Some(mask))
+        } else {
+            (packet.value(), None)
+        };
+        Constraint::new(packet.class(), packet.field(), value, mask)
+    }
+
+    fn new(class: u16, field: u8, value: &[u8], mask: Option<&[u8]>) -> Self {
+        let full_mask = full_mask(class, field, value.len());
+        let mask = match mask {
+            Some(mask) => mask.iter().zip(&full_mask).map(|(m, f)| m & f).collect(),
+            None => full_mask,
+        };
+        let value = value.iter().zip(&mask).map(|(v, m)| v & m).collect();
+        Constraint {
+            class,
+            field,
+            value,
+            mask,
+        }
+    }
+
+    fn is_exact(&self) -> bool {
+        self.mask == full_mask(self.class, self.field, self.mask.len())
+    }
+
+    /// Return `true` if the mask is all zeros, _i.e._ if every packet satisfies the constraint.
+    pub fn is_wildcard(&self) -> bool {
+        self.mask.iter().all(|&m| m == 0)
+    }
+
+    /// Return `true` if every packet that satisfies `self` also satisfies `other`.
+    fn implies(&self, other: &Constraint) -> bool {
+        self.mask
+            .iter()
+            .zip(&other.mask)
+            .zip(self.value.iter().zip(&other.value))
+            .all(|((ma, mb), (va, vb))| mb & !ma == 0 && va & mb == *vb)
+    }
+
+    /// Return `true` if some packets satisfy both `self` and `other`.
+    fn is_compatible(&self, other: &Constraint) -> bool {
+        self.mask
+            .iter()
+            .zip(&other.mask)
+            .zip(self.value.iter().zip(&other.value))
+            .all(|((ma, mb), (va, vb))| (va ^ vb) & ma & mb == 0)
+    }
+
+    /// Return the constraint satisfied by the packets that satisfy both `self` and `other`. They
+    /// must be compatible.
+    fn intersect(&self, other: &Constraint) -> Constraint {
+        Constraint {
+            class: self.class,
+            field: self.field,
+            value: self.value.iter().zip(&other.value).map(|(a, b)| a | b).collect(),
+            mask: self.mask.iter().zip(&other.mask).map(|(a, b)| a | b).collect(),
+        }
+    }
+
+    /// Return the field corresponding to this constraint. A mask that is all ones is dropped.
+    pub fn to_oxm<E>(&self) -> Oxm<E> {
+        let mask = if self.is_exact() {
+            None
+        } else {
+            Some(&self.mask[..])
+        };
+        if self.class == CLASS_PACKET_REGISTERS {
+            return Oxm::PacketRegisters(PacketRegisters {
+                field: self.field,
+                value: NetworkEndian::read_u64(&self.value),
+                mask: mask.map(NetworkEndian::read_u64),
+            });
+        }
+        if self.class == CLASS_NXM1 {
+            let field = nxm::decode(self.field, &self.value, mask)
+                .expect("constraints are built from valid NXM fields");
+            return Oxm::Nxm(field);
+        }
+        // The constraint comes from a valid flow match field, and the mask of a field that is not
+        // maskable is always exact.
+        let field = fields::decode(self.field, &self.value, mask)
+            .expect("constraints are built from valid flow match fields");
+        Oxm::FlowMatchField(field)
+    }
+}
+
+/// Return the wire format of an experimenter field or of an unknown field. A field that cannot be
+/// emitted is compared as zeros.
+fn encode_opaque<E: Repr>(oxm: &Oxm<E>) -> Vec<u8> {
+    let mut bytes = vec![0; oxm.buffer_len()];
+    let _ = oxm.emit(&mut bytes);
+    bytes
+}
+
+/// A flow match in a form suitable for comparisons.
+struct Constraints {
+    /// The constraints on the flow match fields and on the packet registers.
+    fields: Vec<Constraint>,
+    /// The experimenter fields and the unknown fields, in their wire format.
+    opaque: Vec<Vec<u8>>,
+    /// `false` if a field appears twice with incompatible values, so that nothing matches.
+    satisfiable: bool,
+}
+
+impl Constraints {
+    fn new<E: Repr>(flow_match: &FlowMatch<E>) -> Self {
+        let mut constraints = Constraints {
+            fields: Vec::new(),
+            opaque: Vec::new(),
+            satisfiable: true,
+        };
+        for oxm in &flow_match.0 {
+            if let Some(constraint) = Constraint::from_oxm(oxm) {
+                constraints.add(constraint);
+            } else {
+                let bytes = encode_opaque(oxm);
+                if !constraints.opaque.contains(&bytes) {
+                    constraints.opaque.push(bytes);
+                }
+            }
+        }
+        constraints
+    }
+
+    fn find(&self, class: u16, field: u8) -> Option<&Constraint> {
+        self.fields
+            .iter()
+            .find(|constraint| constraint.class == class && constraint.field == field)
+    }
+
+    /// Add a constraint, merging it with the existing constraint on the same field if any.
+    fn add(&mut self, constraint: Constraint) {
+        let existing = self.fields
+            .iter()
+            .position(|c| c.class == constraint.class && c.field == constraint.field);
+        match existing {
+            Some(i) => {
+                if self.fields[i].is_compatible(&constraint) {
+                    self.fields[i] = self.fields[i].intersect(&constraint);
+                } else {
+                    self.satisfiable = false;
+                }
+            }
+            None => self.fields.push(constraint),
+        }
+    }
+
+    fn is_subset_of(&self, other: &Constraints) -> bool {
+        if !self.satisfiable {
+            return true;
+        }
+        if !other.satisfiable {
+            return false;
+        }
+        other.fields.iter().all(|b| match self.find(b.class, b.field) {
+            Some(a) => a.implies(b),
+            None => b.is_wildcard(),
+        }) && other.opaque.iter().all(|b| self.opaque.contains(b))
+    }
+
+    fn overlaps(&self, other: &Constraints) -> bool {
+        self.satisfiable && other.satisfiable
+            && self.fields.iter().all(|a| match other.find(a.class, a.field) {
+                Some(b) => a.is_compatible(b),
+                None => true,
+            })
+    }
+}
+
+impl<E: Repr> FlowMatch<E> {
+    /// Return `true` if every packet matched by this flow match is also matched by `other`, _i.e._
+    /// if a flow entry with this match is shadowed by a flow entry with the `other` match and a
+    /// higher priority.
+    ///
+    /// Experimenter and unknown fields are only compared for equality: `other` may only contain
+    /// experimenter and unknown fields that this flow match also contains.
+    pub fn is_subset_of(&self, other: &FlowMatch<E>) -> bool {
+        Constraints::new(self).is_subset_of(&Constraints::new(other))
+    }
+
+    /// Return `true` if some packets are matched by both this flow match and `other`. Two flow
+    /// entries with the same priority and overlapping matches are rejected by a switch with a
+    /// `FlowModFailedCode::Overlap` error when the `CHECK_OVERLAP` flag is set.
+    ///
+    /// Experimenter and unknown fields cannot be interpreted, and are assumed to overlap.
+    pub fn overlaps(&self, other: &FlowMatch<E>) -> bool {
+        Constraints::new(self).overlaps(&Constraints::new(other))
+    }
+}
+
+impl<E: Repr + Clone> FlowMatch<E> {
+    /// Return the flow match that matches exactly the packets matched by both this flow match and
+    /// `other`, or `None` if they do not overlap.
+    ///
+    /// The fields of this flow match come first, followed by the fields that only `other`
+    /// contains. A field present in both is merged into a single field, whose mask is the union of
+    /// the masks. The experimenter and unknown fields of both flow matches come last, without
+    /// duplicates.
+    pub fn intersect(&self, other: &FlowMatch<E>) -> Option<FlowMatch<E>> {
+        let mut constraints = Constraints::new(self);
+        let other_constraints = Constraints::new(other);
+        if !constraints.overlaps(&other_constraints) {
+            return None;
+        }
+        for constraint in other_constraints.fields {
+            constraints.add(constraint);
+        }
+
+        let mut oxms: Vec<Oxm<E>> = constraints.fields.iter().map(Constraint::to_oxm).collect();
+        let mut opaque = Vec::new();
+        for oxm in self.0.iter().chain(&other.0) {
+            if Constraint::from_oxm(oxm).is_none() {
+                let bytes = encode_opaque(oxm);
+                if !opaque.contains(&bytes) {
+                    opaque.push(bytes);
+                    oxms.push(oxm.clone());
+                }
+            }
+        }
+        Some(FlowMatch(oxms))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use oxm::*;
+    use smoltcp::wire::{EthernetProtocol, Ipv4Address};
+
+    fn flow_match(oxms: Vec<Oxm>) -> FlowMatch {
+        FlowMatch(oxms)
+    }
+
+    fn ipv4_source(address: [u8; 4], mask: Option<[u8; 4]>) -> Oxm {
+        Oxm::FlowMatchField(FlowMatchField::Ipv4Source(Ipv4Source::new(
+            Ipv4Address(address),
+            mask.map(Ipv4Address),
+        )))
+    }
+
+    fn tcp_destination(port: u16) -> Oxm {
+        Oxm::FlowMatchField(FlowMatchField::TcpDestination(TcpDestination::new(port)))
+    }
+
+    fn register(field: u8, value: u64, mask: Option<u64>) -> Oxm {
+        Oxm::PacketRegisters(PacketRegisters { field, value, mask })
+    }
+
+    fn ipv4() -> Vec<Oxm> {
+        vec![
+            Oxm::FlowMatchField(FlowMatchField::EthernetType(EthernetType::new(
+                EthernetProtocol::Ipv4,
+            ))),
+            Oxm::FlowMatchField(FlowMatchField::IpProtocol(IpProtocol::new(6))),
+        ]
+    }
+
+    fn with_ipv4(oxms: Vec<Oxm>) -> FlowMatch {
+        let mut all = ipv4();
+        all.extend(oxms);
+        flow_match(all)
+    }
+
+    #[test]
+    fn test_is_subset_of() {
+        let host = with_ipv4(vec![ipv4_source([10, 0, 0, 1], None), tcp_destination(80)]);
+        let subnet = with_ipv4(vec![ipv4_source([10, 0, 0, 0], Some([255, 255, 255, 0]))]);
+        let other_subnet = with_ipv4(vec![ipv4_source([10, 0, 1, 0], Some([255, 255, 255, 0]))]);
+        let wildcard = flow_match(vec![]);
+
+        assert!(host.is_subset_of(&host));
+        assert!(host.is_subset_of(&subnet));
+        assert!(host.is_subset_of(&wildcard));
+        assert!(subnet.is_subset_of(&wildcard));
+        assert!(!subnet.is_subset_of(&host));
+        assert!(!wildcard.is_subset_of(&subnet));
+        assert!(!host.is_subset_of(&other_subnet));
+    }
+
+    #[test]
+    fn test_is_subset_of_ignores_order_and_bits_outside_mask() {
+        let a = with_ipv4(vec![tcp_destination(80), ipv4_source([10, 0, 0, 7], None)]);
+        let b = flow_match(vec![
+            ipv4_source([10, 0, 0, 255], Some([255, 255, 255, 0])),
+            Oxm::FlowMatchField(FlowMatchField::IpProtocol(IpProtocol::new(6))),
+        ]);
+        assert!(a.is_subset_of(&b));
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let subnet = with_ipv4(vec![ipv4_source([10, 0, 0, 0], Some([255, 255, 255, 0]))]);
+        let large_subnet = with_ipv4(vec![ipv4_source([10, 0, 0, 0], Some([255, 0, 0, 0]))]);
+        let other_subnet = with_ipv4(vec![ipv4_source([10, 0, 1, 0], Some([255, 255, 255, 0]))]);
+        let http = with_ipv4(vec![tcp_destination(80)]);
+        let https = with_ipv4(vec![tcp_destination(443)]);
+
+        assert!(subnet.overlaps(&large_subnet));
+        assert!(large_subnet.overlaps(&other_subnet));
+        assert!(!subnet.overlaps(&other_subnet));
+        assert!(subnet.overlaps(&http));
+        assert!(!http.overlaps(&https));
+    }
+
+    #[test]
+    fn test_registers() {
+        let low = flow_match(vec![register(0, 0x1, Some(0xffff_ffff))]);
+        let high = flow_match(vec![register(0, 0x2_0000_0000, Some(0xffff_ffff_0000_0000))]);
+        let exact = flow_match(vec![register(0, 0x2_0000_0001, None)]);
+        let other_register = flow_match(vec![register(1, 0x2, None)]);
+
+        assert!(exact.is_subset_of(&low));
+        assert!(exact.is_subset_of(&high));
+        assert!(!low.is_subset_of(&exact));
+        assert!(low.overlaps(&high));
+        assert!(!exact.overlaps(&flow_match(vec![register(0, 0x3, Some(0xffff_ffff))])));
+        assert!(exact.overlaps(&other_register));
+        assert_eq!(low.intersect(&high), Some(exact));
+    }
+
+    #[test]
+    fn test_intersect() {
+        let subnet = with_ipv4(vec![ipv4_source([10, 0, 0, 0], Some([255, 255, 0, 0]))]);
+        let hosts = flow_match(vec![
+            tcp_destination(80),
+            ipv4_source([0, 0, 1, 2], Some([0, 0, 255, 255])),
+        ]);
+        assert_eq!(
+            subnet.intersect(&hosts),
+            Some(with_ipv4(vec![ipv4_source([10, 0, 1, 2], None), tcp_destination(80)]))
+        );
+        assert_eq!(hosts.intersect(&with_ipv4(vec![tcp_destination(443)])), None);
+    }
+
+    #[test]
+    fn test_conflicting_duplicates() {
+        let empty = flow_match(vec![tcp_destination(80), tcp_destination(443)]);
+        let http = flow_match(vec![tcp_destination(80)]);
+        assert!(empty.is_subset_of(&http));
+        assert!(!http.is_subset_of(&empty));
+        assert!(!empty.overlaps(&http));
+        assert_eq!(empty.intersect(&http), None);
+    }
+
+    #[test]
+    fn test_experimenter() {
+        let a = flow_match(vec![
+            Oxm::Experimenter(ExperimenterOxm::NxNshSi(1)),
+            tcp_destination(80),
+        ]);
+        let b = flow_match(vec![Oxm::Experimenter(ExperimenterOxm::NxNshSi(2))]);
+        let c = flow_match(vec![Oxm::Experimenter(ExperimenterOxm::NxNshSi(1))]);
+
+        assert!(a.is_subset_of(&c));
+        assert!(!a.is_subset_of(&b));
+        assert!(a.overlaps(&b));
+        assert_eq!(
+            a.intersect(&b),
+            Some(flow_match(vec![
+                tcp_destination(80),
+                Oxm::Experimenter(ExperimenterOxm::NxNshSi(1)),
+                Oxm::Experimenter(ExperimenterOxm::NxNshSi(2)),
+            ]))
+        );
+        assert_eq!(
+            a.intersect(&c),
+            Some(flow_match(vec![
+                tcp_destination(80),
+                Oxm::Experimenter(ExperimenterOxm::NxNshSi(1)),
+            ]))
+        );
+    }
+}
@@ -0,0 +1,828 @@
+//! A builder for flow matches.
+//!
+//! ```rust,ignore
+//! let flow_match: FlowMatch<E> = FlowMatch::builder()
+//!     .in_port(1)
+//!     .ipv4_src_cidr("10.0.0.0/8")
+//!     .tcp_dst(80)
+//!     .build()?;
+//! ```
+//!
+//! The builder adds the prerequisites of the fields when they can be deduced: above, `ipv4_src`
+//! requires `eth_type=0x0800` and `tcp_dst` requires `ip_proto=6`. Prerequisites that are
+//! ambiguous, like the Ethernet type of `tcp_dst` alone (IPv4 or IPv6), must be given explicitly.
+//! [`build`](struct.FlowMatchBuilder.html#method.build) validates the result, and rejects
+//! duplicate fields.
+
+use core::result;
+use core::str::FromStr;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use smoltcp::wire::{ArpOperation, EthernetAddress, EthernetProtocol, Icmpv4Message, Ipv4Address};
+
+use Repr;
+use port::PortNumber;
+use super::fields::{consts, ETH_TYPE_PBB, VLAN_PRESENT};
+use super::*;
+
+/// Return the fields that can be deduced from the prerequisites of the given field.
+fn implied_fields(field: u8) -> Vec<(u8, FlowMatchField)> {
+    let eth_type = |value: EthernetProtocol| {
+        (consts::ETHERNET_TYPE, FlowMatchField::EthernetType(EthernetType::new(value)))
+    };
+    let ip_proto =
+        |value: u8| (consts::IP_PROTOCOL, FlowMatchField::IpProtocol(IpProtocol::new(value)));
+    let icmpv6_type =
+        |value: u8| (consts::ICMPV6_TYPE, FlowMatchField::Icmpv6Type(Icmpv6Type::new(value)));
+    match field {
+        consts::VLAN_PRIORITY => vec![
+            (
+                consts::VLAN_ID,
+                FlowMatchField::VlanId(VlanId::new(VLAN_PRESENT, Some(VLAN_PRESENT))),
+            ),
+        ],
+        consts::IPV4_SOURCE | consts::IPV4_DESTINATION => vec![eth_type(EthernetProtocol::Ipv4)],
+        consts::ICMP_TYPE | consts::ICMP_CODE => vec![eth_type(EthernetProtocol::Ipv4), ip_proto(1)],
+        consts::ARP_OP_CODE | consts::ARP_SPA | consts::ARP_TPA | consts::ARP_SHA
+        | consts::ARP_THA => vec![eth_type(EthernetProtocol::Arp)],
+        consts::IPV6_SOURCE
+        | consts::IPV6_DESTINATION
+        | consts::IPV6_FLOW_LABEL
+        | consts::IPV6_EXTENSION_HEADER => vec![eth_type(EthernetProtocol::Ipv6)],
+        consts::ICMPV6_TYPE | consts::ICMPV6_CODE | consts::IPV6_ND_TARGET => {
+            vec![eth_type(EthernetProtocol::Ipv6), ip_proto(58)]
+        }
+        consts::IPV6_ND_SLL => vec![
+            eth_type(EthernetProtocol::Ipv6),
+            ip_proto(58),
+            icmpv6_type(135),
+        ],
+        consts::IPV6_ND_TLL => vec![
+            eth_type(EthernetProtocol::Ipv6),
+            ip_proto(58),
+            icmpv6_type(136),
+        ],
+        consts::TCP_SOURCE | consts::TCP_DESTINATION | consts::TCP_FLAGS => vec![ip_proto(6)],
+        consts::UDP_SOURCE | consts::UDP_DESTINATION => vec![ip_proto(17)],
+        consts::SCTP_SOURCE | consts::SCTP_DESTINATION => vec![ip_proto(132)],
+        consts::PBB_ISID | consts::PBB_UCA => vec![eth_type(ETH_TYPE_PBB.into())],
+        _ => vec![],
+    }
+}
+
+/// Order in which the fields are put in the flow match, so that each field comes after its own
+/// prerequisites: a field has a higher rank than all the fields it depends on.
+fn prerequisite_rank(field: u8) -> u8 {
+    match field {
+        consts::IN_PHYSICAL_PORT
+        | consts::VLAN_PRIORITY
+        | consts::IP_DSCP
+        | consts::IP_ECN
+        | consts::IP_PROTOCOL
+        | consts::IPV4_SOURCE
+        | consts::IPV4_DESTINATION
+        | consts::ARP_OP_CODE
+        | consts::ARP_SPA
+        | consts::ARP_TPA
+        | consts::ARP_SHA
+        | consts::ARP_THA
+        | consts::IPV6_SOURCE
+        | consts::IPV6_DESTINATION
+        | consts::IPV6_FLOW_LABEL
+        | consts::IPV6_EXTENSION_HEADER
+        | consts::MPLS_LABEL
+        | consts::MPLS_TC
+        | consts::MPLS_BOS
+        | consts::PBB_ISID
+        | consts::PBB_UCA => 1,
+        consts::TCP_SOURCE
+        | consts::TCP_DESTINATION
+        | consts::TCP_FLAGS
+        | consts::UDP_SOURCE
+        | consts::UDP_DESTINATION
+        | consts::SCTP_SOURCE
+        | consts::SCTP_DESTINATION
+        | consts::ICMP_TYPE
+        | consts::ICMP_CODE
+        | consts::ICMPV6_TYPE
+        | consts::ICMPV6_CODE => 2,
+        consts::IPV6_ND_TARGET | consts::IPV6_ND_SLL | consts::IPV6_ND_TLL => 3,
+        _ => 0,
+    }
+}
+
+/// Parse an address with an optional mask: `addr`, `addr/prefix_len` or `addr/mask`. The mask is
+/// `None` if all the bits are significant, and the bits of the address outside of the mask are
+/// cleared.
+fn parse_cidr<A: FromStr>(
+    s: &str,
+    len: usize,
+    octets: fn(&A) -> Vec<u8>,
+) -> Option<(Vec<u8>, Option<Vec<u8>>)> {
+    let mut parts = s.splitn(2, '/');
+    let address = octets(&parts.next()?.trim().parse().ok()?);
+    let mask = match parts.next() {
+        None => return Some((address, None)),
+        Some(mask) => match mask.trim().parse::<u8>() {
+            Ok(prefix_len) => prefix_mask(prefix_len.into(), len)?,
+            Err(_) => octets(&mask.trim().parse().ok()?),
+        },
+    };
+    Some(apply_mask(&address, mask))
+}
+
+/// Clear the bits of the address outside of the mask. The mask is `None` if all the bits are
+/// significant.
+fn apply_mask(address: &[u8], mask: Vec<u8>) -> (Vec<u8>, Option<Vec<u8>>) {
+    let address = address.iter().zip(&mask).map(|(a, m)| a & m).collect();
+    if mask.iter().all(|&m| m == 0xff) {
+        (address, None)
+    } else {
+        (address, Some(mask))
+    }
+}
+
+/// Return the address and mask matching the given IPv4 prefix.
+fn ipv4_prefix(
+    address: Ipv4Address,
+    prefix_len: u8,
+) -> Option<(Ipv4Address, Option<Ipv4Address>)> {
+    let (address, mask) = apply_mask(address.as_bytes(), prefix_mask(prefix_len.into(), 4)?);
+    Some((
+        Ipv4Address::from_bytes(&address),
+        mask.map(|mask| Ipv4Address::from_bytes(&mask)),
+    ))
+}
+
+fn ipv4_octets(address: &Ipv4Addr) -> Vec<u8> {
+    address.octets().to_vec()
+}
+
+fn ipv6_octets(address: &Ipv6Addr) -> Vec<u8> {
+    address.octets().to_vec()
+}
+
+fn to_ipv6(bytes: &[u8]) -> [u8; 16] {
+    let mut address = [0; 16];
+    address.copy_from_slice(bytes);
+    address
+}
+
+/// A builder for [`FlowMatch`](type.FlowMatch.html), returned by `FlowMatch::builder()`.
+#[derive(Debug, Clone)]
+pub struct FlowMatchBuilder<E = ExperimenterOxm> {
+    /// The fields given explicitly, with their `oxm_field`.
+    fields: Vec<(u8, FlowMatchField)>,
+    /// The fields deduced from the prerequisites of the explicit fields.
+    implied: Vec<(u8, FlowMatchField)>,
+    registers: Vec<PacketRegisters>,
+    experimenter: Vec<E>,
+    /// The first invalid value given to the builder.
+    error: Option<ValidationError>,
+}
+
+impl<E> FlowMatch<E> {
+    /// Return a builder for a flow match.
+    pub fn builder() -> FlowMatchBuilder<E> {
+        FlowMatchBuilder::new()
+    }
+}
+
+impl<E> Default for FlowMatchBuilder<E> {
+    fn default() -> Self {
+        FlowMatchBuilder::new()
+    }
+}
+
+impl<E> FlowMatchBuilder<E> {
+    /// Return a builder for an empty flow match, that matches every packet.
+    pub fn new() -> Self {
+        FlowMatchBuilder {
+            fields: Vec::new(),
+            implied: Vec::new(),
+            registers: Vec::new(),
+            experimenter: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Add a flow match field, and the fields that can be deduced from its prerequisites.
+    pub fn field<F: FlowMatchFieldRepr + Into<FlowMatchField>>(mut self, field: F) -> Self {
+        for implied in implied_fields(F::code()) {
+            if self.implied.iter().all(|&(code, _)| code != implied.0) {
+                self.implied.push(implied);
+            }
+        }
+        self.fields.push((F::code(), field.into()));
+        self
+    }
+
+    /// Add a flow match field with the given mask, and the fields that can be deduced from its
+    /// prerequisites.
+    pub fn masked<F>(self, mut field: F, mask: F::Mask) -> Self
+    where
+        F: FlowMatchFieldRepr + FlowMatchFieldMaskedRepr + Into<FlowMatchField>,
+    {
+        field.set_mask(mask);
+        self.field(field)
+    }
+
+    /// Add a packet register, with an optional mask.
+    pub fn register(mut self, field: u8, value: u64, mask: Option<u64>) -> Self {
+        self.registers.push(PacketRegisters { field, value, mask });
+        self
+    }
+
+    /// Add the 32 bits register `index` (see
+    /// [`PacketRegisters::from_reg32()`](struct.PacketRegisters.html#method.from_reg32)), with an
+    /// optional mask. The two halves of a 64 bits register are merged in a single field.
+    pub fn reg32(mut self, index: u8, value: u32, mask: Option<u32>) -> Self {
+        let half = PacketRegisters::from_reg32(index, value, mask);
+        let half_mask = half.mask.unwrap_or(!0);
+        match self.registers.iter_mut().find(|r| r.field == half.field) {
+            Some(register) => {
+                let mask = register.mask.unwrap_or(!0);
+                register.value = (register.value & !half_mask) | half.value;
+                register.mask = match mask | half_mask {
+                    mask if mask == !0 => None,
+                    mask => Some(mask),
+                };
+            }
+            None => self.registers.push(half),
+        }
+        self
+    }
+
+    /// Add an experimenter field.
+    pub fn experimenter(mut self, field: E) -> Self {
+        self.experimenter.push(field);
+        self
+    }
+
+    fn bad_value(mut self, field: u8) -> Self {
+        if self.error.is_none() {
+            self.error = Some(ValidationError {
+                kind: ValidationErrorKind::BadValue,
+                class: CLASS_OPEN_FLOW_BASIC,
+                field,
+            });
+        }
+        self
+    }
+
+    pub fn in_port(self, port: u32) -> Self {
+        self.field(InPort::new(port))
+    }
+
+    pub fn in_phy_port(self, port: u32) -> Self {
+        self.field(InPhysicalPort::new(port))
+    }
+
+    pub fn metadata(self, value: u64) -> Self {
+        self.field(Metadata::new(value, None))
+    }
+
+    pub fn metadata_masked(self, value: u64, mask: u64) -> Self {
+        self.field(Metadata::new(value, Some(mask)))
+    }
+
+    pub fn eth_dst(self, address: EthernetAddress) -> Self {
+        self.field(EthernetDestination::new(address, None))
+    }
+
+    pub fn eth_dst_masked(self, address: EthernetAddress, mask: EthernetAddress) -> Self {
+        self.field(EthernetDestination::new(address, Some(mask)))
+    }
+
+    pub fn eth_src(self, address: EthernetAddress) -> Self {
+        self.field(EthernetSource::new(address, None))
+    }
+
+    pub fn eth_src_masked(self, address: EthernetAddress, mask: EthernetAddress) -> Self {
+        self.field(EthernetSource::new(address, Some(mask)))
+    }
+
+    pub fn eth_type(self, eth_type: EthernetProtocol) -> Self {
+        self.field(EthernetType::new(eth_type))
+    }
+
+    /// Match packets with a VLAN header with the given VLAN id.
+    pub fn vlan_vid(self, vid: u16) -> Self {
+        self.field(VlanId::new(VLAN_PRESENT | vid, None))
+    }
+
+    /// Match packets with a VLAN header, whatever their VLAN id.
+    pub fn vlan_present(self) -> Self {
+        self.field(VlanId::new(VLAN_PRESENT, Some(VLAN_PRESENT)))
+    }
+
+    /// Match packets without a VLAN header.
+    pub fn vlan_none(self) -> Self {
+        self.field(VlanId::new(0, None))
+    }
+
+    pub fn vlan_pcp(self, pcp: u8) -> Self {
+        self.field(VlanPriority::new(pcp))
+    }
+
+    pub fn ip_dscp(self, dscp: u8) -> Self {
+        self.field(IpDscp::new(dscp))
+    }
+
+    pub fn ip_ecn(self, ecn: u8) -> Self {
+        self.field(IpEcn::new(ecn))
+    }
+
+    pub fn ip_proto(self, proto: u8) -> Self {
+        self.field(IpProtocol::new(proto))
+    }
+
+    pub fn ipv4_src(self, address: Ipv4Address) -> Self {
+        self.field(Ipv4Source::new(address, None))
+    }
+
+    pub fn ipv4_src_masked(self, address: Ipv4Address, mask: Ipv4Address) -> Self {
+        self.field(Ipv4Source::new(address, Some(mask)))
+    }
+
+    /// Match the IPv4 source address against a prefix.
+    pub fn ipv4_src_prefix(self, address: Ipv4Address, prefix_len: u8) -> Self {
+        match ipv4_prefix(address, prefix_len) {
+            Some((address, mask)) => self.field(Ipv4Source::new(address, mask)),
+            None => self.bad_value(consts::IPV4_SOURCE),
+        }
+    }
+
+    /// Match the IPv4 source address against an address in the CIDR notation (`10.0.0.0/8`), or
+    /// with an explicit mask (`10.0.0.0/255.0.0.0`).
+    pub fn ipv4_src_cidr(self, cidr: &str) -> Self {
+        match parse_cidr(cidr, 4, ipv4_octets) {
+            Some((address, mask)) => self.field(Ipv4Source::new(
+                Ipv4Address::from_bytes(&address),
+                mask.map(|mask| Ipv4Address::from_bytes(&mask)),
+            )),
+            None => self.bad_value(consts::IPV4_SOURCE),
+        }
+    }
+
+    pub fn ipv4_dst(self, address: Ipv4Address) -> Self {
+        self.field(Ipv4Destination::new(address, None))
+    }
+
+    pub fn ipv4_dst_masked(self, address: Ipv4Address, mask: Ipv4Address) -> Self {
+        self.field(Ipv4Destination::new(address, Some(mask)))
+    }
+
+    /// Match the IPv4 destination address against a prefix.
+    pub fn ipv4_dst_prefix(self, address: Ipv4Address, prefix_len: u8) -> Self {
+        match ipv4_prefix(address, prefix_len) {
+            Some((address, mask)) => self.field(Ipv4Destination::new(address, mask)),
+            None => self.bad_value(consts::IPV4_DESTINATION),
+        }
+    }
+
+    /// Match the IPv4 destination address against an address in the CIDR notation
+    /// (`10.0.0.0/8`), or with an explicit mask (`10.0.0.0/255.0.0.0`).
+    pub fn ipv4_dst_cidr(self, cidr: &str) -> Self {
+        match parse_cidr(cidr, 4, ipv4_octets) {
+            Some((address, mask)) => self.field(Ipv4Destination::new(
+                Ipv4Address::from_bytes(&address),
+                mask.map(|mask| Ipv4Address::from_bytes(&mask)),
+            )),
+            None => self.bad_value(consts::IPV4_DESTINATION),
+        }
+    }
+
+    pub fn tcp_src(self, port: u16) -> Self {
+        self.field(TcpSource::new(port))
+    }
+
+    pub fn tcp_dst(self, port: u16) -> Self {
+        self.field(TcpDestination::new(port))
+    }
+
+    pub fn tcp_flags(self, flags: u16) -> Self {
+        self.field(TcpFlags::new(flags, None))
+    }
+
+    pub fn tcp_flags_masked(self, flags: u16, mask: u16) -> Self {
+        self.field(TcpFlags::new(flags, Some(mask)))
+    }
+
+    pub fn udp_src(self, port: u16) -> Self {
+        self.field(UdpSource::new(port))
+    }
+
+    pub fn udp_dst(self, port: u16) -> Self {
+        self.field(UdpDestination::new(port))
+    }
+
+    pub fn sctp_src(self, port: u16) -> Self {
+        self.field(SctpSource::new(port))
+    }
+
+    pub fn sctp_dst(self, port: u16) -> Self {
+        self.field(SctpDestination::new(port))
+    }
+
+    pub fn icmp_type(self, icmp_type: Icmpv4Message) -> Self {
+        self.field(IcmpType::new(icmp_type))
+    }
+
+    pub fn icmp_code(self, code: u8) -> Self {
+        self.field(IcmpCode::new(code))
+    }
+
+    pub fn arp_op(self, operation: ArpOperation) -> Self {
+        self.field(ArpOpCode::new(operation))
+    }
+
+    pub fn arp_spa(self, address: Ipv4Address) -> Self {
+        self.field(ArpSpa::new(address, None))
+    }
+
+    /// Match the ARP source IPv4 address against an address in the CIDR notation (`10.0.0.0/8`),
+    /// or with an explicit mask (`10.0.0.0/255.0.0.0`).
+    pub fn arp_spa_cidr(self, cidr: &str) -> Self {
+        match parse_cidr(cidr, 4, ipv4_octets) {
+            Some((address, mask)) => self.field(ArpSpa::new(
+                Ipv4Address::from_bytes(&address),
+                mask.map(|mask| Ipv4Address::from_bytes(&mask)),
+            )),
+            None => self.bad_value(consts::ARP_SPA),
+        }
+    }
+
+    pub fn arp_tpa(self, address: Ipv4Address) -> Self {
+        self.field(ArpTpa::new(address, None))
+    }
+
+    /// Match the ARP target IPv4 address against an address in the CIDR notation (`10.0.0.0/8`),
+    /// or with an explicit mask (`10.0.0.0/255.0.0.0`).
+    pub fn arp_tpa_cidr(self, cidr: &str) -> Self {
+        match parse_cidr(cidr, 4, ipv4_octets) {
+            Some((address, mask)) => self.field(ArpTpa::new(
+                Ipv4Address::from_bytes(&address),
+                mask.map(|mask| Ipv4Address::from_bytes(&mask)),
+            )),
+            None => self.bad_value(consts::ARP_TPA),
+        }
+    }
+
+    pub fn arp_sha(self, address: EthernetAddress) -> Self {
+        self.field(ArpSha::new(address, None))
+    }
+
+    pub fn arp_tha(self, address: EthernetAddress) -> Self {
+        self.field(ArpTha::new(address, None))
+    }
+
+    pub fn ipv6_src(self, address: [u8; 16]) -> Self {
+        self.field(Ipv6Source::new(address, None))
+    }
+
+    pub fn ipv6_src_masked(self, address: [u8; 16], mask: [u8; 16]) -> Self {
+        self.field(Ipv6Source::new(address, Some(mask)))
+    }
+
+    /// Match the IPv6 source address against an address in the CIDR notation (`2001:db8::/32`),
+    /// or with an explicit mask (`2001:db8::/ffff:ffff::`).
+    pub fn ipv6_src_cidr(self, cidr: &str) -> Self {
+        match parse_cidr(cidr, 16, ipv6_octets) {
+            Some((address, mask)) => self.field(Ipv6Source::new(
+                to_ipv6(&address),
+                mask.map(|mask| to_ipv6(&mask)),
+            )),
+            None => self.bad_value(consts::IPV6_SOURCE),
+        }
+    }
+
+    pub fn ipv6_dst(self, address: [u8; 16]) -> Self {
+        self.field(Ipv6Destination::new(address, None))
+    }
+
+    pub fn ipv6_dst_masked(self, address: [u8; 16], mask: [u8; 16]) -> Self {
+        self.field(Ipv6Destination::new(address, Some(mask)))
+    }
+
+    /// Match the IPv6 destination address against an address in the CIDR notation
+    /// (`2001:db8::/32`), or with an explicit mask (`2001:db8::/ffff:ffff::`).
+    pub fn ipv6_dst_cidr(self, cidr: &str) -> Self {
+        match parse_cidr(cidr, 16, ipv6_octets) {
+            Some((address, mask)) => self.field(Ipv6Destination::new(
+                to_ipv6(&address),
+                mask.map(|mask| to_ipv6(&mask)),
+            )),
+            None => self.bad_value(consts::IPV6_DESTINATION),
+        }
+    }
+
+    pub fn ipv6_flabel(self, label: u32) -> Self {
+        self.field(Ipv6FlowLabel::new(label, None))
+    }
+
+    pub fn ipv6_flabel_masked(self, label: u32, mask: u32) -> Self {
+        self.field(Ipv6FlowLabel::new(label, Some(mask)))
+    }
+
+    pub fn icmpv6_type(self, icmpv6_type: u8) -> Self {
+        self.field(Icmpv6Type::new(icmpv6_type))
+    }
+
+    pub fn icmpv6_code(self, code: u8) -> Self {
+        self.field(Icmpv6Code::new(code))
+    }
+
+    pub fn ipv6_nd_target(self, address: [u8; 16]) -> Self {
+        self.field(Ipv6NdTarget::new(address))
+    }
+
+    pub fn ipv6_nd_sll(self, address: EthernetAddress) -> Self {
+        self.field(Ipv6NdSll::new(address))
+    }
+
+    pub fn ipv6_nd_tll(self, address: EthernetAddress) -> Self {
+        self.field(Ipv6NdTll::new(address))
+    }
+
+    pub fn mpls_label(self, label: u32) -> Self {
+        self.field(MplsLabel::new(label))
+    }
+
+    pub fn mpls_tc(self, tc: u8) -> Self {
+        self.field(MplsTc::new(tc))
+    }
+
+    pub fn mpls_bos(self, bos: u8) -> Self {
+        self.field(MplsBos::new(bos))
+    }
+
+    pub fn pbb_isid(self, isid: u32) -> Self {
+        self.field(PbbIsid::new(isid, None))
+    }
+
+    pub fn pbb_uca(self, uca: bool) -> Self {
+        self.field(PbbUca::new(uca))
+    }
+
+    pub fn tunnel_id(self, id: u64) -> Self {
+        self.field(TunnelId::new(id, None))
+    }
+
+    pub fn tunnel_id_masked(self, id: u64, mask: u64) -> Self {
+        self.field(TunnelId::new(id, Some(mask)))
+    }
+
+    pub fn ipv6_exthdr(self, flags: u16) -> Self {
+        self.field(Ipv6ExtensionHeader::new(flags, None))
+    }
+
+    pub fn ipv6_exthdr_masked(self, flags: u16, mask: u16) -> Self {
+        self.field(Ipv6ExtensionHeader::new(flags, Some(mask)))
+    }
+
+    pub fn actset_output(self, port: PortNumber) -> Self {
+        self.field(ActionSetOutput::new(port))
+    }
+
+    pub fn packet_type(self, packet_type: u32) -> Self {
+        self.field(PacketType::new(packet_type))
+    }
+}
+
+impl<E: Repr> FlowMatchBuilder<E> {
+    /// Build the flow match. The flow match fields, explicit or deduced from the prerequisites,
+    /// are ordered so that every field comes after its prerequisites, as required by the
+    /// specification. Fields of the same rank keep their order, the deduced fields coming first.
+    /// They are followed by the packet registers and the experimenter fields.
+    ///
+    /// An error is returned if a value given to the builder was invalid, or if the flow match is
+    /// not [valid](type.FlowMatch.html#method.validate), for instance because a field was given
+    /// twice or because a prerequisite could not be deduced.
+    pub fn build(self) -> result::Result<FlowMatch<E>, ValidationError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let explicit = self.fields;
+        let mut fields: Vec<(u8, FlowMatchField)> = self.implied
+            .into_iter()
+            .filter(|&(code, _)| explicit.iter().all(|&(other, _)| other != code))
+            .collect();
+        fields.extend(explicit);
+        fields.sort_by_key(|&(code, _)| prerequisite_rank(code));
+
+        let flow_match = FlowMatch(
+            fields
+                .into_iter()
+                .map(|(_, field)| Oxm::FlowMatchField(field))
+                .chain(self.registers.into_iter().map(Oxm::PacketRegisters))
+                .chain(self.experimenter.into_iter().map(Oxm::Experimenter))
+                .collect(),
+        );
+        flow_match.validate()?;
+        Ok(flow_match)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    type Builder = FlowMatchBuilder;
+
+    fn error(kind: ValidationErrorKind, field: u8) -> ValidationError {
+        ValidationError {
+            kind,
+            class: CLASS_OPEN_FLOW_BASIC,
+            field,
+        }
+    }
+
+    #[test]
+    fn test_prerequisites() {
+        let repr = FlowMatch::<ExperimenterOxm>::builder()
+            .in_port(1)
+            .ipv4_src_cidr("10.0.0.0/8")
+            .tcp_dst(80)
+            .build()
+            .unwrap();
+        assert_eq!(
+            repr.0,
+            vec![
+                Oxm::FlowMatchField(FlowMatchField::EthernetType(EthernetType::new(
+                    EthernetProtocol::Ipv4,
+                ))),
+                Oxm::FlowMatchField(FlowMatchField::InPort(InPort::new(1))),
+                Oxm::FlowMatchField(FlowMatchField::IpProtocol(IpProtocol::new(6))),
+                Oxm::FlowMatchField(FlowMatchField::Ipv4Source(Ipv4Source::new(
+                    Ipv4Address([10, 0, 0, 0]),
+                    Some(Ipv4Address([255, 0, 0, 0])),
+                ))),
+                Oxm::FlowMatchField(FlowMatchField::TcpDestination(TcpDestination::new(80))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_explicit_prerequisites() {
+        let repr = Builder::new()
+            .tcp_dst(80)
+            .eth_type(EthernetProtocol::Ipv6)
+            .build()
+            .unwrap();
+        assert_eq!(
+            repr.0,
+            vec![
+                Oxm::FlowMatchField(FlowMatchField::EthernetType(EthernetType::new(
+                    EthernetProtocol::Ipv6,
+                ))),
+                Oxm::FlowMatchField(FlowMatchField::IpProtocol(IpProtocol::new(6))),
+                Oxm::FlowMatchField(FlowMatchField::TcpDestination(TcpDestination::new(80))),
+            ]
+        );
+
+        let repr = Builder::new().vlan_pcp(3).vlan_vid(100).build().unwrap();
+        assert_eq!(
+            repr.0,
+            vec![
+                Oxm::FlowMatchField(FlowMatchField::VlanId(VlanId::new(0x1064, None))),
+                Oxm::FlowMatchField(FlowMatchField::VlanPriority(VlanPriority::new(3))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ambiguous_prerequisites() {
+        assert_eq!(
+            Builder::new().tcp_dst(80).build(),
+            Err(error(ValidationErrorKind::BadPrerequisite, consts::IP_PROTOCOL))
+        );
+    }
+
+    #[test]
+    fn test_duplicate() {
+        assert_eq!(
+            Builder::new().in_port(1).in_port(2).build(),
+            Err(error(ValidationErrorKind::DuplicateField, consts::IN_PORT))
+        );
+    }
+
+    #[test]
+    fn test_cidr() {
+        let repr = Builder::new()
+            .ipv4_dst_cidr("192.168.1.7/255.255.255.0")
+            .build()
+            .unwrap();
+        assert_eq!(
+            repr.0[1],
+            Oxm::FlowMatchField(FlowMatchField::Ipv4Destination(Ipv4Destination::new(
+                Ipv4Address([192, 168, 1, 0]),
+                Some(Ipv4Address([255, 255, 255, 0])),
+            )))
+        );
+        let repr = Builder::new().ipv6_src_cidr("2001:db8::1/32").build().unwrap();
+        let mut address = [0; 16];
+        address[..4].copy_from_slice(&[0x20, 0x01, 0x0d, 0xb8]);
+        let mut mask = [0; 16];
+        mask[..4].copy_from_slice(&[0xff; 4]);
+        assert_eq!(
+            repr.0[1],
+            Oxm::FlowMatchField(FlowMatchField::Ipv6Source(Ipv6Source::new(
+                address,
+                Some(mask),
+            )))
+        );
+
+        let repr = Builder::new().ipv4_src_cidr("10.0.0.1/32").build().unwrap();
+        assert_eq!(
+            repr.0[1],
+            Oxm::FlowMatchField(FlowMatchField::Ipv4Source(Ipv4Source::new(
+                Ipv4Address([10, 0, 0, 1]),
+                None,
+            )))
+        );
+
+        let repr = Builder::new()
+            .ipv4_src_prefix(Ipv4Address([172, 17, 5, 1]), 12)
+            .build()
+            .unwrap();
+        assert_eq!(
+            repr.0[1],
+            Oxm::FlowMatchField(FlowMatchField::Ipv4Source(Ipv4Source::new(
+                Ipv4Address([172, 16, 0, 0]),
+                Some(Ipv4Address([255, 240, 0, 0])),
+            )))
+        );
+    }
+
+    #[test]
+    fn test_bad_cidr() {
+        assert_eq!(
+            Builder::new().ipv4_src_cidr("10.0.0.0/33").build(),
+            Err(error(ValidationErrorKind::BadValue, consts::IPV4_SOURCE))
+        );
+        assert_eq!(
+            Builder::new().ipv6_dst_cidr("10.0.0.0/8").build(),
+            Err(error(ValidationErrorKind::BadValue, consts::IPV6_DESTINATION))
+        );
+        assert_eq!(
+            Builder::new()
+                .ipv4_dst_prefix(Ipv4Address([10, 0, 0, 0]), 40)
+                .build(),
+            Err(error(ValidationErrorKind::BadValue, consts::IPV4_DESTINATION))
+        );
+    }
+
+    #[test]
+    fn test_generic_fields() {
+        let repr = Builder::new()
+            .field(EthernetType::new(EthernetProtocol::Ipv4))
+            .masked(
+                Ipv4Destination::new(Ipv4Address([10, 0, 0, 0]), None),
+                Ipv4Address([255, 0, 0, 0]),
+            )
+            .register(2, 5, None)
+            .build()
+            .unwrap();
+        assert_eq!(
+            repr.0,
+            vec![
+                Oxm::FlowMatchField(FlowMatchField::EthernetType(EthernetType::new(
+                    EthernetProtocol::Ipv4,
+                ))),
+                Oxm::FlowMatchField(FlowMatchField::Ipv4Destination(Ipv4Destination::new(
+                    Ipv4Address([10, 0, 0, 0]),
+                    Some(Ipv4Address([255, 0, 0, 0])),
+                ))),
+                Oxm::PacketRegisters(PacketRegisters {
+                    field: 2,
+                    value: 5,
+                    mask: None,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reg32() {
+        let repr = Builder::new().reg32(1, 7, Some(0xff)).build().unwrap();
+        assert_eq!(
+            repr.0,
+            vec![Oxm::PacketRegisters(PacketRegisters::new(0, 7, Some(0xff)))]
+        );
+
+        let repr = Builder::new()
+            .reg32(1, 7, None)
+            .reg32(3, 1, None)
+            .reg32(0, 0x2a, None)
+            .build()
+            .unwrap();
+        assert_eq!(
+            repr.0,
+            vec![
+                Oxm::PacketRegisters(PacketRegisters::new(0, 0x2a_0000_0007, None)),
+                Oxm::PacketRegisters(PacketRegisters::new(1, 1, Some(0xffff_ffff))),
+            ]
+        );
+    }
+}
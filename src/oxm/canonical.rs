@@ -0,0 +1,162 @@
+//! Canonical form of flow matches.
+//!
+//! Switches do not necessarily send back flow matches exactly as they received them, for instance
+//! in flow stats replies. They usually canonicalize them: the bits of the values that are outside
+//! of the masks are cleared, masks that are all ones are dropped, fields that are fully wildcarded
+//! are dropped, and the fields are sorted. Two flow matches are considered equal if their
+//! canonical forms are equal.
+
+use core::hash::{Hash, Hasher};
+use core::mem;
+
+use super::algebra::Constraint;
+use super::{FlowMatch, FlowMatchField, Oxm, CLASS_OPEN_FLOW_BASIC};
+
+/// Return the canonical form of the flow match fields and packet registers of the given fields,
+/// sorted by class and field.
+fn constraints<E>(oxms: &[Oxm<E>]) -> Vec<Constraint> {
+    let mut constraints: Vec<Constraint> = oxms.iter()
+        .filter_map(Constraint::from_oxm)
+        .filter(|constraint| !constraint.is_wildcard())
+        .collect();
+    constraints.sort_by_key(|constraint| (constraint.class, constraint.field));
+    constraints
+}
+
+/// Return the experimenter fields and the unknown fields, that cannot be put in canonical form.
+fn opaque<E>(oxms: &[Oxm<E>]) -> Vec<&Oxm<E>> {
+    oxms.iter()
+        .filter(|oxm| Constraint::from_oxm(oxm).is_none())
+        .collect()
+}
+
+impl<E> FlowMatch<E> {
+    /// Put this flow match in canonical form:
+    ///
+    /// - the bits of the values that are outside of the masks are cleared,
+    /// - masks that cover all the bits of the field are dropped (`0x1fff` is enough for
+    ///   `OXM_OF_VLAN_VID`),
+    /// - fields with a mask that is all zeros are dropped,
+    /// - flow match fields and packet registers are sorted by `oxm_class` and `oxm_field`. The
+    ///   relative order of duplicate fields is preserved.
+    /// - experimenter fields and unknown fields, that cannot be interpreted, come last, in their
+    ///   original order.
+    pub fn normalize(&mut self) {
+        let oxms = mem::take(&mut self.0);
+        let mut normalized: Vec<Oxm<E>> = constraints(&oxms)
+            .iter()
+            .map(Constraint::to_oxm)
+            .collect();
+        normalized.extend(
+            oxms.into_iter()
+                .filter(|oxm| Constraint::from_oxm(oxm).is_none()),
+        );
+        self.0 = normalized;
+    }
+}
+
+/// Two flow matches are equal if their [canonical forms](#method.normalize) are equal.
+impl<E: PartialEq> PartialEq for FlowMatch<E> {
+    fn eq(&self, other: &Self) -> bool {
+        constraints(&self.0) == constraints(&other.0)
+            && opaque(&self.0) == opaque(&other.0)
+    }
+}
+
+impl<E: Eq> Eq for FlowMatch<E> {}
+
+/// The hash of a flow match is the hash of its [canonical form](#method.normalize).
+impl<E: Hash> Hash for FlowMatch<E> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        constraints(&self.0).hash(state);
+        for oxm in opaque(&self.0) {
+            match *oxm {
+                Oxm::Experimenter(ref repr) => repr.hash(state),
+                Oxm::Unknown {
+                    class,
+                    field,
+                    has_mask,
+                    ref value,
+                } => (class, field, has_mask, value).hash(state),
+                Oxm::FlowMatchField(FlowMatchField::Unknown {
+                    field,
+                    has_mask,
+                    ref value,
+                }) => (CLASS_OPEN_FLOW_BASIC, field, has_mask, value).hash(state),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    use oxm::*;
+    use smoltcp::wire::Ipv4Address;
+
+    fn hash(flow_match: &FlowMatch) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        flow_match.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn sent() -> FlowMatch {
+        FlowMatch(vec![
+            Oxm::Experimenter(ExperimenterOxm::NxNshSi(1)),
+            Oxm::FlowMatchField(FlowMatchField::Ipv4Source(Ipv4Source::new(
+                Ipv4Address([10, 0, 0, 1]),
+                Some(Ipv4Address([255, 255, 255, 0])),
+            ))),
+            Oxm::FlowMatchField(FlowMatchField::Metadata(Metadata::new(0x1234, Some(0)))),
+            Oxm::PacketRegisters(PacketRegisters {
+                field: 1,
+                value: 7,
+                mask: Some(!0),
+            }),
+            Oxm::FlowMatchField(FlowMatchField::InPort(InPort::new(3))),
+            Oxm::FlowMatchField(FlowMatchField::VlanId(VlanId::new(0x1064, Some(0xffff)))),
+        ])
+    }
+
+    fn canonical() -> FlowMatch {
+        FlowMatch(vec![
+            Oxm::FlowMatchField(FlowMatchField::InPort(InPort::new(3))),
+            Oxm::FlowMatchField(FlowMatchField::VlanId(VlanId::new(0x1064, None))),
+            Oxm::FlowMatchField(FlowMatchField::Ipv4Source(Ipv4Source::new(
+                Ipv4Address([10, 0, 0, 0]),
+                Some(Ipv4Address([255, 255, 255, 0])),
+            ))),
+            Oxm::PacketRegisters(PacketRegisters {
+                field: 1,
+                value: 7,
+                mask: None,
+            }),
+            Oxm::Experimenter(ExperimenterOxm::NxNshSi(1)),
+        ])
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut repr = sent();
+        repr.normalize();
+        let canonical = canonical();
+        assert_eq!(repr.0, canonical.0);
+    }
+
+    #[test]
+    fn test_eq_and_hash() {
+        assert_eq!(sent(), canonical());
+        assert_eq!(hash(&sent()), hash(&canonical()));
+
+        let mut other = canonical();
+        other.0[0] = Oxm::FlowMatchField(FlowMatchField::InPort(InPort::new(4)));
+        assert_ne!(sent(), other);
+
+        let mut other = canonical();
+        other.0[4] = Oxm::Experimenter(ExperimenterOxm::NxNshSi(2));
+        assert_ne!(sent(), other);
+    }
+}
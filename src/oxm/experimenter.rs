@@ -0,0 +1,341 @@
+//! Built-in representation of the experimenter OXM fields.
+//!
+//! An experimenter OXM field has an `oxm_class` equal to
+//! [`CLASS_EXPERIMENTER`](../constant.CLASS_EXPERIMENTER.html), and its value starts with a 32
+//! bits experimenter ID:
+//!
+//! ```no_rust
+//! 0                       16             23  24             32
+//! +------------------------+-------------+----+-------------+
+//! |     oxm_class=0xffff   |  oxm_field  | HM |  oxm_length |
+//! +------------------------+-------------+----+-------------+
+//! |                      experimenter                       |
+//! +---------------------------------------------------------+
+//! |                     value and/or mask                   |
+//! |                      (variable size)                    |
+//! +---------------------------------------------------------+
+//! ```
+//!
+//! [`ExperimenterOxm`](enum.ExperimenterOxm.html) decodes the fields defined by the ONF for
+//! OpenFlow 1.3 switches, and some of the Open vSwitch ones. The others are kept as opaque bytes.
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use {Error, Repr, Result};
+use super::{Packet, CLASS_EXPERIMENTER, OXM_HEADER_LEN};
+
+/// Experimenter ID of the Open Networking Foundation.
+pub const ONF_EXPERIMENTER_ID: u32 = 0x4f4e_4600;
+/// Experimenter ID of Nicira, used by Open vSwitch.
+pub const NX_EXPERIMENTER_ID: u32 = 0x0000_2320;
+
+/// `oxm_field` of `ONFOXM_ET_TCP_FLAGS` (ONF extension 109).
+pub const ONF_TCP_FLAGS: u8 = 42;
+/// `oxm_field` of `ONFOXM_ET_PBB_UCA` (ONF extension 256). This extension adds a 16 bits
+/// `exp_type` after the experimenter ID, equal to
+/// [`ONF_PBB_UCA_EXP_TYPE`](constant.ONF_PBB_UCA_EXP_TYPE.html).
+pub const ONF_PBB_UCA: u8 = 0;
+/// `exp_type` of `ONFOXM_ET_PBB_UCA`.
+pub const ONF_PBB_UCA_EXP_TYPE: u16 = 2560;
+/// `oxm_field` of `NXOXM_ET_DP_HASH`.
+pub const NX_DP_HASH: u8 = 0;
+/// `oxm_field` of `NXOXM_NSH_SPI`.
+pub const NX_NSH_SPI: u8 = 4;
+/// `oxm_field` of `NXOXM_NSH_SI`.
+pub const NX_NSH_SI: u8 = 5;
+
+const EXPERIMENTER_LEN: usize = 4;
+
+/// An experimenter OXM field.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum ExperimenterOxm {
+    /// `ONFOXM_ET_TCP_FLAGS`: the TCP flags, for OpenFlow 1.3 switches. Same as
+    /// [`TcpFlags`](struct.TcpFlags.html).
+    OnfTcpFlags { value: u16, mask: Option<u16> },
+    /// `ONFOXM_ET_PBB_UCA`: the PBB UCA header field, for OpenFlow 1.3 switches. Same as
+    /// [`PbbUca`](struct.PbbUca.html).
+    OnfPbbUca(u8),
+    /// `NXOXM_ET_DP_HASH`: a hash of the packet computed by the datapath.
+    NxDpHash { value: u32, mask: Option<u32> },
+    /// `NXOXM_NSH_SPI`: the service path identifier of the NSH header (24 bits).
+    NxNshSpi(u32),
+    /// `NXOXM_NSH_SI`: the service index of the NSH header.
+    NxNshSi(u8),
+    /// An experimenter field unknown to this library. `value` contains what follows the
+    /// experimenter ID, including the mask if `has_mask` is set.
+    Unknown {
+        experimenter: u32,
+        field: u8,
+        has_mask: bool,
+        value: Vec<u8>,
+    },
+}
+
+impl ExperimenterOxm {
+    /// Return the experimenter ID of this field.
+    pub fn experimenter(&self) -> u32 {
+        match *self {
+            ExperimenterOxm::OnfTcpFlags { .. } | ExperimenterOxm::OnfPbbUca(_) => {
+                ONF_EXPERIMENTER_ID
+            }
+            ExperimenterOxm::NxDpHash { .. }
+            | ExperimenterOxm::NxNshSpi(_)
+            | ExperimenterOxm::NxNshSi(_) => NX_EXPERIMENTER_ID,
+            ExperimenterOxm::Unknown { experimenter, .. } => experimenter,
+        }
+    }
+
+    /// Return the `oxm_field` of this field.
+    pub fn field(&self) -> u8 {
+        match *self {
+            ExperimenterOxm::OnfTcpFlags { .. } => ONF_TCP_FLAGS,
+            ExperimenterOxm::OnfPbbUca(_) => ONF_PBB_UCA,
+            ExperimenterOxm::NxDpHash { .. } => NX_DP_HASH,
+            ExperimenterOxm::NxNshSpi(_) => NX_NSH_SPI,
+            ExperimenterOxm::NxNshSi(_) => NX_NSH_SI,
+            ExperimenterOxm::Unknown { field, .. } => field,
+        }
+    }
+
+    fn has_mask(&self) -> bool {
+        match *self {
+            ExperimenterOxm::OnfTcpFlags { mask, .. } => mask.is_some(),
+            ExperimenterOxm::NxDpHash { mask, .. } => mask.is_some(),
+            ExperimenterOxm::Unknown { has_mask, .. } => has_mask,
+            _ => false,
+        }
+    }
+
+    /// Return what follows the experimenter ID.
+    fn payload(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        match *self {
+            ExperimenterOxm::OnfTcpFlags { value, mask } => {
+                payload.resize(if mask.is_some() { 4 } else { 2 }, 0);
+                NetworkEndian::write_u16(&mut payload[0..2], value);
+                if let Some(mask) = mask {
+                    NetworkEndian::write_u16(&mut payload[2..4], mask);
+                }
+            }
+            ExperimenterOxm::OnfPbbUca(value) => {
+                payload.resize(2, 0);
+                NetworkEndian::write_u16(&mut payload, ONF_PBB_UCA_EXP_TYPE);
+                payload.push(value);
+            }
+            ExperimenterOxm::NxDpHash { value, mask } => {
+                payload.resize(if mask.is_some() { 8 } else { 4 }, 0);
+                NetworkEndian::write_u32(&mut payload[0..4], value);
+                if let Some(mask) = mask {
+                    NetworkEndian::write_u32(&mut payload[4..8], mask);
+                }
+            }
+            ExperimenterOxm::NxNshSpi(value) => {
+                payload.resize(4, 0);
+                NetworkEndian::write_u32(&mut payload, value);
+            }
+            ExperimenterOxm::NxNshSi(value) => payload.push(value),
+            ExperimenterOxm::Unknown { ref value, .. } => payload.extend_from_slice(value),
+        }
+        payload
+    }
+
+    /// Decode a known field from its payload, or return `None` if the field is unknown or if the
+    /// payload does not have the expected format.
+    fn decode(experimenter: u32, field: u8, has_mask: bool, payload: &[u8]) -> Option<Self> {
+        let masked_u16 = || match (has_mask, payload.len()) {
+            (false, 2) => Some((NetworkEndian::read_u16(payload), None)),
+            (true, 4) => Some((
+                NetworkEndian::read_u16(&payload[0..2]),
+                Some(NetworkEndian::read_u16(&payload[2..4])),
+            )),
+            _ => None,
+        };
+        let masked_u32 = || match (has_mask, payload.len()) {
+            (false, 4) => Some((NetworkEndian::read_u32(payload), None)),
+            (true, 8) => Some((
+                NetworkEndian::read_u32(&payload[0..4]),
+                Some(NetworkEndian::read_u32(&payload[4..8])),
+            )),
+            _ => None,
+        };
+        match (experimenter, field) {
+            (ONF_EXPERIMENTER_ID, ONF_TCP_FLAGS) => {
+                masked_u16().map(|(value, mask)| ExperimenterOxm::OnfTcpFlags { value, mask })
+            }
+            (ONF_EXPERIMENTER_ID, ONF_PBB_UCA)
+                if !has_mask && payload.len() == 3
+                    && NetworkEndian::read_u16(&payload[0..2]) == ONF_PBB_UCA_EXP_TYPE =>
+            {
+                Some(ExperimenterOxm::OnfPbbUca(payload[2]))
+            }
+            (NX_EXPERIMENTER_ID, NX_DP_HASH) => {
+                masked_u32().map(|(value, mask)| ExperimenterOxm::NxDpHash { value, mask })
+            }
+            (NX_EXPERIMENTER_ID, NX_NSH_SPI) if !has_mask && payload.len() == 4 => {
+                Some(ExperimenterOxm::NxNshSpi(NetworkEndian::read_u32(payload)))
+            }
+            (NX_EXPERIMENTER_ID, NX_NSH_SI) if !has_mask && payload.len() == 1 => {
+                Some(ExperimenterOxm::NxNshSi(payload[0]))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Repr for ExperimenterOxm {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        if packet.class() != CLASS_EXPERIMENTER {
+            return Err(Error::BadOxmClass);
+        }
+        // `packet.value()` extends to the end of the buffer, which may hold other fields.
+        let value = &packet.value()[..packet.length() as usize];
+        if value.len() < EXPERIMENTER_LEN {
+            return Err(Error::Malformed);
+        }
+        let experimenter = NetworkEndian::read_u32(&value[..EXPERIMENTER_LEN]);
+        let payload = &value[EXPERIMENTER_LEN..];
+        let (field, has_mask) = (packet.field(), packet.has_mask());
+        Ok(
+            ExperimenterOxm::decode(experimenter, field, has_mask, payload).unwrap_or_else(|| {
+                ExperimenterOxm::Unknown {
+                    experimenter,
+                    field,
+                    has_mask,
+                    value: payload.to_vec(),
+                }
+            }),
+        )
+    }
+
+    fn buffer_len(&self) -> usize {
+        OXM_HEADER_LEN + EXPERIMENTER_LEN + self.payload().len()
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        let payload = self.payload();
+        if EXPERIMENTER_LEN + payload.len() > 0xff {
+            return Err(Error::Malformed);
+        }
+        if self.buffer_len() > buffer.len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(&mut buffer[..self.buffer_len()]);
+        packet.set_class(CLASS_EXPERIMENTER);
+        packet.set_field(self.field());
+        packet.set_length((EXPERIMENTER_LEN + payload.len()) as u8);
+        if self.has_mask() {
+            packet.set_mask();
+        } else {
+            packet.unset_mask();
+        }
+        let value = packet.value_mut();
+        NetworkEndian::write_u32(&mut value[..EXPERIMENTER_LEN], self.experimenter());
+        value[EXPERIMENTER_LEN..].copy_from_slice(&payload);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check(bytes: &[u8], repr: ExperimenterOxm) {
+        assert_eq!(ExperimenterOxm::parse(bytes), Ok(repr.clone()));
+        let mut buffer = vec![0; repr.buffer_len()];
+        repr.emit(&mut buffer).unwrap();
+        assert_eq!(&buffer[..], bytes);
+    }
+
+    #[test]
+    fn test_onf() {
+        check(
+            &[0xff, 0xff, 0x55, 0x08, 0x4f, 0x4e, 0x46, 0x00, 0x00, 0x12, 0x0f, 0xff],
+            ExperimenterOxm::OnfTcpFlags {
+                value: 0x12,
+                mask: Some(0x0fff),
+            },
+        );
+        check(
+            &[0xff, 0xff, 0x00, 0x07, 0x4f, 0x4e, 0x46, 0x00, 0x0a, 0x00, 0x01],
+            ExperimenterOxm::OnfPbbUca(1),
+        );
+    }
+
+    #[test]
+    fn test_nicira() {
+        check(
+            &[0xff, 0xff, 0x00, 0x08, 0x00, 0x00, 0x23, 0x20, 0x12, 0x34, 0x56, 0x78],
+            ExperimenterOxm::NxDpHash {
+                value: 0x1234_5678,
+                mask: None,
+            },
+        );
+        check(
+            &[0xff, 0xff, 0x0a, 0x05, 0x00, 0x00, 0x23, 0x20, 0xfe],
+            ExperimenterOxm::NxNshSi(0xfe),
+        );
+    }
+
+    #[test]
+    fn test_unknown() {
+        check(
+            &[0xff, 0xff, 0x07, 0x06, 0x00, 0x00, 0x00, 0x2a, 0xab, 0xcd],
+            ExperimenterOxm::Unknown {
+                experimenter: 42,
+                field: 3,
+                has_mask: true,
+                value: vec![0xab, 0xcd],
+            },
+        );
+        // a known field with an unexpected length is kept as is
+        check(
+            &[0xff, 0xff, 0x0a, 0x06, 0x00, 0x00, 0x23, 0x20, 0xfe, 0xff],
+            ExperimenterOxm::Unknown {
+                experimenter: NX_EXPERIMENTER_ID,
+                field: NX_NSH_SI,
+                has_mask: false,
+                value: vec![0xfe, 0xff],
+            },
+        );
+    }
+
+    #[test]
+    fn test_flow_match() {
+        use oxm::{FlowMatch, FlowMatchField, InPort, Oxm};
+
+        let bytes = [
+            0x00, 0x01, // match type = OXM
+            0x00, 0x18, // length = 24
+            0xff, 0xff, 0x00, 0x08, 0x00, 0x00, 0x23, 0x20, 0x12, 0x34, 0x56, 0x78, // dp_hash
+            0x80, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01, // in_port = 1
+        ];
+        let flow_match: FlowMatch = FlowMatch::parse(&bytes).unwrap();
+        assert_eq!(
+            flow_match.0,
+            vec![
+                Oxm::Experimenter(ExperimenterOxm::NxDpHash {
+                    value: 0x1234_5678,
+                    mask: None,
+                }),
+                Oxm::FlowMatchField(FlowMatchField::InPort(InPort::new(1))),
+            ]
+        );
+        assert_eq!(flow_match.buffer_len(), bytes.len());
+        let mut buffer = vec![0; flow_match.buffer_len()];
+        flow_match.emit(&mut buffer).unwrap();
+        assert_eq!(&buffer[..], &bytes[..]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            ExperimenterOxm::parse(&[0xff, 0xff, 0x00, 0x02, 0x00, 0x00]),
+            Err(Error::Malformed)
+        );
+        assert_eq!(
+            ExperimenterOxm::parse(&[0x80, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01]),
+            Err(Error::BadOxmClass)
+        );
+    }
+}
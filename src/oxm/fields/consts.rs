@@ -1,3 +1,5 @@
+//! Values of the `oxm_field` header field of the flow match fields.
+
 /// Switch input port
 pub const IN_PORT: u8 = 0;
 /// Switch physical input port
@@ -86,3 +88,56 @@ pub const TCP_FLAGS: u8 = 42;
 pub const ACTION_SET_OUTPUT: u8 = 43;
 /// Packet type value
 pub const PACKET_TYPE: u8 = 44;
+
+/// Return the name of the given flow match field, as it appears in the OpenFlow specification
+/// (for instance `OXM_OF_TCP_SRC`).
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub fn name(field: u8) -> Option<&'static str> {
+    Some(match field {
+        IN_PORT               => "OXM_OF_IN_PORT",
+        IN_PHYSICAL_PORT      => "OXM_OF_IN_PHY_PORT",
+        METADATA              => "OXM_OF_METADATA",
+        ETHERNET_DESTINATION  => "OXM_OF_ETH_DST",
+        ETHERNET_SOURCE       => "OXM_OF_ETH_SRC",
+        ETHERNET_TYPE         => "OXM_OF_ETH_TYPE",
+        VLAN_ID               => "OXM_OF_VLAN_VID",
+        VLAN_PRIORITY         => "OXM_OF_VLAN_PCP",
+        IP_DSCP               => "OXM_OF_IP_DSCP",
+        IP_ECN                => "OXM_OF_IP_ECN",
+        IP_PROTOCOL           => "OXM_OF_IP_PROTO",
+        IPV4_SOURCE           => "OXM_OF_IPV4_SRC",
+        IPV4_DESTINATION      => "OXM_OF_IPV4_DST",
+        TCP_SOURCE            => "OXM_OF_TCP_SRC",
+        TCP_DESTINATION       => "OXM_OF_TCP_DST",
+        UDP_SOURCE            => "OXM_OF_UDP_SRC",
+        UDP_DESTINATION       => "OXM_OF_UDP_DST",
+        SCTP_SOURCE           => "OXM_OF_SCTP_SRC",
+        SCTP_DESTINATION      => "OXM_OF_SCTP_DST",
+        ICMP_TYPE             => "OXM_OF_ICMPV4_TYPE",
+        ICMP_CODE             => "OXM_OF_ICMPV4_CODE",
+        ARP_OP_CODE           => "OXM_OF_ARP_OP",
+        ARP_SPA               => "OXM_OF_ARP_SPA",
+        ARP_TPA               => "OXM_OF_ARP_TPA",
+        ARP_SHA               => "OXM_OF_ARP_SHA",
+        ARP_THA               => "OXM_OF_ARP_THA",
+        IPV6_SOURCE           => "OXM_OF_IPV6_SRC",
+        IPV6_DESTINATION      => "OXM_OF_IPV6_DST",
+        IPV6_FLOW_LABEL       => "OXM_OF_IPV6_FLABEL",
+        ICMPV6_TYPE           => "OXM_OF_ICMPV6_TYPE",
+        ICMPV6_CODE           => "OXM_OF_ICMPV6_CODE",
+        IPV6_ND_TARGET        => "OXM_OF_IPV6_ND_TARGET",
+        IPV6_ND_SLL           => "OXM_OF_IPV6_ND_SLL",
+        IPV6_ND_TLL           => "OXM_OF_IPV6_ND_TLL",
+        MPLS_LABEL            => "OXM_OF_MPLS_LABEL",
+        MPLS_TC               => "OXM_OF_MPLS_TC",
+        MPLS_BOS              => "OXM_OF_MPLS_BOS",
+        PBB_ISID              => "OXM_OF_PBB_ISID",
+        TUNNEL_ID             => "OXM_OF_TUNNEL_ID",
+        IPV6_EXTENSION_HEADER => "OXM_OF_IPV6_EXTHDR",
+        PBB_UCA               => "OXM_OF_PBB_UCA",
+        TCP_FLAGS             => "OXM_OF_TCP_FLAGS",
+        ACTION_SET_OUTPUT     => "OXM_OF_ACTSET_OUTPUT",
+        PACKET_TYPE           => "OXM_OF_PACKET_TYPE",
+        _                     => return None,
+    })
+}
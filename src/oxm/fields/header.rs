@@ -1,5 +1,5 @@
 use byteorder::{ByteOrder, NetworkEndian};
-use smoltcp::wire::{EthernetAddress, EthernetProtocol, Icmpv4Message, Ipv4Address};
+use smoltcp::wire::{ArpOperation, EthernetAddress, EthernetProtocol, Icmpv4Message, Ipv4Address};
 
 use super::Packet;
 use super::{FlowMatchFieldMaskedRepr, FlowMatchFieldRepr};
@@ -49,9 +49,9 @@ impl FlowMatchFieldRepr for EthernetDestination {
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
-        buf.copy_from_slice(self.value.as_bytes());
+        buf[0..6].copy_from_slice(self.value.as_bytes());
         if let Some(mask) = self.mask {
-            buf[6..].as_mut().copy_from_slice(mask.as_bytes())
+            buf[6..12].copy_from_slice(mask.as_bytes())
         }
     }
 
@@ -126,9 +126,9 @@ impl FlowMatchFieldRepr for EthernetSource {
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
-        buf.copy_from_slice(self.value.as_bytes());
+        buf[0..6].copy_from_slice(self.value.as_bytes());
         if let Some(mask) = self.mask {
-            buf[6..].as_mut().copy_from_slice(mask.as_bytes())
+            buf[6..12].copy_from_slice(mask.as_bytes())
         }
     }
 
@@ -257,7 +257,11 @@ impl FlowMatchFieldRepr for VlanId {
     }
 
     fn value_len(&self) -> usize {
-        2
+        if self.mask.is_some() {
+            4
+        } else {
+            2
+        }
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
@@ -508,7 +512,11 @@ impl FlowMatchFieldRepr for Ipv4Source {
     }
 
     fn value_len(&self) -> usize {
-        4
+        if self.mask.is_some() {
+            8
+        } else {
+            4
+        }
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
@@ -579,7 +587,11 @@ impl FlowMatchFieldRepr for ArpSpa {
     }
 
     fn value_len(&self) -> usize {
-        4
+        if self.mask.is_some() {
+            8
+        } else {
+            4
+        }
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
@@ -643,7 +655,11 @@ impl FlowMatchFieldRepr for ArpTpa {
     }
 
     fn value_len(&self) -> usize {
-        4
+        if self.mask.is_some() {
+            8
+        } else {
+            4
+        }
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
@@ -804,7 +820,11 @@ impl FlowMatchFieldRepr for TcpFlags {
     }
 
     fn value_len(&self) -> usize {
-        2
+        if self.mask.is_some() {
+            4
+        } else {
+            2
+        }
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
@@ -1113,21 +1133,19 @@ impl FlowMatchFieldRepr for IcmpType {
 ///
 /// Masking: Not maskable
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct ArpOpCode(EthernetProtocol);
+pub struct ArpOpCode(ArpOperation);
 
 impl ArpOpCode {
-    pub fn new(value: EthernetProtocol) -> Self {
+    pub fn new(value: ArpOperation) -> Self {
         ArpOpCode(value)
     }
 }
 
 impl FlowMatchFieldRepr for ArpOpCode {
-    type Value = EthernetProtocol;
+    type Value = ArpOperation;
 
     fn parse<'a, T: AsRef<[u8]> + ?Sized>(packet: &Packet<&'a T>) -> Self {
-        ArpOpCode::new(EthernetProtocol::from(
-            NetworkEndian::read_u16(packet.value()),
-        ))
+        ArpOpCode::new(ArpOperation::from(NetworkEndian::read_u16(packet.value())))
     }
 
     fn value_len(&self) -> usize {
@@ -1188,7 +1206,11 @@ impl FlowMatchFieldRepr for Ipv4Destination {
     }
 
     fn value_len(&self) -> usize {
-        4
+        if self.mask.is_some() {
+            8
+        } else {
+            4
+        }
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
@@ -1267,7 +1289,7 @@ impl FlowMatchFieldRepr for ArpSha {
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
-        buf.copy_from_slice(self.value.as_bytes());
+        buf[0..6].copy_from_slice(self.value.as_bytes());
         if let Some(mask) = self.mask {
             buf[6..12].as_mut().copy_from_slice(mask.as_bytes())
         }
@@ -1337,7 +1359,7 @@ impl FlowMatchFieldRepr for ArpTha {
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
-        buf.copy_from_slice(self.value.as_bytes());
+        buf[0..6].copy_from_slice(self.value.as_bytes());
         if let Some(mask) = self.mask {
             buf[6..12].as_mut().copy_from_slice(mask.as_bytes())
         }
@@ -1383,7 +1405,7 @@ pub struct Ipv6Source {
 }
 
 impl Ipv6Source {
-    fn new(value: [u8; 16], mask: Option<[u8; 16]>) -> Self {
+    pub fn new(value: [u8; 16], mask: Option<[u8; 16]>) -> Self {
         Ipv6Source {
             value: value,
             mask: mask,
@@ -1420,9 +1442,9 @@ impl FlowMatchFieldRepr for Ipv6Source {
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
-        buf.copy_from_slice(&self.value[..]);
+        buf[0..16].copy_from_slice(&self.value[..]);
         if let Some(mask) = self.mask {
-            buf[16..].as_mut().copy_from_slice(&mask[..])
+            buf[16..32].copy_from_slice(&mask[..])
         }
     }
 
@@ -1458,7 +1480,7 @@ pub struct Ipv6Destination {
 }
 
 impl Ipv6Destination {
-    fn new(value: [u8; 16], mask: Option<[u8; 16]>) -> Self {
+    pub fn new(value: [u8; 16], mask: Option<[u8; 16]>) -> Self {
         Ipv6Destination {
             value: value,
             mask: mask,
@@ -1495,9 +1517,9 @@ impl FlowMatchFieldRepr for Ipv6Destination {
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
-        buf.copy_from_slice(&self.value[..]);
+        buf[0..16].copy_from_slice(&self.value[..]);
         if let Some(mask) = self.mask {
-            buf[16..].as_mut().copy_from_slice(&mask[..])
+            buf[16..32].copy_from_slice(&mask[..])
         }
     }
 
@@ -1563,7 +1585,11 @@ impl FlowMatchFieldRepr for Ipv6FlowLabel {
     }
 
     fn value_len(&self) -> usize {
-        4
+        if self.mask.is_some() {
+            8
+        } else {
+            4
+        }
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
@@ -1699,7 +1725,7 @@ impl FlowMatchFieldRepr for Icmpv6Type {
 pub struct Ipv6NdTarget([u8; 16]);
 
 impl Ipv6NdTarget {
-    fn new(value: [u8; 16]) -> Self {
+    pub fn new(value: [u8; 16]) -> Self {
         Ipv6NdTarget(value)
     }
 }
@@ -2048,7 +2074,11 @@ impl FlowMatchFieldRepr for PbbIsid {
     }
 
     fn value_len(&self) -> usize {
-        3
+        if self.mask.is_some() {
+            6
+        } else {
+            3
+        }
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
@@ -2124,7 +2154,11 @@ impl FlowMatchFieldRepr for Ipv6ExtensionHeader {
     }
 
     fn value_len(&self) -> usize {
-        2
+        if self.mask.is_some() {
+            4
+        } else {
+            2
+        }
     }
 
     fn emit_value(&self, buf: &mut [u8]) {
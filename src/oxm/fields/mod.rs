@@ -1,17 +1,26 @@
-mod consts;
+pub mod consts;
 pub mod header;
 pub mod pipeline;
 
 use {Error, Result};
-use super::{Packet, CLASS_OPEN_FLOW_BASIC, OXM_HEADER_LEN};
+use super::{emit_raw, Packet, CLASS_OPEN_FLOW_BASIC, OXM_HEADER_LEN};
 use self::header::*;
 use self::pipeline::*;
 
-trait FlowMatchFieldRepr {
+/// Ethernet type of Provider Backbone Bridging (802.1ah), the prerequisite of the `PBB_*` fields.
+pub const ETH_TYPE_PBB: u16 = 0x88e7;
+/// Bit of the `OXM_OF_VLAN_VID` field set when a VLAN header is present (`OFPVID_PRESENT`).
+pub const VLAN_PRESENT: u16 = 0x1000;
+
+/// A flow match field representation. Each flow match field (`InPort`, `Ipv4Source`, _etc._)
+/// implements this trait.
+pub trait FlowMatchFieldRepr {
+    /// Type of the value of this flow match field.
     type Value;
 
     /// Parse an OXM field buffer and return the corresponding flow match field representation
     fn parse<'a, T: AsRef<[u8]> + ?Sized>(packet: &Packet<&'a T>) -> Self;
+    /// Return the length of the value of this flow match field, including the mask if any.
     fn value_len(&self) -> usize;
     /// Write this flow match field representation value in the given buffer.
     fn emit_value(&self, buf: &mut [u8]);
@@ -26,7 +35,7 @@ trait FlowMatchFieldRepr {
         if self.buffer_len() > buf.len() {
             return Err(Error::Exhausted);
         }
-        let mut packet = Packet::new(buf);
+        let mut packet = Packet::new(&mut buf[..self.buffer_len()]);
         packet.set_class(CLASS_OPEN_FLOW_BASIC);
         packet.set_field(Self::code());
         packet.set_length(self.value_len() as u8);
@@ -45,7 +54,9 @@ trait FlowMatchFieldRepr {
     }
 }
 
-trait FlowMatchFieldMaskedRepr {
+/// A flow match field representation that can be masked.
+pub trait FlowMatchFieldMaskedRepr {
+    /// Type of the mask of this flow match field.
     type Mask;
     /// Set the mask
     fn set_mask(&mut self, mask: Self::Mask);
@@ -53,8 +64,116 @@ trait FlowMatchFieldMaskedRepr {
     fn unset_mask(&mut self);
 }
 
+/// Return the length of the value of the given flow match field (without mask), and whether the
+/// field can be masked.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub fn value_len(field: u8) -> Option<(usize, bool)> {
+    Some(match field {
+        consts::IN_PORT               => (4, false),
+        consts::IN_PHYSICAL_PORT      => (4, false),
+        consts::METADATA              => (8, true),
+        consts::ETHERNET_DESTINATION  => (6, true),
+        consts::ETHERNET_SOURCE       => (6, true),
+        consts::ETHERNET_TYPE         => (2, false),
+        consts::VLAN_ID               => (2, true),
+        consts::VLAN_PRIORITY         => (1, false),
+        consts::IP_DSCP               => (1, false),
+        consts::IP_ECN                => (1, false),
+        consts::IP_PROTOCOL           => (1, false),
+        consts::IPV4_SOURCE           => (4, true),
+        consts::IPV4_DESTINATION      => (4, true),
+        consts::TCP_SOURCE            => (2, false),
+        consts::TCP_DESTINATION       => (2, false),
+        consts::UDP_SOURCE            => (2, false),
+        consts::UDP_DESTINATION       => (2, false),
+        consts::SCTP_SOURCE           => (2, false),
+        consts::SCTP_DESTINATION      => (2, false),
+        consts::ICMP_TYPE             => (1, false),
+        consts::ICMP_CODE             => (1, false),
+        consts::ARP_OP_CODE           => (2, false),
+        consts::ARP_SPA               => (4, true),
+        consts::ARP_TPA               => (4, true),
+        consts::ARP_SHA               => (6, true),
+        consts::ARP_THA               => (6, true),
+        consts::IPV6_SOURCE           => (16, true),
+        consts::IPV6_DESTINATION      => (16, true),
+        consts::IPV6_FLOW_LABEL       => (4, true),
+        consts::ICMPV6_TYPE           => (1, false),
+        consts::ICMPV6_CODE           => (1, false),
+        consts::IPV6_ND_TARGET        => (16, false),
+        consts::IPV6_ND_SLL           => (6, false),
+        consts::IPV6_ND_TLL           => (6, false),
+        consts::MPLS_LABEL            => (4, false),
+        consts::MPLS_TC               => (1, false),
+        consts::MPLS_BOS              => (1, false),
+        consts::PBB_ISID              => (3, true),
+        consts::TUNNEL_ID             => (8, true),
+        consts::IPV6_EXTENSION_HEADER => (2, true),
+        consts::PBB_UCA               => (1, false),
+        consts::TCP_FLAGS             => (2, true),
+        consts::ACTION_SET_OUTPUT     => (4, false),
+        consts::PACKET_TYPE           => (4, false),
+        _                             => return None,
+    })
+}
+
+/// Return the wire format of the given flow match field. This fails with `Error::Malformed` for
+/// an [`Unknown`](enum.FlowMatchField.html#variant.Unknown) field whose value is longer than 255
+/// bytes.
+pub fn encode(field: &FlowMatchField) -> Result<Vec<u8>> {
+    let mut bytes = vec![0; field.buffer_len()];
+    field.emit(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Parse a flow match field from its `oxm_field`, its value and its optional mask.
+pub fn decode(field: u8, value: &[u8], mask: Option<&[u8]>) -> Result<FlowMatchField> {
+    let mask = mask.unwrap_or(&[]);
+    let mut bytes = vec![0; OXM_HEADER_LEN + value.len() + mask.len()];
+    {
+        let mut packet = Packet::new(&mut bytes[..]);
+        packet.set_class(CLASS_OPEN_FLOW_BASIC);
+        packet.set_field(field);
+        if mask.is_empty() {
+            packet.unset_mask();
+        } else {
+            packet.set_mask();
+        }
+        packet.set_length((value.len() + mask.len()) as u8);
+        let buf = packet.value_mut();
+        buf[..value.len()].copy_from_slice(value);
+        buf[value.len()..].copy_from_slice(mask);
+    }
+    FlowMatchField::parse(&Packet::new(&bytes[..]))
+}
+
+macro_rules! impl_from_field {
+    ($($field:ident),*) => {
+        $(
+            impl From<$field> for FlowMatchField {
+                fn from(field: $field) -> Self {
+                    FlowMatchField::$field(field)
+                }
+            }
+        )*
+    }
+}
+
+impl_from_field!(
+    InPort, InPhysicalPort, Metadata, EthernetDestination, EthernetSource, EthernetType, VlanId,
+    VlanPriority, IpDscp, IpEcn, IpProtocol, Ipv4Source, Ipv4Destination, TcpSource,
+    TcpDestination, UdpSource, UdpDestination, SctpSource, SctpDestination, IcmpType, IcmpCode,
+    ArpOpCode, ArpSpa, ArpTpa, ArpSha, ArpTha, Ipv6Source, Ipv6Destination, Ipv6FlowLabel,
+    Icmpv6Type, Icmpv6Code, Ipv6NdTarget, Ipv6NdSll, Ipv6NdTll, MplsLabel, MplsTc, MplsBos,
+    PbbUca, TcpFlags, ActionSetOutput, PbbIsid, TunnelId, Ipv6ExtensionHeader, PacketType
+);
+
 /// Represent a flow match field. A flow match field is an OXM field with `oxm_class` set to
 /// [`CLASS_OPEN_FLOW_BASIC`](constant.CLASS_OPEN_FLOW_BASIC.html)
+///
+/// Fields with an `oxm_field` this crate does not know, like fields defined by later versions of
+/// the specification, are represented by [`Unknown`](#variant.Unknown), and are emitted back
+/// unchanged.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum FlowMatchField {
     InPort(InPort),
@@ -62,6 +181,7 @@ pub enum FlowMatchField {
     Metadata(Metadata),
     EthernetDestination(EthernetDestination),
     EthernetSource(EthernetSource),
+    EthernetType(EthernetType),
     VlanId(VlanId),
     VlanPriority(VlanPriority),
     IpDscp(IpDscp),
@@ -100,17 +220,39 @@ pub enum FlowMatchField {
     TunnelId(TunnelId),
     Ipv6ExtensionHeader(Ipv6ExtensionHeader),
     PacketType(PacketType),
+    /// A field whose `oxm_field` is not known. `value` contains the mask if `has_mask` is set.
+    Unknown {
+        field: u8,
+        has_mask: bool,
+        value: Vec<u8>,
+    },
 }
 
 impl FlowMatchField {
     #[cfg_attr(rustfmt, rustfmt_skip)]
     pub fn parse<'a, T: AsRef<[u8]> + ?Sized>(packet: &Packet<&'a T>) -> Result<Self> {
+        let (len, maskable) = match value_len(packet.field()) {
+            Some(value_len) => value_len,
+            None => return Ok(FlowMatchField::Unknown {
+                field: packet.field(),
+                has_mask: packet.has_mask(),
+                value: packet.value()[..packet.length() as usize].to_vec(),
+            }),
+        };
+        if packet.has_mask() && !maskable {
+            return Err(Error::Malformed);
+        }
+        let expected_len = if packet.has_mask() { 2 * len } else { len };
+        if packet.length() as usize != expected_len {
+            return Err(Error::Malformed);
+        }
         let match_field = match packet.field() {
             consts::IN_PORT               => FlowMatchField::InPort(InPort::parse(packet)),
             consts::IN_PHYSICAL_PORT      => FlowMatchField::InPhysicalPort(InPhysicalPort::parse(packet)),
             consts::METADATA              => FlowMatchField::Metadata(Metadata::parse(packet)),
             consts::ETHERNET_DESTINATION  => FlowMatchField::EthernetDestination(EthernetDestination::parse(packet)),
             consts::ETHERNET_SOURCE       => FlowMatchField::EthernetSource(EthernetSource::parse(packet)),
+            consts::ETHERNET_TYPE         => FlowMatchField::EthernetType(EthernetType::parse(packet)),
             consts::VLAN_ID               => FlowMatchField::VlanId(VlanId::parse(packet)),
             consts::VLAN_PRIORITY         => FlowMatchField::VlanPriority(VlanPriority::parse(packet)),
             consts::IP_DSCP               => FlowMatchField::IpDscp(IpDscp::parse(packet)),
@@ -149,7 +291,7 @@ impl FlowMatchField {
             consts::TCP_FLAGS             => FlowMatchField::TcpFlags(TcpFlags::parse(packet)),
             consts::ACTION_SET_OUTPUT     => FlowMatchField::ActionSetOutput(ActionSetOutput::parse(packet)),
             consts::PACKET_TYPE           => FlowMatchField::PacketType(PacketType::parse(packet)),
-            _                             => return Err(Error::BadOxmField),
+            _                             => unreachable!(),
         };
         Ok(match_field)
     }
@@ -162,6 +304,7 @@ impl FlowMatchField {
             FlowMatchField::Metadata(ref field)            => field.buffer_len(),
             FlowMatchField::EthernetDestination(ref field) => field.buffer_len(),
             FlowMatchField::EthernetSource(ref field)      => field.buffer_len(),
+            FlowMatchField::EthernetType(ref field)        => field.buffer_len(),
             FlowMatchField::VlanId(ref field)              => field.buffer_len(),
             FlowMatchField::VlanPriority(ref field)        => field.buffer_len(),
             FlowMatchField::IpDscp(ref field)              => field.buffer_len(),
@@ -200,6 +343,7 @@ impl FlowMatchField {
             FlowMatchField::TcpFlags(ref field)            => field.buffer_len(),
             FlowMatchField::ActionSetOutput(ref field)     => field.buffer_len(),
             FlowMatchField::PacketType(ref field)          => field.buffer_len(),
+            FlowMatchField::Unknown { ref value, .. }      => OXM_HEADER_LEN + value.len(),
         }
     }
 
@@ -211,6 +355,7 @@ impl FlowMatchField {
             FlowMatchField::Metadata(ref field)            => field.emit(buffer),
             FlowMatchField::EthernetDestination(ref field) => field.emit(buffer),
             FlowMatchField::EthernetSource(ref field)      => field.emit(buffer),
+            FlowMatchField::EthernetType(ref field)        => field.emit(buffer),
             FlowMatchField::VlanId(ref field)              => field.emit(buffer),
             FlowMatchField::VlanPriority(ref field)        => field.emit(buffer),
             FlowMatchField::IpDscp(ref field)              => field.emit(buffer),
@@ -249,13 +394,19 @@ impl FlowMatchField {
             FlowMatchField::TcpFlags(ref field)            => field.emit(buffer),
             FlowMatchField::ActionSetOutput(ref field)     => field.emit(buffer),
             FlowMatchField::PacketType(ref field)          => field.emit(buffer),
+            FlowMatchField::Unknown { field, has_mask, ref value } => {
+                emit_raw(CLASS_OPEN_FLOW_BASIC, field, has_mask, value, buffer)
+            }
         }
     }
 }
 
 #[cfg(test)]
-mod test {
+pub mod test {
     use super::*;
+    use port::PortNumber;
+    use smoltcp::wire::{ArpOperation, EthernetAddress, EthernetProtocol, Icmpv4Message,
+                        Ipv4Address};
 
     #[cfg_attr(rustfmt, rustfmt_skip)]
     static BYTES: [u8; 8] = [
@@ -272,4 +423,183 @@ mod test {
         let expected = FlowMatchField::InPort(InPort::new(0xabcd));
         assert_eq!(parsed, expected);
     }
+
+    #[test]
+    fn test_unknown() {
+        // field = 45, with a mask
+        let bytes = [0x80, 0x00, 0x5b, 0x04, 0x12, 0x34, 0xff, 0x00];
+        let parsed = FlowMatchField::parse(&Packet::new(&bytes)).unwrap();
+        let expected = FlowMatchField::Unknown {
+            field: 45,
+            has_mask: true,
+            value: vec![0x12, 0x34, 0xff, 0x00],
+        };
+        assert_eq!(parsed, expected);
+        assert_eq!(encode(&parsed), Ok(bytes.to_vec()));
+
+        // known field with a wrong length
+        let bytes = [0x80, 0x00, 0x00, 0x02, 0xab, 0xcd];
+        assert_eq!(FlowMatchField::parse(&Packet::new(&bytes)), Err(Error::Malformed));
+    }
+
+    const MAC: EthernetAddress = EthernetAddress([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]);
+    const MAC_MASK: EthernetAddress = EthernetAddress([0xff, 0xff, 0xff, 0x00, 0x00, 0x00]);
+    const IPV4: Ipv4Address = Ipv4Address([10, 0, 0, 1]);
+    const IPV4_MASK: Ipv4Address = Ipv4Address([255, 255, 0, 0]);
+    const IPV6: [u8; 16] = [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+    const IPV6_MASK: [u8; 16] = [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    /// One entry per OXM_OF_* field: the field code, and a representation without mask and with
+    /// a mask (if the field is maskable).
+    pub fn all_fields() -> Vec<(u8, FlowMatchField, Option<FlowMatchField>)> {
+        use super::FlowMatchField::*;
+        vec![
+            (consts::IN_PORT, InPort(super::InPort::new(7)), None),
+            (consts::IN_PHYSICAL_PORT, InPhysicalPort(super::InPhysicalPort::new(8)), None),
+            (consts::METADATA,
+             Metadata(super::Metadata::new(0x0102_0304_0506_0708, None)),
+             Some(Metadata(super::Metadata::new(0x0102_0304_0506_0708, Some(0xffff_0000_ffff_0000))))),
+            (consts::ETHERNET_DESTINATION,
+             EthernetDestination(super::EthernetDestination::new(MAC, None)),
+             Some(EthernetDestination(super::EthernetDestination::new(MAC, Some(MAC_MASK))))),
+            (consts::ETHERNET_SOURCE,
+             EthernetSource(super::EthernetSource::new(MAC, None)),
+             Some(EthernetSource(super::EthernetSource::new(MAC, Some(MAC_MASK))))),
+            (consts::ETHERNET_TYPE, EthernetType(super::EthernetType::new(EthernetProtocol::Arp)), None),
+            (consts::VLAN_ID,
+             VlanId(super::VlanId::new(0x1064, None)),
+             Some(VlanId(super::VlanId::new(0x1000, Some(0x1000))))),
+            (consts::VLAN_PRIORITY, VlanPriority(super::VlanPriority::new(5)), None),
+            (consts::IP_DSCP, IpDscp(super::IpDscp::new(46)), None),
+            (consts::IP_ECN, IpEcn(super::IpEcn::new(3)), None),
+            (consts::IP_PROTOCOL, IpProtocol(super::IpProtocol::new(6)), None),
+            (consts::IPV4_SOURCE,
+             Ipv4Source(super::Ipv4Source::new(IPV4, None)),
+             Some(Ipv4Source(super::Ipv4Source::new(IPV4, Some(IPV4_MASK))))),
+            (consts::IPV4_DESTINATION,
+             Ipv4Destination(super::Ipv4Destination::new(IPV4, None)),
+             Some(Ipv4Destination(super::Ipv4Destination::new(IPV4, Some(IPV4_MASK))))),
+            (consts::TCP_SOURCE, TcpSource(super::TcpSource::new(1234)), None),
+            (consts::TCP_DESTINATION, TcpDestination(super::TcpDestination::new(80)), None),
+            (consts::UDP_SOURCE, UdpSource(super::UdpSource::new(5353)), None),
+            (consts::UDP_DESTINATION, UdpDestination(super::UdpDestination::new(53)), None),
+            (consts::SCTP_SOURCE, SctpSource(super::SctpSource::new(2905)), None),
+            (consts::SCTP_DESTINATION, SctpDestination(super::SctpDestination::new(2944)), None),
+            (consts::ICMP_TYPE, IcmpType(super::IcmpType::new(Icmpv4Message::EchoRequest)), None),
+            (consts::ICMP_CODE, IcmpCode(super::IcmpCode::new(3)), None),
+            (consts::ARP_OP_CODE, ArpOpCode(super::ArpOpCode::new(ArpOperation::Reply)), None),
+            (consts::ARP_SPA,
+             ArpSpa(super::ArpSpa::new(IPV4, None)),
+             Some(ArpSpa(super::ArpSpa::new(IPV4, Some(IPV4_MASK))))),
+            (consts::ARP_TPA,
+             ArpTpa(super::ArpTpa::new(IPV4, None)),
+             Some(ArpTpa(super::ArpTpa::new(IPV4, Some(IPV4_MASK))))),
+            (consts::ARP_SHA,
+             ArpSha(super::ArpSha::new(MAC, None)),
+             Some(ArpSha(super::ArpSha::new(MAC, Some(MAC_MASK))))),
+            (consts::ARP_THA,
+             ArpTha(super::ArpTha::new(MAC, None)),
+             Some(ArpTha(super::ArpTha::new(MAC, Some(MAC_MASK))))),
+            (consts::IPV6_SOURCE,
+             Ipv6Source(super::Ipv6Source::new(IPV6, None)),
+             Some(Ipv6Source(super::Ipv6Source::new(IPV6, Some(IPV6_MASK))))),
+            (consts::IPV6_DESTINATION,
+             Ipv6Destination(super::Ipv6Destination::new(IPV6, None)),
+             Some(Ipv6Destination(super::Ipv6Destination::new(IPV6, Some(IPV6_MASK))))),
+            (consts::IPV6_FLOW_LABEL,
+             Ipv6FlowLabel(super::Ipv6FlowLabel::new(0x000a_bcde, None)),
+             Some(Ipv6FlowLabel(super::Ipv6FlowLabel::new(0x000a_bcde, Some(0x000f_f000))))),
+            (consts::ICMPV6_TYPE, Icmpv6Type(super::Icmpv6Type::new(135)), None),
+            (consts::ICMPV6_CODE, Icmpv6Code(super::Icmpv6Code::new(0)), None),
+            (consts::IPV6_ND_TARGET, Ipv6NdTarget(super::Ipv6NdTarget::new(IPV6)), None),
+            (consts::IPV6_ND_SLL, Ipv6NdSll(super::Ipv6NdSll::new(MAC)), None),
+            (consts::IPV6_ND_TLL, Ipv6NdTll(super::Ipv6NdTll::new(MAC)), None),
+            (consts::MPLS_LABEL, MplsLabel(super::MplsLabel::new(0x000f_edcb)), None),
+            (consts::MPLS_TC, MplsTc(super::MplsTc::new(4)), None),
+            (consts::MPLS_BOS, MplsBos(super::MplsBos::new(1)), None),
+            (consts::PBB_ISID,
+             PbbIsid(super::PbbIsid::new(0x00ab_cdef, None)),
+             Some(PbbIsid(super::PbbIsid::new(0x00ab_cdef, Some(0x00ff_0000))))),
+            (consts::TUNNEL_ID,
+             TunnelId(super::TunnelId::new(0xdead_beef, None)),
+             Some(TunnelId(super::TunnelId::new(0xdead_beef, Some(0xffff_0000))))),
+            (consts::IPV6_EXTENSION_HEADER,
+             Ipv6ExtensionHeader(super::Ipv6ExtensionHeader::new(0x0104, None)),
+             Some(Ipv6ExtensionHeader(super::Ipv6ExtensionHeader::new(0x0104, Some(0x0104))))),
+            (consts::PBB_UCA, PbbUca(super::PbbUca::new(true)), None),
+            (consts::TCP_FLAGS,
+             TcpFlags(super::TcpFlags::new(0x0012, None)),
+             Some(TcpFlags(super::TcpFlags::new(0x0002, Some(0x0012))))),
+            (consts::ACTION_SET_OUTPUT, ActionSetOutput(super::ActionSetOutput::new(PortNumber::Controller)), None),
+            (consts::PACKET_TYPE, PacketType(super::PacketType::new(0x0001_0800)), None),
+        ]
+    }
+
+    /// Emit the given field, check its header, and parse it back.
+    fn round_trip(code: u8, field: &FlowMatchField, has_mask: bool) {
+        let (len, _) = value_len(code).unwrap();
+        let value_len = if has_mask { 2 * len } else { len };
+        assert_eq!(field.buffer_len(), OXM_HEADER_LEN + value_len);
+
+        let mut bytes = vec![0xff; field.buffer_len()];
+        field.emit(&mut bytes).unwrap();
+        let packet = Packet::new(&bytes[..]);
+        assert_eq!(packet.class(), CLASS_OPEN_FLOW_BASIC);
+        assert_eq!(packet.field(), code);
+        assert_eq!(packet.has_mask(), has_mask);
+        assert_eq!(packet.length() as usize, value_len);
+        assert_eq!(&FlowMatchField::parse(&packet).unwrap(), field);
+    }
+
+    #[test]
+    fn test_round_trip_all_fields() {
+        let fields = all_fields();
+        for code in 0..64 {
+            let entries: Vec<_> = fields.iter().filter(|entry| entry.0 == code).collect();
+            match value_len(code) {
+                None => assert!(entries.is_empty()),
+                Some((_, maskable)) => {
+                    assert_eq!(entries.len(), 1, "field {} is not tested", code);
+                    assert_eq!(entries[0].2.is_some(), maskable);
+                }
+            }
+        }
+        for &(code, ref field, ref masked) in &fields {
+            round_trip(code, field, false);
+            if let Some(ref masked) = *masked {
+                round_trip(code, masked, true);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_bad_mask() {
+        for &(code, ref field, ref masked) in &all_fields() {
+            if masked.is_some() {
+                continue;
+            }
+            // Set the HM bit and double the length, as if the field carried a mask
+            let (len, _) = value_len(code).unwrap();
+            let mut bytes = vec![0; OXM_HEADER_LEN + 2 * len];
+            field.emit(&mut bytes).unwrap();
+            let mut packet = Packet::new(&mut bytes[..]);
+            packet.set_mask();
+            packet.set_length(2 * len as u8);
+            let packet = Packet::new(&bytes[..]);
+            assert_eq!(FlowMatchField::parse(&packet), Err(Error::Malformed));
+        }
+    }
+
+    #[test]
+    fn test_parse_bad_length() {
+        for &(code, ref field, _) in &all_fields() {
+            let mut bytes = vec![0; field.buffer_len()];
+            field.emit(&mut bytes).unwrap();
+            let mut packet = Packet::new(&mut bytes[..]);
+            let (len, _) = value_len(code).unwrap();
+            packet.set_length(len as u8 - 1);
+            let packet = Packet::new(&bytes[..]);
+            assert_eq!(FlowMatchField::parse(&packet), Err(Error::Malformed));
+        }
+    }
 }
@@ -1,6 +1,9 @@
+use core::marker::PhantomData;
+
 use {Error, Repr, Result};
 use byteorder::{ByteOrder, NetworkEndian};
-use super::Oxm;
+use super::{ExperimenterOxm, FlowMatchField, Oxm, CLASS_EXPERIMENTER, OXM_HEADER_LEN};
+use super::Packet as OxmPacket;
 
 mod field {
     #![allow(non_snake_case)]
@@ -70,23 +73,65 @@ impl<T: AsRef<[u8]>> Packet<T> {
         NetworkEndian::read_u16(&self.inner.as_ref()[field::LENGTH])
     }
 
-    /// Parse and return the OXM fields
-    fn oxm_fields<E: Repr>(&self) -> Result<Vec<Oxm<E>>> {
+    /// Parse the OXM fields and pass them to `f`. Return the length of the fields that were
+    /// parsed, which is less than the length of the `oxm_fields` field if the last field is
+    /// truncated and truncated fields are accepted.
+    fn parse_fields<E: Repr, F: FnMut(Oxm<E>)>(
+        &self,
+        options: &ParseOptions,
+        mut f: F,
+    ) -> Result<usize> {
         let length = self.length() as usize;
+        if length < field::LENGTH.end {
+            return Err(Error::Malformed);
+        }
         let bytes = &self.inner.as_ref()[field::OXM_FIELDS(length)];
-        let mut oxm_fields = Vec::new();
         let mut offset = 0;
-        loop {
-            match Oxm::parse(&bytes[offset..]) {
-                Ok(repr) => {
-                    offset += repr.buffer_len();
-                    oxm_fields.push(repr);
+        while offset < bytes.len() {
+            let repr = match Oxm::parse_lenient(&bytes[offset..], !options.reject_unknown) {
+                Ok(Oxm::FlowMatchField(FlowMatchField::Unknown { .. }))
+                    if options.reject_unknown =>
+                {
+                    return Err(Error::BadOxmField)
                 }
-                Err(Error::Truncated) => return Ok(oxm_fields),
+                Ok(repr) => repr,
+                Err(Error::Truncated) if !options.reject_truncated => break,
                 Err(e) => return Err(e),
+            };
+            if options.reject_duplicates && is_duplicate(&bytes[..offset], &bytes[offset..]) {
+                return Err(Error::DuplicateOxmField);
             }
+            offset += OXM_HEADER_LEN + OxmPacket::new(&bytes[offset..]).length() as usize;
+            f(repr);
+        }
+        Ok(offset)
+    }
+
+    /// Return the `padding` field
+    fn padding(&self) -> &[u8] {
+        &self.inner.as_ref()[field::PADDING(self.length() as usize)]
+    }
+}
+
+/// Return `true` if `field` is not an experimenter field, and one of the `previous` fields has the
+/// same `oxm_class` and `oxm_field`.
+fn is_duplicate(previous: &[u8], field: &[u8]) -> bool {
+    let header = |bytes| {
+        let packet = OxmPacket::new(bytes);
+        (packet.class(), packet.field())
+    };
+    let (class, code) = header(field);
+    if class == CLASS_EXPERIMENTER {
+        return false;
+    }
+    let mut offset = 0;
+    while offset < previous.len() {
+        if header(&previous[offset..]) == (class, code) {
+            return true;
         }
+        offset += OXM_HEADER_LEN + OxmPacket::new(&previous[offset..]).length() as usize;
     }
+    false
 }
 
 impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
@@ -139,29 +184,116 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
 /// - The `match type` field can only take one valid value `0x0002`
 /// - The `length` is the length of the oxm fields only
 /// - The `padding` field is for 8 bytes alignment
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct PacketRepr<E>(pub Vec<Oxm<E>>);
+///
+/// Flow matches are compared by their [canonical form](#method.normalize).
+#[derive(Debug, Clone)]
+pub struct PacketRepr<E = ExperimenterOxm>(pub Vec<Oxm<E>>);
 
 impl<E: Repr> PacketRepr<E> {
     fn fields_len(&self) -> usize {
         self.0.iter().fold(0, |acc, field| acc + field.buffer_len())
     }
+
+    /// Return the length of the flow match at the beginning of `buffer`, padding included, as
+    /// given by its `length` field. This is where the data that follows the flow match starts,
+    /// even if a truncated field was dropped when parsing it.
+    pub fn wire_len(buffer: &[u8]) -> Result<usize> {
+        let packet = Packet::new_checked(buffer)?;
+        let length = packet.length() as usize;
+        if length < field::LENGTH.end {
+            return Err(Error::Malformed);
+        }
+        Ok(field::PADDING(length).end)
+    }
 }
 
-impl<E: Repr> Repr for PacketRepr<E> {
-    fn parse(buffer: &[u8]) -> Result<Self> {
+/// Options that control how strictly the OXM fields of a flow match are parsed.
+///
+/// [`strict()`](#method.strict) parsing rejects anything unexpected, while
+/// [`lenient()`](#method.lenient) parsing, which is the default, accepts as much as possible
+/// without losing information: the fields that cannot be parsed are kept as
+/// [`Oxm::Unknown`](enum.Oxm.html#variant.Unknown) (or
+/// [`FlowMatchField::Unknown`](enum.FlowMatchField.html#variant.Unknown) for the flow match
+/// fields this crate does not know), so that the flow match is emitted back unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Return an error for a field that cannot be parsed, instead of an
+    /// [`Oxm::Unknown`](enum.Oxm.html#variant.Unknown) field, and
+    /// [`Error::BadOxmField`](../enum.Error.html#variant.BadOxmField) for a
+    /// [`FlowMatchField::Unknown`](enum.FlowMatchField.html#variant.Unknown) field.
+    pub reject_unknown: bool,
+    /// Return [`Error::Truncated`](../enum.Error.html#variant.Truncated) if the last field is
+    /// truncated. Otherwise, the truncated field is dropped, and the flow match is not emitted
+    /// back unchanged.
+    pub reject_truncated: bool,
+    /// Return [`Error::Malformed`](../enum.Error.html#variant.Malformed) if the padding bytes
+    /// are not zeros.
+    pub reject_bad_padding: bool,
+    /// Return [`Error::DuplicateOxmField`](../enum.Error.html#variant.DuplicateOxmField) if a
+    /// field appears more than once. Experimenter fields are not checked.
+    pub reject_duplicates: bool,
+}
+
+impl ParseOptions {
+    /// Reject truncated fields, unknown fields, bad padding and duplicate fields.
+    pub fn strict() -> Self {
+        ParseOptions {
+            reject_unknown: true,
+            reject_truncated: true,
+            reject_bad_padding: true,
+            reject_duplicates: true,
+        }
+    }
+
+    /// Keep unknown fields as [`Oxm::Unknown`](enum.Oxm.html#variant.Unknown), and accept bad
+    /// padding and duplicate fields. Truncated fields are still rejected, since they cannot be
+    /// kept.
+    pub fn lenient() -> Self {
+        ParseOptions {
+            reject_unknown: false,
+            reject_truncated: true,
+            reject_bad_padding: false,
+            reject_duplicates: false,
+        }
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions::lenient()
+    }
+}
+
+impl<E: Repr> PacketRepr<E> {
+    /// Parse a flow match with the given options. [`parse()`](#method.parse) uses the
+    /// [default](struct.ParseOptions.html#method.lenient) options.
+    pub fn parse_with(buffer: &[u8], options: &ParseOptions) -> Result<Self> {
         let packet = Packet::new_checked(buffer)?;
-        match packet.match_type() {
-            MatchType::OXM => Ok(PacketRepr(packet.oxm_fields()?)),
-            _ => Err(Error::BadMatchType),
+        if packet.match_type() != MatchType::OXM {
+            return Err(Error::BadMatchType);
         }
+        let mut oxm_fields = Vec::new();
+        packet.parse_fields(options, |oxm| oxm_fields.push(oxm))?;
+        if options.reject_bad_padding && packet.padding().iter().any(|&byte| byte != 0) {
+            return Err(Error::Malformed);
+        }
+        Ok(PacketRepr(oxm_fields))
+    }
+}
+
+impl<E: Repr> Repr for PacketRepr<E> {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        PacketRepr::parse_with(buffer, &ParseOptions::default())
     }
 
     fn buffer_len(&self) -> usize {
-        field::PADDING(self.fields_len()).end
+        field::PADDING(field::LENGTH.end + self.fields_len()).end
     }
 
     fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
         let mut packet = Packet::new(buffer);
         packet.set_match_type(MatchType::OXM);
         packet.set_length(4 + self.fields_len() as u16);
@@ -171,27 +303,109 @@ impl<E: Repr> Repr for PacketRepr<E> {
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use oxm::{FlowMatchField, InPort, Oxm, TunnelId, VlanId};
+/// A flow match that borrows the OXM fields from the buffer it was parsed from, instead of
+/// copying them in a `Vec`. The fields are checked when the flow match is parsed, and decoded
+/// again each time they are [iterated](#method.iter) on.
+///
+/// Unlike [`FlowMatch`](type.FlowMatch.html), borrowed flow matches are compared byte by byte.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PacketReprRef<'a, E = ExperimenterOxm> {
+    fields: &'a [u8],
+    experimenter: PhantomData<E>,
+}
 
-    // a dummy Oxm Experimenter type.
-    // needed because openflow::PacketRepr is generic of it.
-    #[derive(Debug, PartialEq, Eq, Clone)]
-    struct OxmExperimenter;
+impl<'a, E> Clone for PacketReprRef<'a, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
 
-    impl Repr for OxmExperimenter {
-        fn parse(_buffer: &[u8]) -> Result<Self> {
-            unreachable!()
+impl<'a, E> Copy for PacketReprRef<'a, E> {}
+
+impl<'a, E: Repr> PacketReprRef<'a, E> {
+    /// Parse a flow match with the [default](struct.ParseOptions.html#method.lenient) options.
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        PacketReprRef::parse_with(buffer, &ParseOptions::default())
+    }
+
+    /// Parse a flow match with the given options.
+    pub fn parse_with(buffer: &'a [u8], options: &ParseOptions) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        if packet.match_type() != MatchType::OXM {
+            return Err(Error::BadMatchType);
+        }
+        let len = packet.parse_fields(options, |_: Oxm<E>| ())?;
+        if options.reject_bad_padding && packet.padding().iter().any(|&byte| byte != 0) {
+            return Err(Error::Malformed);
         }
-        fn buffer_len(&self) -> usize {
-            unreachable!()
+        Ok(PacketReprRef {
+            fields: &buffer[field::LENGTH.end..field::LENGTH.end + len],
+            experimenter: PhantomData,
+        })
+    }
+
+    /// Return the wire format of the OXM fields.
+    pub fn fields(&self) -> &'a [u8] {
+        self.fields
+    }
+
+    /// Return an iterator over the OXM fields.
+    pub fn iter(&self) -> OxmsIterator<'a, E> {
+        OxmsIterator {
+            bytes: self.fields,
+            experimenter: PhantomData,
         }
-        fn emit(&self, _buffer: &mut [u8]) -> Result<()> {
-            unreachable!()
+    }
+
+    /// Return the length of the flow match, including the padding.
+    pub fn buffer_len(&self) -> usize {
+        field::PADDING(field::LENGTH.end + self.fields.len()).end
+    }
+
+    /// Write the flow match in the given buffer.
+    pub fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
         }
+        let mut packet = Packet::new(buffer);
+        packet.set_match_type(MatchType::OXM);
+        packet.set_length((field::LENGTH.end + self.fields.len()) as u16);
+        packet.inner[field::OXM_FIELDS(field::LENGTH.end + self.fields.len())]
+            .copy_from_slice(self.fields);
+        packet.set_padding();
+        Ok(())
+    }
+
+    /// Copy the OXM fields into an owned flow match.
+    pub fn to_repr(&self) -> PacketRepr<E> {
+        PacketRepr(self.iter().collect())
     }
+}
+
+/// An iterator over the OXM fields of a [`FlowMatchRef`](struct.FlowMatchRef.html).
+#[derive(Debug)]
+pub struct OxmsIterator<'a, E> {
+    bytes: &'a [u8],
+    experimenter: PhantomData<E>,
+}
+
+impl<'a, E: Repr> Iterator for OxmsIterator<'a, E> {
+    type Item = Oxm<E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The fields were checked when the flow match was parsed, so parsing them leniently
+        // gives the same result.
+        let oxm = Oxm::parse_lenient(self.bytes, true).ok()?;
+        let len = OXM_HEADER_LEN + OxmPacket::new(self.bytes).length() as usize;
+        self.bytes = &self.bytes[len..];
+        Some(oxm)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use oxm::{FlowMatchField, InPort, Oxm, TunnelId, VlanId};
 
     #[cfg_attr(rustfmt, rustfmt_skip)]
     static BYTES: [u8; 32] = [
@@ -243,8 +457,8 @@ mod test {
 
     #[test]
     fn test_parse() {
-        let parsed = PacketRepr::<OxmExperimenter>::parse(&BYTES).unwrap();
-        let expected = PacketRepr::<OxmExperimenter>(vec![
+        let parsed = PacketRepr::<ExperimenterOxm>::parse(&BYTES).unwrap();
+        let expected = PacketRepr::<ExperimenterOxm>(vec![
             Oxm::FlowMatchField(FlowMatchField::InPort(InPort::new(0xabcd))),
             Oxm::FlowMatchField(FlowMatchField::TunnelId(TunnelId::new(50_000, None))),
             Oxm::FlowMatchField(FlowMatchField::VlanId(VlanId::new(0x0777, None))),
@@ -254,7 +468,7 @@ mod test {
 
     #[test]
     fn test_emit() {
-        let repr = PacketRepr::<OxmExperimenter>(vec![
+        let repr = PacketRepr::<ExperimenterOxm>(vec![
             Oxm::FlowMatchField(FlowMatchField::InPort(InPort::new(0xabcd))),
             Oxm::FlowMatchField(FlowMatchField::TunnelId(TunnelId::new(50_000, None))),
             Oxm::FlowMatchField(FlowMatchField::VlanId(VlanId::new(0x0777, None))),
@@ -265,4 +479,126 @@ mod test {
         repr.emit(&mut bytes).unwrap();
         assert_eq!(&bytes[..], &BYTES[..]);
     }
+
+    fn modified(index: usize, byte: u8) -> [u8; 32] {
+        let mut bytes = BYTES;
+        bytes[index] = byte;
+        bytes
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        // field = 127, unknown
+        let bytes = modified(26, 127 << 1);
+        let parsed = PacketRepr::<ExperimenterOxm>::parse(&bytes).unwrap();
+        assert_eq!(
+            parsed.0[2],
+            Oxm::FlowMatchField(FlowMatchField::Unknown {
+                field: 127,
+                has_mask: false,
+                value: vec![0x07, 0x77],
+            })
+        );
+        let mut emitted = [0xff; 32];
+        parsed.emit(&mut emitted).unwrap();
+        assert_eq!(&emitted[..], &bytes[..]);
+
+        let strict = PacketRepr::<ExperimenterOxm>::parse_with(&bytes, &ParseOptions::strict());
+        assert_eq!(strict, Err(Error::BadOxmField));
+    }
+
+    #[test]
+    fn test_malformed_field() {
+        // OXM_OF_VLAN_VID with the HM bit set, but without a mask
+        let bytes = modified(26, (0x06 << 1) | 1);
+        let parsed = PacketRepr::<ExperimenterOxm>::parse(&bytes).unwrap();
+        assert_eq!(
+            parsed.0[2],
+            Oxm::Unknown {
+                class: 0x8000,
+                field: 6,
+                has_mask: true,
+                value: vec![0x07, 0x77],
+            }
+        );
+        let mut emitted = [0xff; 32];
+        parsed.emit(&mut emitted).unwrap();
+        assert_eq!(&emitted[..], &bytes[..]);
+
+        let strict = PacketRepr::<ExperimenterOxm>::parse_with(&bytes, &ParseOptions::strict());
+        assert_eq!(strict, Err(Error::Malformed));
+    }
+
+    #[test]
+    fn test_truncated_field() {
+        // the value of the third field is 4 bytes long, but only 2 bytes remain
+        let bytes = modified(27, 4);
+        assert_eq!(
+            PacketRepr::<ExperimenterOxm>::parse(&bytes),
+            Err(Error::Truncated)
+        );
+
+        let options = ParseOptions {
+            reject_truncated: false,
+            ..ParseOptions::lenient()
+        };
+        let parsed = PacketRepr::<ExperimenterOxm>::parse_with(&bytes, &options).unwrap();
+        assert_eq!(parsed.0.len(), 2);
+        assert_eq!(PacketRepr::<ExperimenterOxm>::wire_len(&bytes), Ok(32));
+    }
+
+    #[test]
+    fn test_bad_padding() {
+        let bytes = modified(31, 1);
+        assert!(PacketRepr::<ExperimenterOxm>::parse(&bytes).is_ok());
+
+        let strict = PacketRepr::<ExperimenterOxm>::parse_with(&bytes, &ParseOptions::strict());
+        assert_eq!(strict, Err(Error::Malformed));
+    }
+
+    #[test]
+    fn test_duplicate_field() {
+        let repr = PacketRepr::<ExperimenterOxm>(vec![
+            Oxm::FlowMatchField(FlowMatchField::InPort(InPort::new(1))),
+            Oxm::FlowMatchField(FlowMatchField::InPort(InPort::new(2))),
+        ]);
+        let mut bytes = vec![0; repr.buffer_len()];
+        repr.emit(&mut bytes).unwrap();
+        assert_eq!(PacketRepr::<ExperimenterOxm>::parse(&bytes), Ok(repr));
+
+        let strict = PacketRepr::<ExperimenterOxm>::parse_with(&bytes, &ParseOptions::strict());
+        assert_eq!(strict, Err(Error::DuplicateOxmField));
+        let strict = PacketReprRef::<ExperimenterOxm>::parse_with(&bytes, &ParseOptions::strict());
+        assert_eq!(strict, Err(Error::DuplicateOxmField));
+    }
+
+    #[test]
+    fn test_borrowed() {
+        let borrowed = PacketReprRef::<ExperimenterOxm>::parse(&BYTES).unwrap();
+        assert_eq!(borrowed.fields(), &BYTES[4..30]);
+        assert_eq!(borrowed.iter().count(), 3);
+        assert_eq!(
+            borrowed.to_repr(),
+            PacketRepr::<ExperimenterOxm>::parse(&BYTES).unwrap()
+        );
+
+        assert_eq!(borrowed.buffer_len(), 32);
+        let mut bytes = [0xff; 32];
+        borrowed.emit(&mut bytes).unwrap();
+        assert_eq!(&bytes[..], &BYTES[..]);
+
+        // the truncated field is left out when truncated fields are accepted
+        let bytes = modified(27, 4);
+        assert_eq!(
+            PacketReprRef::<ExperimenterOxm>::parse(&bytes),
+            Err(Error::Truncated)
+        );
+        let options = ParseOptions {
+            reject_truncated: false,
+            ..ParseOptions::lenient()
+        };
+        let borrowed = PacketReprRef::<ExperimenterOxm>::parse_with(&bytes, &options).unwrap();
+        assert_eq!(borrowed.iter().count(), 2);
+        assert_eq!(borrowed.buffer_len(), 24);
+    }
 }
@@ -0,0 +1,661 @@
+//! Evaluation of flow matches against raw Ethernet frames.
+//!
+//! The frame is dissected into the value every flow match field would have for it, in the OXM
+//! wire format. Matching a flow match field is then a byte-wise comparison of its value with the
+//! value extracted from the frame, honoring the mask if any.
+//!
+//! Frames may be truncated, as in packet-in messages: a field whose header is cut off is
+//! considered absent from the frame, and a flow match that uses it does not match.
+//!
+//! The same dissection is used to build an exact match from a frame, for instance to install a
+//! microflow for the frame of a packet-in message.
+
+use byteorder::{ByteOrder, NetworkEndian};
+use smoltcp::wire::{ArpHardware, ArpPacket, EthernetFrame, EthernetProtocol, Ipv4Packet,
+                    TcpPacket};
+
+use Repr;
+use port::PortNumber;
+use super::fields::{self, consts, ETH_TYPE_PBB, VLAN_PRESENT};
+use super::{FlowMatch, FlowMatchField, Oxm, Packet};
+
+const ETH_TYPE_VLAN: u16 = 0x8100;
+const ETH_TYPE_QINQ: u16 = 0x88a8;
+const ETH_TYPE_ARP: u16 = 0x0806;
+const ETH_TYPE_IPV4: u16 = 0x0800;
+const ETH_TYPE_IPV6: u16 = 0x86dd;
+const ETH_TYPE_MPLS: u16 = 0x8847;
+const ETH_TYPE_MPLS_MULTICAST: u16 = 0x8848;
+
+const IP_PROTO_ICMP: u8 = 1;
+const IP_PROTO_TCP: u8 = 6;
+const IP_PROTO_UDP: u8 = 17;
+const IP_PROTO_ICMPV6: u8 = 58;
+const IP_PROTO_SCTP: u8 = 132;
+
+/// IPv6 extension headers, and the corresponding bit of the `OXM_OF_IPV6_EXTHDR` pseudo-field.
+const IPV6_HOP_BY_HOP: u8 = 0;
+const IPV6_ROUTING: u8 = 43;
+const IPV6_FRAGMENT: u8 = 44;
+const IPV6_ESP: u8 = 50;
+const IPV6_AUTH: u8 = 51;
+const IPV6_NO_NEXT: u8 = 59;
+const IPV6_DESTINATION: u8 = 60;
+const EXTHDR_NONEXT: u16 = 1;
+const EXTHDR_ESP: u16 = 1 << 1;
+const EXTHDR_AUTH: u16 = 1 << 2;
+const EXTHDR_DEST: u16 = 1 << 3;
+const EXTHDR_FRAG: u16 = 1 << 4;
+const EXTHDR_ROUTER: u16 = 1 << 5;
+const EXTHDR_HOP: u16 = 1 << 6;
+const EXTHDR_UNREP: u16 = 1 << 7;
+
+const ICMPV6_NEIGHBOR_SOLICIT: u8 = 135;
+const ICMPV6_NEIGHBOR_ADVERT: u8 = 136;
+const ND_OPTION_SOURCE_LL_ADDR: u8 = 1;
+const ND_OPTION_TARGET_LL_ADDR: u8 = 2;
+
+/// The pipeline fields of a packet, that cannot be extracted from the frame itself.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct PipelineFields {
+    /// Port the packet was received on. It is also used as the physical input port.
+    pub in_port: u32,
+    /// Metadata passed between tables.
+    pub metadata: u64,
+    /// Metadata associated with a logical port.
+    pub tunnel_id: u64,
+}
+
+/// The flow match fields of a frame, in the OXM wire format, in the order they appear in the
+/// frame.
+pub struct FrameFields(pub Vec<(u8, Vec<u8>)>);
+
+impl FrameFields {
+    /// Extract the header fields of the given Ethernet frame.
+    pub fn dissect(frame: &[u8]) -> Self {
+        let mut fields = FrameFields(Vec::new());
+        fields.ethernet(frame);
+        fields
+    }
+
+    /// Add the pipeline fields. Fields that are not part of `pipeline` get the value they have at
+    /// the beginning of the pipeline.
+    pub fn push_pipeline(&mut self, pipeline: &PipelineFields) {
+        self.push_u32(consts::IN_PORT, pipeline.in_port);
+        self.push_u32(consts::IN_PHYSICAL_PORT, pipeline.in_port);
+        self.push_u64(consts::METADATA, pipeline.metadata);
+        self.push_u64(consts::TUNNEL_ID, pipeline.tunnel_id);
+        self.push_u32(consts::ACTION_SET_OUTPUT, PortNumber::Unset.into());
+        // Ethernet packet type: namespace 0, type 0.
+        self.push_u32(consts::PACKET_TYPE, 0);
+    }
+
+    /// Return the value of the given field, if it is present in the frame.
+    pub fn get(&self, field: u8) -> Option<&[u8]> {
+        self.0
+            .iter()
+            .find(|&&(code, _)| code == field)
+            .map(|(_, value)| &value[..])
+    }
+
+    /// Return `true` if the given flow match field matches the frame.
+    pub fn matches(&self, field: &FlowMatchField) -> bool {
+        let bytes = match fields::encode(field) {
+            Ok(bytes) => bytes,
+            Err(_) => return false,
+        };
+        let packet = Packet::new(&bytes[..]);
+        let frame_value = match self.get(packet.field()) {
+            Some(value) => value,
+            None => return false,
+        };
+        let (value, mask) = if packet.has_mask() {
+            packet.value().split_at(frame_value.len())
+        } else {
+            (packet.value(), &[][..])
+        };
+        value.iter().zip(frame_value).enumerate().all(|(i, (value, frame_value))| {
+            let mask = mask.get(i).cloned().unwrap_or(0xff);
+            value & mask == frame_value & mask
+        })
+    }
+
+    fn push(&mut self, field: u8, value: &[u8]) {
+        self.0.push((field, value.to_vec()));
+    }
+
+    fn push_u16(&mut self, field: u8, value: u16) {
+        let mut bytes = [0; 2];
+        NetworkEndian::write_u16(&mut bytes, value);
+        self.push(field, &bytes);
+    }
+
+    fn push_u32(&mut self, field: u8, value: u32) {
+        let mut bytes = [0; 4];
+        NetworkEndian::write_u32(&mut bytes, value);
+        self.push(field, &bytes);
+    }
+
+    fn push_u64(&mut self, field: u8, value: u64) {
+        let mut bytes = [0; 8];
+        NetworkEndian::write_u64(&mut bytes, value);
+        self.push(field, &bytes);
+    }
+
+    fn ethernet(&mut self, frame: &[u8]) {
+        let frame = match EthernetFrame::new_checked(frame) {
+            Ok(frame) => frame,
+            Err(_) => return,
+        };
+        self.push(consts::ETHERNET_DESTINATION, frame.dst_addr().as_bytes());
+        self.push(consts::ETHERNET_SOURCE, frame.src_addr().as_bytes());
+
+        // Skip the VLAN tags, only the outermost one is matched.
+        let mut eth_type = u16::from(frame.ethertype());
+        let mut payload = frame.payload();
+        let mut tci = None;
+        while eth_type == ETH_TYPE_VLAN || eth_type == ETH_TYPE_QINQ {
+            if payload.len() < 4 {
+                return;
+            }
+            tci = tci.or_else(|| Some(NetworkEndian::read_u16(&payload[0..2])));
+            eth_type = NetworkEndian::read_u16(&payload[2..4]);
+            payload = &payload[4..];
+        }
+        match tci {
+            Some(tci) => {
+                self.push_u16(consts::VLAN_ID, (tci & 0x0fff) | VLAN_PRESENT);
+                self.push(consts::VLAN_PRIORITY, &[(tci >> 13) as u8]);
+            }
+            None => self.push_u16(consts::VLAN_ID, 0),
+        }
+        self.push_u16(consts::ETHERNET_TYPE, eth_type);
+
+        match eth_type {
+            ETH_TYPE_ARP => self.arp(payload),
+            ETH_TYPE_IPV4 => self.ipv4(payload),
+            ETH_TYPE_IPV6 => self.ipv6(payload),
+            ETH_TYPE_MPLS | ETH_TYPE_MPLS_MULTICAST => self.mpls(payload),
+            ETH_TYPE_PBB => self.pbb(payload),
+            _ => {}
+        }
+    }
+
+    fn arp(&mut self, payload: &[u8]) {
+        let packet = match ArpPacket::new_checked(payload) {
+            Ok(packet) => packet,
+            Err(_) => return,
+        };
+        // The ARP fields are only meaningful for Ethernet+IP ARP packets, and are 0 otherwise.
+        if packet.hardware_type() == ArpHardware::Ethernet
+            && packet.protocol_type() == EthernetProtocol::Ipv4
+            && packet.hardware_len() == 6 && packet.protocol_len() == 4
+        {
+            self.push_u16(consts::ARP_OP_CODE, packet.operation().into());
+            self.push(consts::ARP_SPA, packet.source_protocol_addr());
+            self.push(consts::ARP_TPA, packet.target_protocol_addr());
+            self.push(consts::ARP_SHA, packet.source_hardware_addr());
+            self.push(consts::ARP_THA, packet.target_hardware_addr());
+        } else {
+            self.push_u16(consts::ARP_OP_CODE, 0);
+            self.push(consts::ARP_SPA, &[0; 4]);
+            self.push(consts::ARP_TPA, &[0; 4]);
+            self.push(consts::ARP_SHA, &[0; 6]);
+            self.push(consts::ARP_THA, &[0; 6]);
+        }
+    }
+
+    fn ipv4(&mut self, payload: &[u8]) {
+        let packet = match Ipv4Packet::new_checked(payload) {
+            Ok(packet) => packet,
+            Err(_) => return,
+        };
+        let protocol = u8::from(packet.protocol());
+        self.push(consts::IP_DSCP, &[packet.dscp()]);
+        self.push(consts::IP_ECN, &[packet.ecn()]);
+        self.push(consts::IP_PROTOCOL, &[protocol]);
+        self.push(consts::IPV4_SOURCE, packet.src_addr().as_bytes());
+        self.push(consts::IPV4_DESTINATION, packet.dst_addr().as_bytes());
+        // Only the first fragment carries the transport header.
+        if packet.frag_offset() == 0 {
+            // Don't use `payload()`, that panics on truncated packets.
+            self.transport(protocol, &payload[packet.header_len() as usize..], false);
+        }
+    }
+
+    fn ipv6(&mut self, payload: &[u8]) {
+        if payload.len() < 40 {
+            return;
+        }
+        let first_word = NetworkEndian::read_u32(&payload[0..4]);
+        let traffic_class = (first_word >> 20) as u8;
+        self.push(consts::IP_DSCP, &[traffic_class >> 2]);
+        self.push(consts::IP_ECN, &[traffic_class & 0b11]);
+        self.push_u32(consts::IPV6_FLOW_LABEL, first_word & 0x000f_ffff);
+        self.push(consts::IPV6_SOURCE, &payload[8..24]);
+        self.push(consts::IPV6_DESTINATION, &payload[24..40]);
+
+        let mut next_header = payload[6];
+        let mut rest = &payload[40..];
+        let mut exthdr = 0;
+        let mut has_transport = true;
+        loop {
+            let flag = match next_header {
+                IPV6_HOP_BY_HOP => EXTHDR_HOP,
+                IPV6_ROUTING => EXTHDR_ROUTER,
+                IPV6_FRAGMENT => EXTHDR_FRAG,
+                IPV6_DESTINATION => EXTHDR_DEST,
+                IPV6_AUTH => EXTHDR_AUTH,
+                IPV6_ESP => EXTHDR_ESP,
+                IPV6_NO_NEXT => EXTHDR_NONEXT,
+                _ => break,
+            };
+            if exthdr & flag != 0 {
+                exthdr |= EXTHDR_UNREP;
+            }
+            exthdr |= flag;
+            // The payload of ESP is encrypted, so that's as far as we can go.
+            if next_header == IPV6_ESP || next_header == IPV6_NO_NEXT || rest.len() < 8 {
+                has_transport = false;
+                break;
+            }
+            let len = match next_header {
+                IPV6_FRAGMENT => {
+                    // Only the first fragment carries the transport header.
+                    if NetworkEndian::read_u16(&rest[2..4]) & 0xfff8 != 0 {
+                        has_transport = false;
+                    }
+                    8
+                }
+                IPV6_AUTH => (rest[1] as usize + 2) * 4,
+                _ => (rest[1] as usize + 1) * 8,
+            };
+            next_header = rest[0];
+            if rest.len() < len {
+                has_transport = false;
+                break;
+            }
+            rest = &rest[len..];
+        }
+        self.push_u16(consts::IPV6_EXTENSION_HEADER, exthdr);
+        self.push(consts::IP_PROTOCOL, &[next_header]);
+        if has_transport {
+            self.transport(next_header, rest, true);
+        }
+    }
+
+    fn transport(&mut self, protocol: u8, payload: &[u8], ipv6: bool) {
+        match protocol {
+            IP_PROTO_TCP if payload.len() >= 14 => {
+                let packet = TcpPacket::new(payload);
+                self.push_u16(consts::TCP_SOURCE, packet.src_port());
+                self.push_u16(consts::TCP_DESTINATION, packet.dst_port());
+                self.push_u16(
+                    consts::TCP_FLAGS,
+                    NetworkEndian::read_u16(&payload[12..14]) & 0x0fff,
+                );
+            }
+            IP_PROTO_UDP if payload.len() >= 4 => {
+                self.push(consts::UDP_SOURCE, &payload[0..2]);
+                self.push(consts::UDP_DESTINATION, &payload[2..4]);
+            }
+            IP_PROTO_SCTP if payload.len() >= 4 => {
+                self.push(consts::SCTP_SOURCE, &payload[0..2]);
+                self.push(consts::SCTP_DESTINATION, &payload[2..4]);
+            }
+            IP_PROTO_ICMP if !ipv6 && payload.len() >= 2 => {
+                self.push(consts::ICMP_TYPE, &payload[0..1]);
+                self.push(consts::ICMP_CODE, &payload[1..2]);
+            }
+            IP_PROTO_ICMPV6 if ipv6 && payload.len() >= 2 => self.icmpv6(payload),
+            _ => {}
+        }
+    }
+
+    fn icmpv6(&mut self, payload: &[u8]) {
+        let icmpv6_type = payload[0];
+        self.push(consts::ICMPV6_TYPE, &payload[0..1]);
+        self.push(consts::ICMPV6_CODE, &payload[1..2]);
+        if (icmpv6_type != ICMPV6_NEIGHBOR_SOLICIT && icmpv6_type != ICMPV6_NEIGHBOR_ADVERT)
+            || payload.len() < 24
+        {
+            return;
+        }
+        self.push(consts::IPV6_ND_TARGET, &payload[8..24]);
+
+        // The link-layer address is 0 if the corresponding option is absent.
+        let (option, field) = if icmpv6_type == ICMPV6_NEIGHBOR_SOLICIT {
+            (ND_OPTION_SOURCE_LL_ADDR, consts::IPV6_ND_SLL)
+        } else {
+            (ND_OPTION_TARGET_LL_ADDR, consts::IPV6_ND_TLL)
+        };
+        let mut ll_addr = [0; 6];
+        let mut options = &payload[24..];
+        while options.len() >= 8 {
+            let len = options[1] as usize * 8;
+            if len == 0 || options.len() < len {
+                break;
+            }
+            if options[0] == option {
+                ll_addr.copy_from_slice(&options[2..8]);
+                break;
+            }
+            options = &options[len..];
+        }
+        self.push(field, &ll_addr);
+    }
+
+    fn mpls(&mut self, payload: &[u8]) {
+        if payload.len() < 4 {
+            return;
+        }
+        let entry = NetworkEndian::read_u32(&payload[0..4]);
+        self.push_u32(consts::MPLS_LABEL, entry >> 12);
+        self.push(consts::MPLS_TC, &[((entry >> 9) & 0b111) as u8]);
+        self.push(consts::MPLS_BOS, &[((entry >> 8) & 1) as u8]);
+    }
+
+    fn pbb(&mut self, payload: &[u8]) {
+        if payload.len() < 4 {
+            return;
+        }
+        self.push(consts::PBB_ISID, &payload[1..4]);
+        self.push(consts::PBB_UCA, &[(payload[0] >> 3) & 1]);
+    }
+}
+
+impl<E: Repr + Clone> FlowMatch<E> {
+    /// Build a flow match that matches exactly the headers of the given Ethernet frame: Ethernet
+    /// addresses and type, VLAN, IP addresses, DSCP, ECN and protocol, transport ports, ICMP type
+    /// and code, ARP fields, IPv6 flow label and neighbor discovery fields, MPLS and PBB fields.
+    /// The TCP flags are left out, since they vary between the packets of a connection.
+    ///
+    /// `pipeline_fields` are the pipeline fields associated with the frame, typically the flow
+    /// match of a packet-in message. They are put first in the result. If they contain a packet
+    /// type other than Ethernet, the frame is not dissected.
+    pub fn from_frame(frame: &[u8], pipeline_fields: &FlowMatch<E>) -> Self {
+        let mut oxms = pipeline_fields.0.clone();
+        let mut pipeline = FrameFields(Vec::new());
+        for oxm in &pipeline_fields.0 {
+            if let Oxm::FlowMatchField(ref field) = *oxm {
+                if let Ok(bytes) = fields::encode(field) {
+                    let packet = Packet::new(&bytes[..]);
+                    pipeline.push(packet.field(), packet.value());
+                }
+            }
+        }
+        if pipeline.get(consts::PACKET_TYPE).unwrap_or(&[0; 4]) != [0; 4] {
+            return FlowMatch(oxms);
+        }
+
+        for (field, value) in FrameFields::dissect(frame).0 {
+            if field == consts::TCP_FLAGS || pipeline.get(field).is_some() {
+                continue;
+            }
+            if let Ok(field) = fields::decode(field, &value, None) {
+                oxms.push(Oxm::FlowMatchField(field));
+            }
+        }
+        FlowMatch(oxms)
+    }
+}
+
+impl<E: Repr> FlowMatch<E> {
+    /// Return `true` if the given Ethernet frame, with the given pipeline fields, matches this flow
+    /// match.
+    ///
+    /// Packet registers are considered to be zero, as they are at the beginning of the pipeline.
+    /// Experimenter, NXM and unknown fields cannot be evaluated, and never match.
+    pub fn matches(&self, frame: &[u8], pipeline: &PipelineFields) -> bool {
+        let mut fields = FrameFields::dissect(frame);
+        fields.push_pipeline(pipeline);
+        self.0.iter().all(|oxm| match *oxm {
+            Oxm::FlowMatchField(FlowMatchField::Unknown { .. }) => false,
+            Oxm::FlowMatchField(ref field) => fields.matches(field),
+            Oxm::PacketRegisters(ref register) => {
+                register.value & register.mask.unwrap_or(!0) == 0
+            }
+            Oxm::Experimenter(_) => false,
+            Oxm::Nxm(_) => false,
+            Oxm::Unknown { .. } => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use oxm::*;
+    use smoltcp::wire::{ArpOperation, EthernetAddress, Ipv4Address};
+
+    fn flow_match(fields: Vec<FlowMatchField>) -> FlowMatch {
+        FlowMatch(fields.into_iter().map(Oxm::FlowMatchField).collect())
+    }
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    static TCP_FRAME: [u8; 58] = [
+        // ethernet
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, // destination
+        0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, // source
+        0x81, 0x00,                         // ethertype = vlan
+        0xa0, 0x64,                         // pcp = 5, vid = 100
+        0x08, 0x00,                         // ethertype = ipv4
+        // ipv4
+        0x45, 0xb8, 0x00, 0x28,             // version, ihl, dscp = 46, ecn = 0, total length
+        0x00, 0x00, 0x40, 0x00,             // identification, flags, fragment offset
+        0x40, 0x06, 0x00, 0x00,             // ttl, protocol = tcp, checksum
+        0x0a, 0x00, 0x00, 0x01,             // source
+        0xc0, 0xa8, 0x01, 0x02,             // destination
+        // tcp
+        0x04, 0xd2, 0x00, 0x50,             // source port = 1234, destination port = 80
+        0x00, 0x00, 0x00, 0x00,             // sequence number
+        0x00, 0x00, 0x00, 0x00,             // ack number
+        0x50, 0x02, 0xff, 0xff,             // data offset, flags = syn, window
+        0x00, 0x00, 0x00, 0x00,             // checksum, urgent pointer
+    ];
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    static ARP_FRAME: [u8; 42] = [
+        // ethernet
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // destination
+        0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, // source
+        0x08, 0x06,                         // ethertype = arp
+        // arp
+        0x00, 0x01, 0x08, 0x00,             // hardware type, protocol type
+        0x06, 0x04, 0x00, 0x01,             // hardware len, protocol len, operation = request
+        0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, // sender hardware address
+        0x0a, 0x00, 0x00, 0x01,             // sender protocol address
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // target hardware address
+        0x0a, 0x00, 0x00, 0x02,             // target protocol address
+    ];
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    static NEIGHBOR_SOLICIT_FRAME: [u8; 86] = [
+        // ethernet
+        0x33, 0x33, 0xff, 0x00, 0x00, 0x02, // destination
+        0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, // source
+        0x86, 0xdd,                         // ethertype = ipv6
+        // ipv6
+        0x60, 0x00, 0x00, 0x00,             // version, traffic class, flow label
+        0x00, 0x20, 0x3a, 0xff,             // payload length, next header = icmpv6, hop limit
+        0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // source
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0xff, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // destination
+        0x00, 0x00, 0x00, 0x01, 0xff, 0x00, 0x00, 0x02,
+        // icmpv6
+        0x87, 0x00, 0x00, 0x00,             // type = neighbor solicitation, code, checksum
+        0x00, 0x00, 0x00, 0x00,             // reserved
+        0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // target
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        0x01, 0x01,                         // option = source link-layer address, length
+        0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, // link-layer address
+    ];
+
+    #[test]
+    fn test_match_tcp() {
+        let pipeline = PipelineFields {
+            in_port: 3,
+            ..Default::default()
+        };
+        let repr = flow_match(vec![
+            FlowMatchField::InPort(InPort::new(3)),
+            FlowMatchField::EthernetDestination(EthernetDestination::new(
+                EthernetAddress([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]),
+                None,
+            )),
+            FlowMatchField::VlanId(VlanId::new(0x1000 | 100, None)),
+            FlowMatchField::VlanPriority(VlanPriority::new(5)),
+            FlowMatchField::EthernetType(EthernetType::new(EthernetProtocol::Ipv4)),
+            FlowMatchField::IpDscp(IpDscp::new(46)),
+            FlowMatchField::IpProtocol(IpProtocol::new(6)),
+            FlowMatchField::Ipv4Source(Ipv4Source::new(
+                Ipv4Address([10, 0, 0, 0]),
+                Some(Ipv4Address([255, 0, 0, 0])),
+            )),
+            FlowMatchField::TcpDestination(TcpDestination::new(80)),
+            FlowMatchField::TcpFlags(TcpFlags::new(0x002, Some(0x012))),
+        ]);
+        assert!(repr.matches(&TCP_FRAME, &pipeline));
+
+        let other_port = PipelineFields {
+            in_port: 4,
+            ..Default::default()
+        };
+        assert!(!repr.matches(&TCP_FRAME, &other_port));
+    }
+
+    #[test]
+    fn test_no_match() {
+        let pipeline = PipelineFields::default();
+        for field in &[
+            FlowMatchField::Ipv4Source(Ipv4Source::new(
+                Ipv4Address([10, 0, 1, 0]),
+                Some(Ipv4Address([255, 255, 255, 0])),
+            )),
+            FlowMatchField::TcpSource(TcpSource::new(80)),
+            FlowMatchField::UdpSource(UdpSource::new(1234)),
+            FlowMatchField::VlanId(VlanId::new(0, None)),
+            FlowMatchField::Metadata(Metadata::new(1, None)),
+        ] {
+            assert!(!flow_match(vec![field.clone()]).matches(&TCP_FRAME, &pipeline));
+        }
+        // Any VLAN matches
+        let repr = flow_match(vec![FlowMatchField::VlanId(VlanId::new(0x1000, Some(0x1000)))]);
+        assert!(repr.matches(&TCP_FRAME, &pipeline));
+        assert!(!repr.matches(&ARP_FRAME, &pipeline));
+    }
+
+    #[test]
+    fn test_match_truncated() {
+        let pipeline = PipelineFields::default();
+        let repr = flow_match(vec![FlowMatchField::TcpSource(TcpSource::new(1234))]);
+        assert!(repr.matches(&TCP_FRAME, &pipeline));
+        assert!(!repr.matches(&TCP_FRAME[..50], &pipeline));
+
+        let repr = flow_match(vec![FlowMatchField::Ipv4Destination(Ipv4Destination::new(
+            Ipv4Address([192, 168, 1, 2]),
+            None,
+        ))]);
+        assert!(repr.matches(&TCP_FRAME[..50], &pipeline));
+    }
+
+    #[test]
+    fn test_match_arp() {
+        let repr = flow_match(vec![
+            FlowMatchField::EthernetType(EthernetType::new(EthernetProtocol::Arp)),
+            FlowMatchField::ArpOpCode(ArpOpCode::new(ArpOperation::Request)),
+            FlowMatchField::ArpTpa(ArpTpa::new(Ipv4Address([10, 0, 0, 2]), None)),
+            FlowMatchField::ArpSha(ArpSha::new(
+                EthernetAddress([0x66, 0x77, 0x88, 0x00, 0x00, 0x00]),
+                Some(EthernetAddress([0xff, 0xff, 0xff, 0x00, 0x00, 0x00])),
+            )),
+        ]);
+        assert!(repr.matches(&ARP_FRAME, &PipelineFields::default()));
+    }
+
+    #[test]
+    fn test_match_neighbor_solicit() {
+        let mut target = [0; 16];
+        target[0] = 0xfe;
+        target[1] = 0x80;
+        target[15] = 0x02;
+        let repr = flow_match(vec![
+            FlowMatchField::EthernetType(EthernetType::new(EthernetProtocol::Unknown(0x86dd))),
+            FlowMatchField::IpProtocol(IpProtocol::new(58)),
+            FlowMatchField::Icmpv6Type(Icmpv6Type::new(135)),
+            FlowMatchField::Ipv6NdTarget(Ipv6NdTarget::new(target)),
+            FlowMatchField::Ipv6NdSll(Ipv6NdSll::new(EthernetAddress([
+                0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb,
+            ]))),
+            FlowMatchField::Ipv6ExtensionHeader(Ipv6ExtensionHeader::new(0, None)),
+        ]);
+        assert!(repr.matches(&NEIGHBOR_SOLICIT_FRAME, &PipelineFields::default()));
+    }
+
+    #[test]
+    fn test_from_frame() {
+        let pipeline_fields = flow_match(vec![FlowMatchField::InPort(InPort::new(3))]);
+        let repr = FlowMatch::from_frame(&TCP_FRAME, &pipeline_fields);
+        let expected = flow_match(vec![
+            FlowMatchField::InPort(InPort::new(3)),
+            FlowMatchField::EthernetDestination(EthernetDestination::new(
+                EthernetAddress([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]),
+                None,
+            )),
+            FlowMatchField::EthernetSource(EthernetSource::new(
+                EthernetAddress([0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb]),
+                None,
+            )),
+            FlowMatchField::VlanId(VlanId::new(0x1000 | 100, None)),
+            FlowMatchField::VlanPriority(VlanPriority::new(5)),
+            FlowMatchField::EthernetType(EthernetType::new(EthernetProtocol::Ipv4)),
+            FlowMatchField::IpDscp(IpDscp::new(46)),
+            FlowMatchField::IpEcn(IpEcn::new(0)),
+            FlowMatchField::IpProtocol(IpProtocol::new(6)),
+            FlowMatchField::Ipv4Source(Ipv4Source::new(Ipv4Address([10, 0, 0, 1]), None)),
+            FlowMatchField::Ipv4Destination(Ipv4Destination::new(
+                Ipv4Address([192, 168, 1, 2]),
+                None,
+            )),
+            FlowMatchField::TcpSource(TcpSource::new(1234)),
+            FlowMatchField::TcpDestination(TcpDestination::new(80)),
+        ]);
+        assert_eq!(repr, expected);
+        assert_eq!(repr.validate(), Ok(()));
+        let pipeline = PipelineFields {
+            in_port: 3,
+            ..Default::default()
+        };
+        assert!(repr.matches(&TCP_FRAME, &pipeline));
+    }
+
+    #[test]
+    fn test_from_frame_ipv6() {
+        let pipeline_fields = flow_match(vec![]);
+        let repr = FlowMatch::from_frame(&NEIGHBOR_SOLICIT_FRAME, &pipeline_fields);
+        assert_eq!(repr.validate(), Ok(()));
+        assert!(repr.matches(&NEIGHBOR_SOLICIT_FRAME, &PipelineFields::default()));
+        assert!(!repr.matches(&ARP_FRAME, &PipelineFields::default()));
+    }
+
+    #[test]
+    fn test_from_frame_packet_type() {
+        let pipeline_fields = flow_match(vec![
+            FlowMatchField::PacketType(PacketType::new(0x0001_0800)),
+        ]);
+        let repr = FlowMatch::from_frame(&TCP_FRAME[18..], &pipeline_fields);
+        assert_eq!(repr, pipeline_fields);
+    }
+
+    #[test]
+    fn test_match_registers() {
+        let repr = FlowMatch::<ExperimenterOxm>(vec![
+            Oxm::PacketRegisters(PacketRegisters {
+                field: 0,
+                value: 0,
+                mask: None,
+            }),
+        ]);
+        assert!(repr.matches(&ARP_FRAME, &PipelineFields::default()));
+    }
+}
@@ -0,0 +1,230 @@
+//! OXM IDs: OXM headers without value.
+//!
+//! Some messages, like the table features properties, describe the OXM fields a switch supports
+//! with lists of OXM headers. Such a header has the same format as the header of an OXM field,
+//! and its `oxm_length` is the length of the value the field would have, but no value follows.
+//! The header of an experimenter field is followed by its 32 bits experimenter ID.
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use {Error, Repr, Result};
+use super::{Oxm, Packet, CLASS_EXPERIMENTER, OXM_HEADER_LEN};
+
+const EXPERIMENTER_LEN: usize = 4;
+
+/// An OXM ID, _i.e._ the header of an OXM field.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum OxmId {
+    /// The header of a field that is not an experimenter field.
+    Header {
+        class: u16,
+        field: u8,
+        has_mask: bool,
+        /// Length of the value of the field, including the mask if any.
+        length: u8,
+    },
+    /// The header of an experimenter field (_i.e._ with `oxm_class` equal to
+    /// [`CLASS_EXPERIMENTER`](constant.CLASS_EXPERIMENTER.html)), and its experimenter ID.
+    Experimenter {
+        field: u8,
+        has_mask: bool,
+        /// Length of the value of the field, including the experimenter ID and the mask if any.
+        length: u8,
+        experimenter: u32,
+    },
+}
+
+impl OxmId {
+    /// Return the ID of the given OXM field.
+    pub fn of<E: Repr>(oxm: &Oxm<E>) -> Result<Self> {
+        let mut bytes = vec![0; oxm.buffer_len()];
+        oxm.emit(&mut bytes)?;
+        OxmId::parse(&bytes)
+    }
+
+    /// Return the `oxm_class` of this ID.
+    pub fn class(&self) -> u16 {
+        match *self {
+            OxmId::Header { class, .. } => class,
+            OxmId::Experimenter { .. } => CLASS_EXPERIMENTER,
+        }
+    }
+
+    /// Return the `oxm_field` of this ID.
+    pub fn field(&self) -> u8 {
+        match *self {
+            OxmId::Header { field, .. } | OxmId::Experimenter { field, .. } => field,
+        }
+    }
+
+    /// Return `true` if this ID has the `HM` flag set.
+    pub fn has_mask(&self) -> bool {
+        match *self {
+            OxmId::Header { has_mask, .. } | OxmId::Experimenter { has_mask, .. } => has_mask,
+        }
+    }
+
+    /// Return the `oxm_length` of this ID.
+    pub fn length(&self) -> u8 {
+        match *self {
+            OxmId::Header { length, .. } | OxmId::Experimenter { length, .. } => length,
+        }
+    }
+
+    /// Parse a list of OXM IDs that fills the whole buffer.
+    pub fn parse_list(buffer: &[u8]) -> Result<Vec<OxmId>> {
+        let mut ids = Vec::new();
+        let mut offset = 0;
+        while offset < buffer.len() {
+            let id = OxmId::parse(&buffer[offset..])?;
+            offset += id.buffer_len();
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+
+    /// Return the length of a list of OXM IDs.
+    pub fn list_buffer_len(ids: &[OxmId]) -> usize {
+        ids.iter().map(OxmId::buffer_len).sum()
+    }
+
+    /// Emit a list of OXM IDs one after the other.
+    pub fn emit_list(ids: &[OxmId], buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < OxmId::list_buffer_len(ids) {
+            return Err(Error::Exhausted);
+        }
+        let mut offset = 0;
+        for id in ids {
+            id.emit(&mut buffer[offset..])?;
+            offset += id.buffer_len();
+        }
+        Ok(())
+    }
+}
+
+impl Repr for OxmId {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        // Packet::new_checked() cannot be used, since no value follows the header.
+        if buffer.len() < OXM_HEADER_LEN {
+            return Err(Error::Truncated);
+        }
+        let packet = Packet::new(buffer);
+        if packet.class() != CLASS_EXPERIMENTER {
+            return Ok(OxmId::Header {
+                class: packet.class(),
+                field: packet.field(),
+                has_mask: packet.has_mask(),
+                length: packet.length(),
+            });
+        }
+        if buffer.len() < OXM_HEADER_LEN + EXPERIMENTER_LEN {
+            return Err(Error::Truncated);
+        }
+        if (packet.length() as usize) < EXPERIMENTER_LEN {
+            return Err(Error::Malformed);
+        }
+        Ok(OxmId::Experimenter {
+            field: packet.field(),
+            has_mask: packet.has_mask(),
+            length: packet.length(),
+            experimenter: NetworkEndian::read_u32(
+                &buffer[OXM_HEADER_LEN..OXM_HEADER_LEN + EXPERIMENTER_LEN],
+            ),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        match *self {
+            OxmId::Header { .. } => OXM_HEADER_LEN,
+            OxmId::Experimenter { .. } => OXM_HEADER_LEN + EXPERIMENTER_LEN,
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        if let OxmId::Header { class, .. } = *self {
+            if class == CLASS_EXPERIMENTER {
+                return Err(Error::BadOxmClass);
+            }
+        }
+        if let OxmId::Experimenter { experimenter, .. } = *self {
+            NetworkEndian::write_u32(
+                &mut buffer[OXM_HEADER_LEN..OXM_HEADER_LEN + EXPERIMENTER_LEN],
+                experimenter,
+            );
+        }
+        let mut packet = Packet::new(&mut buffer[..OXM_HEADER_LEN]);
+        packet.set_class(self.class());
+        packet.set_field(self.field());
+        packet.set_length(self.length());
+        if self.has_mask() {
+            packet.set_mask();
+        } else {
+            packet.unset_mask();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use oxm::*;
+
+    static BYTES: [u8; 16] = [
+        0x80, 0x00, 0x00, 0x04, // OXM_OF_IN_PORT
+        0x80, 0x00, 0x17, 0x08, // OXM_OF_IPV4_SRC_W
+        0xff, 0xff, 0x54, 0x06, // ONFOXM_ET_TCP_FLAGS
+        0x4f, 0x4e, 0x46, 0x00,
+    ];
+
+    fn ids() -> Vec<OxmId> {
+        vec![
+            OxmId::Header {
+                class: CLASS_OPEN_FLOW_BASIC,
+                field: consts::IN_PORT,
+                has_mask: false,
+                length: 4,
+            },
+            OxmId::Header {
+                class: CLASS_OPEN_FLOW_BASIC,
+                field: consts::IPV4_SOURCE,
+                has_mask: true,
+                length: 8,
+            },
+            OxmId::Experimenter {
+                field: experimenter::ONF_TCP_FLAGS,
+                has_mask: false,
+                length: 6,
+                experimenter: experimenter::ONF_EXPERIMENTER_ID,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(OxmId::parse_list(&BYTES), Ok(ids()));
+        assert_eq!(OxmId::parse_list(&BYTES[..14]), Err(Error::Truncated));
+    }
+
+    #[test]
+    fn test_emit_list() {
+        let ids = ids();
+        let mut buffer = vec![0; OxmId::list_buffer_len(&ids)];
+        OxmId::emit_list(&ids, &mut buffer).unwrap();
+        assert_eq!(&buffer[..], &BYTES[..]);
+    }
+
+    #[test]
+    fn test_of() {
+        let oxm: Oxm = Oxm::FlowMatchField(FlowMatchField::InPort(InPort::new(3)));
+        assert_eq!(OxmId::of(&oxm), Ok(ids()[0]));
+        let oxm: Oxm = Oxm::Experimenter(ExperimenterOxm::OnfTcpFlags {
+            value: 0x12,
+            mask: None,
+        });
+        assert_eq!(OxmId::of(&oxm), Ok(ids()[2]));
+    }
+}
@@ -24,8 +24,15 @@
 //! - [`CLASS_OPEN_FLOW_BASIC`](constant.CLASS_OPEN_FLOW_BASIC.html) which correspond to a regular
 //!   flow match field. Such fields are represented by the [`FlowMatchField`](enum.FlowMatchField.html)
 //!   enum.
-//! - [`CLASS_PACKET_REGISTERS`](constant.CLASS_PACKET_REGISTERS.html)
-//! - [`CLASS_EXPERIMENTER`](constant.CLASS_EXPERIMENTER.html)
+//! - [`CLASS_PACKET_REGISTERS`](constant.CLASS_PACKET_REGISTERS.html). The packet registers are
+//!   represented by [`PacketRegisters`](struct.PacketRegisters.html).
+//! - [`CLASS_EXPERIMENTER`](constant.CLASS_EXPERIMENTER.html). The experimenter fields are
+//!   represented by a type parameter, which defaults to the built-in
+//!   [`ExperimenterOxm`](enum.ExperimenterOxm.html).
+//!
+//! The most common [`CLASS_NXM1`](constant.CLASS_NXM1.html) fields used by Open vSwitch are also
+//! supported, see the [`nxm`](nxm/index.html) module. The `nxm` feature adds their `ovs-ofctl`
+//! names to the textual representation.
 //!
 //! ## `oxm_field`
 //!
@@ -48,18 +55,46 @@
 //!
 
 use {Error, Repr, Result};
-use byteorder::{ByteOrder, NetworkEndian};
 
 mod packet;
 pub use self::packet::Packet;
 
 mod fields;
-pub use self::fields::FlowMatchField;
+pub use self::fields::{FlowMatchField, FlowMatchFieldMaskedRepr, FlowMatchFieldRepr};
+pub use self::fields::consts;
 pub use self::fields::header::*;
 pub use self::fields::pipeline::*;
 
 mod flow_match;
 pub use self::flow_match::PacketRepr as FlowMatch;
+pub use self::flow_match::ParseOptions;
+pub use self::flow_match::{OxmsIterator, PacketReprRef as FlowMatchRef};
+
+mod validate;
+pub use self::validate::{ValidationError, ValidationErrorKind};
+
+mod frame;
+pub use self::frame::PipelineFields;
+
+mod algebra;
+mod canonical;
+
+mod builder;
+pub use self::builder::FlowMatchBuilder;
+
+mod text;
+
+pub mod experimenter;
+pub use self::experimenter::ExperimenterOxm;
+
+mod id;
+pub use self::id::OxmId;
+
+mod registers;
+pub use self::registers::PacketRegisters;
+
+pub mod nxm;
+pub use self::nxm::NxmField;
 
 /// Backward compatibility with NXM
 pub const CLASS_NXM0: u16 = 0x0000;
@@ -76,7 +111,7 @@ const OXM_HEADER_LEN: usize = 4;
 
 /// Represent an OXM field
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum Oxm<E> {
+pub enum Oxm<E = ExperimenterOxm> {
     /// Represent an flow match field OXM field (_i.e._ with `oxm_class` equal to
     /// [`CLASS_OPEN_FLOW_BASIC`](constant.CLASS_OPEN_FLOW_BASIC.html)
     FlowMatchField(FlowMatchField),
@@ -86,6 +121,35 @@ pub enum Oxm<E> {
     /// Represent a packet registers OXM packet (_i.e._ with `oxm_class` equal to
     /// [`CLASS_PACKET_REGISTERS`](constant.CLASS_PACKET_REGISTERS.html)
     PacketRegisters(PacketRegisters),
+    /// Represent an NXM field (_i.e._ with `oxm_class` equal to
+    /// [`CLASS_NXM1`](constant.CLASS_NXM1.html).
+    Nxm(NxmField),
+    /// An OXM field that could not be parsed, kept as is so that it can be emitted back. Such
+    /// fields are only produced by [lenient](struct.ParseOptions.html#method.lenient) parsing.
+    /// `value` contains the mask if `has_mask` is set.
+    Unknown {
+        class: u16,
+        field: u8,
+        has_mask: bool,
+        value: Vec<u8>,
+    },
+}
+
+impl<E: Repr> Oxm<E> {
+    /// Parse an OXM field. If `lenient` is `true`, a field whose header is valid but that cannot
+    /// be parsed is returned as [`Oxm::Unknown`](#variant.Unknown).
+    fn parse_lenient(buffer: &[u8], lenient: bool) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        match Oxm::parse(buffer) {
+            Err(_) if lenient => Ok(Oxm::Unknown {
+                class: packet.class(),
+                field: packet.field(),
+                has_mask: packet.has_mask(),
+                value: packet.value()[..packet.length() as usize].to_vec(),
+            }),
+            result => result,
+        }
+    }
 }
 
 impl<E: Repr> Repr for Oxm<E> {
@@ -96,7 +160,8 @@ impl<E: Repr> Repr for Oxm<E> {
             CLASS_OPEN_FLOW_BASIC => Ok(Oxm::FlowMatchField(FlowMatchField::parse(&packet)?)),
             CLASS_PACKET_REGISTERS => Ok(Oxm::PacketRegisters(PacketRegisters::parse(&packet)?)),
             CLASS_EXPERIMENTER => Ok(Oxm::Experimenter(E::parse(packet.into_inner())?)),
-            CLASS_NXM0 | CLASS_NXM1 => Err(Error::UnsupportedOxmClass),
+            CLASS_NXM1 => Ok(Oxm::Nxm(NxmField::parse(&packet)?)),
+            CLASS_NXM0 => Err(Error::UnsupportedOxmClass),
             _ => Err(Error::BadOxmClass),
         }
     }
@@ -106,6 +171,8 @@ impl<E: Repr> Repr for Oxm<E> {
             Oxm::FlowMatchField(ref repr) => repr.buffer_len(),
             Oxm::PacketRegisters(ref repr) => repr.buffer_len(),
             Oxm::Experimenter(ref repr) => repr.buffer_len(),
+            Oxm::Nxm(ref repr) => repr.buffer_len(),
+            Oxm::Unknown { ref value, .. } => OXM_HEADER_LEN + value.len(),
         }
     }
 
@@ -114,69 +181,65 @@ impl<E: Repr> Repr for Oxm<E> {
             Oxm::FlowMatchField(ref repr) => repr.emit(buffer),
             Oxm::PacketRegisters(ref repr) => repr.emit(buffer),
             Oxm::Experimenter(ref repr) => repr.emit(buffer),
+            Oxm::Nxm(ref repr) => repr.emit(buffer),
+            Oxm::Unknown {
+                class,
+                field,
+                has_mask,
+                ref value,
+            } => emit_raw(class, field, has_mask, value, buffer),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct PacketRegisters {
-    pub field: u8,
-    pub value: u64,
-    pub mask: Option<u64>,
+/// Write an OXM field from its header fields and its raw value, which contains the mask if
+/// `has_mask` is set.
+fn emit_raw(class: u16, field: u8, has_mask: bool, value: &[u8], buffer: &mut [u8]) -> Result<()> {
+    if value.len() > 0xff {
+        return Err(Error::Malformed);
+    }
+    if buffer.len() < OXM_HEADER_LEN + value.len() {
+        return Err(Error::Exhausted);
+    }
+    let mut packet = Packet::new(&mut buffer[..OXM_HEADER_LEN + value.len()]);
+    packet.set_class(class);
+    packet.set_field(field);
+    packet.set_length(value.len() as u8);
+    if has_mask {
+        packet.set_mask();
+    } else {
+        packet.unset_mask();
+    }
+    packet.value_mut().copy_from_slice(value);
+    Ok(())
 }
 
-impl PacketRegisters {
-    fn parse<'a, T: AsRef<[u8]> + ?Sized>(packet: &Packet<&'a T>) -> Result<Self> {
-        // check that the length field should be 8 or 16 depending on whether there is a mask.
-        let len_field = packet.length();
-        if len_field != 8 && len_field != 16 {
-            return Err(Error::Malformed);
-        }
-        // We know that the length is correct per above check,
-        // and that the inner buffer has enough bytes since `Packet.check_len()` is called
-        // before this method is called.
-        let buf = packet.value();
-        let value = NetworkEndian::read_u64(&buf[0..8]);
-        let mask = if packet.has_mask() {
-            Some(NetworkEndian::read_u64(&buf[8..16]))
-        } else {
-            None
-        };
-        Ok(PacketRegisters {
-            field: packet.field(),
-            value: value,
-            mask: mask,
-        })
+/// Return the mask of the given prefix length, for an address of `len` bytes, or `None` if the
+/// prefix is longer than the address.
+fn prefix_mask(prefix_len: usize, len: usize) -> Option<Vec<u8>> {
+    if prefix_len > 8 * len {
+        return None;
     }
+    Some(
+        (0..len)
+            .map(|i| (0xff00u16 >> prefix_len.saturating_sub(8 * i).min(8)) as u8)
+            .collect(),
+    )
+}
 
-    fn buffer_len(&self) -> usize {
-        if self.mask.is_some() {
-            // 4 bytes header + 8 bytes value + 8 bytes mask
-            20
-        } else {
-            // 4 bytes header + 8 bytes value
-            12
-        }
+/// Return the length of the prefix if `mask` is a prefix mask. This is the inverse of
+/// [`prefix_mask`](fn.prefix_mask.html).
+fn prefix_len(mask: &[u8]) -> Option<usize> {
+    let len = mask.iter().map(|byte| byte.count_ones() as usize).sum();
+    if prefix_mask(len, mask.len()) == Some(mask.to_vec()) {
+        Some(len)
+    } else {
+        None
     }
+}
 
-    fn emit(&self, buf: &mut [u8]) -> Result<()> {
-        if self.buffer_len() > buf.len() {
-            return Err(Error::Exhausted);
-        }
-        let mut packet = Packet::new(buf);
-        packet.set_class(CLASS_PACKET_REGISTERS);
-        packet.set_field(self.field);
-        packet.set_length(self.buffer_len() as u8);
-        if self.mask.is_some() {
-            packet.set_mask();
-        } else {
-            packet.unset_mask();
-        }
-        let buf = packet.value_mut();
-        NetworkEndian::write_u64(&mut buf[0..8], self.value);
-        if let Some(mask) = self.mask {
-            NetworkEndian::write_u64(&mut buf[8..16], mask);
-        }
-        Ok(())
+impl<E> From<PacketRegisters> for Oxm<E> {
+    fn from(registers: PacketRegisters) -> Self {
+        Oxm::PacketRegisters(registers)
     }
 }
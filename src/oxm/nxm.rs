@@ -0,0 +1,319 @@
+//! Nicira eXtensible Match (NXM) fields.
+//!
+//! NXM is the match format Open vSwitch used before OXM was standardized. OXM kept its wire
+//! format, and Open vSwitch still uses NXM fields (with `oxm_class` equal to
+//! [`CLASS_NXM1`](../constant.CLASS_NXM1.html)) for the fields that have no OpenFlow equivalent,
+//! even inside OpenFlow 1.5 matches. The most common ones are represented by
+//! [`NxmField`](enum.NxmField.html).
+//!
+//! Their `ovs-ofctl` names, like `reg0` or `ct_state`, are only used in the textual
+//! representation of flow matches with the `nxm` feature. Without it, they are written like
+//! unknown fields.
+
+use byteorder::{ByteOrder, NetworkEndian};
+use smoltcp::wire::Ipv4Address;
+
+use {Error, Result};
+use super::{Packet, CLASS_NXM1, OXM_HEADER_LEN};
+
+/// `oxm_field` values of the supported NXM fields, and flags of the connection tracking state.
+pub mod consts {
+    /// `NXM_NX_REG0`. The sixteen registers `NXM_NX_REG0` to `NXM_NX_REG15` have consecutive
+    /// `oxm_field` values.
+    pub const REG0: u8 = 0;
+    /// `NXM_NX_REG15`
+    pub const REG15: u8 = 15;
+    /// `NXM_NX_TUN_IPV4_SRC`
+    pub const TUN_IPV4_SRC: u8 = 31;
+    /// `NXM_NX_TUN_IPV4_DST`
+    pub const TUN_IPV4_DST: u8 = 32;
+    /// `NXM_NX_CT_STATE`
+    pub const CT_STATE: u8 = 105;
+    /// `NXM_NX_CT_ZONE`
+    pub const CT_ZONE: u8 = 106;
+    /// `NXM_NX_CT_MARK`
+    pub const CT_MARK: u8 = 107;
+
+    /// The connection is new.
+    pub const CT_STATE_NEW: u32 = 1 << 0;
+    /// The connection is established.
+    pub const CT_STATE_ESTABLISHED: u32 = 1 << 1;
+    /// The connection is related to an established connection.
+    pub const CT_STATE_RELATED: u32 = 1 << 2;
+    /// The packet is in the reply direction.
+    pub const CT_STATE_REPLY: u32 = 1 << 3;
+    /// The packet is invalid.
+    pub const CT_STATE_INVALID: u32 = 1 << 4;
+    /// The packet went through the connection tracker.
+    pub const CT_STATE_TRACKED: u32 = 1 << 5;
+    /// The source address or port of the packet was translated.
+    pub const CT_STATE_SOURCE_NAT: u32 = 1 << 6;
+    /// The destination address or port of the packet was translated.
+    pub const CT_STATE_DESTINATION_NAT: u32 = 1 << 7;
+}
+
+/// Represent an NXM field (_i.e._ with `oxm_class` equal to
+/// [`CLASS_NXM1`](../constant.CLASS_NXM1.html)).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum NxmField {
+    /// `NXM_NX_REG0` to `NXM_NX_REG15`: 32 bits registers, initially zero. `index` is between 0
+    /// and 15.
+    Register {
+        index: u8,
+        value: u32,
+        mask: Option<u32>,
+    },
+    /// `NXM_NX_TUN_IPV4_SRC`: source address of the tunnel the packet was received on.
+    TunnelIpv4Source {
+        value: Ipv4Address,
+        mask: Option<Ipv4Address>,
+    },
+    /// `NXM_NX_TUN_IPV4_DST`: destination address of the tunnel the packet was received on.
+    TunnelIpv4Destination {
+        value: Ipv4Address,
+        mask: Option<Ipv4Address>,
+    },
+    /// `NXM_NX_CT_STATE`: connection tracking state, made of the
+    /// [`CT_STATE_XXX`](consts/index.html) flags.
+    CtState { value: u32, mask: Option<u32> },
+    /// `NXM_NX_CT_ZONE`: connection tracking zone. Not maskable.
+    CtZone(u16),
+    /// `NXM_NX_CT_MARK`: connection tracking mark.
+    CtMark { value: u32, mask: Option<u32> },
+}
+
+/// Return the length of the value of the given NXM field (without mask), and whether the field
+/// can be masked.
+pub fn value_len(field: u8) -> Option<(usize, bool)> {
+    Some(match field {
+        consts::REG0..=consts::REG15 => (4, true),
+        consts::TUN_IPV4_SRC | consts::TUN_IPV4_DST => (4, true),
+        consts::CT_STATE | consts::CT_MARK => (4, true),
+        consts::CT_ZONE => (2, false),
+        _ => return None,
+    })
+}
+
+impl NxmField {
+    pub fn parse<T: AsRef<[u8]> + ?Sized>(packet: &Packet<&T>) -> Result<Self> {
+        let (len, maskable) = value_len(packet.field()).ok_or(Error::BadOxmField)?;
+        if packet.has_mask() && !maskable {
+            return Err(Error::Malformed);
+        }
+        let expected_len = if packet.has_mask() { 2 * len } else { len };
+        if packet.length() as usize != expected_len {
+            return Err(Error::Malformed);
+        }
+        // `packet.value()` extends to the end of the buffer, which may hold other fields.
+        let (value, mask) = packet.value()[..expected_len].split_at(len);
+        let mask = if packet.has_mask() { Some(mask) } else { None };
+        let read_u32 = |bytes| NetworkEndian::read_u32(bytes);
+        Ok(match packet.field() {
+            field @ consts::REG0..=consts::REG15 => NxmField::Register {
+                index: field - consts::REG0,
+                value: read_u32(value),
+                mask: mask.map(read_u32),
+            },
+            consts::TUN_IPV4_SRC => NxmField::TunnelIpv4Source {
+                value: Ipv4Address::from_bytes(value),
+                mask: mask.map(Ipv4Address::from_bytes),
+            },
+            consts::TUN_IPV4_DST => NxmField::TunnelIpv4Destination {
+                value: Ipv4Address::from_bytes(value),
+                mask: mask.map(Ipv4Address::from_bytes),
+            },
+            consts::CT_STATE => NxmField::CtState {
+                value: read_u32(value),
+                mask: mask.map(read_u32),
+            },
+            consts::CT_ZONE => NxmField::CtZone(NetworkEndian::read_u16(value)),
+            consts::CT_MARK => NxmField::CtMark {
+                value: read_u32(value),
+                mask: mask.map(read_u32),
+            },
+            _ => unreachable!(),
+        })
+    }
+
+    /// Return the `oxm_field` value that correspond to this NXM field.
+    pub fn code(&self) -> u8 {
+        match *self {
+            NxmField::Register { index, .. } => consts::REG0 + index,
+            NxmField::TunnelIpv4Source { .. } => consts::TUN_IPV4_SRC,
+            NxmField::TunnelIpv4Destination { .. } => consts::TUN_IPV4_DST,
+            NxmField::CtState { .. } => consts::CT_STATE,
+            NxmField::CtZone(_) => consts::CT_ZONE,
+            NxmField::CtMark { .. } => consts::CT_MARK,
+        }
+    }
+
+    /// Return the value of this NXM field, and its mask if any, in network byte order.
+    fn value_and_mask(&self) -> (Vec<u8>, Option<Vec<u8>>) {
+        let u32_bytes = |value: u32| {
+            let mut bytes = vec![0; 4];
+            NetworkEndian::write_u32(&mut bytes, value);
+            bytes
+        };
+        match *self {
+            NxmField::Register { value, mask, .. }
+            | NxmField::CtState { value, mask }
+            | NxmField::CtMark { value, mask } => (u32_bytes(value), mask.map(u32_bytes)),
+            NxmField::TunnelIpv4Source { value, mask }
+            | NxmField::TunnelIpv4Destination { value, mask } => (
+                value.as_bytes().to_vec(),
+                mask.map(|mask| mask.as_bytes().to_vec()),
+            ),
+            NxmField::CtZone(value) => {
+                let mut bytes = vec![0; 2];
+                NetworkEndian::write_u16(&mut bytes, value);
+                (bytes, None)
+            }
+        }
+    }
+
+    pub fn buffer_len(&self) -> usize {
+        let (value, mask) = self.value_and_mask();
+        OXM_HEADER_LEN + value.len() + mask.map_or(0, |mask| mask.len())
+    }
+
+    pub fn emit(&self, buf: &mut [u8]) -> Result<()> {
+        if let NxmField::Register { index, .. } = *self {
+            if consts::REG0 + index > consts::REG15 {
+                return Err(Error::BadOxmField);
+            }
+        }
+        if self.buffer_len() > buf.len() {
+            return Err(Error::Exhausted);
+        }
+        let (value, mask) = self.value_and_mask();
+        let mut packet = Packet::new(&mut buf[..self.buffer_len()]);
+        packet.set_class(CLASS_NXM1);
+        packet.set_field(self.code());
+        packet.set_length((value.len() + mask.as_ref().map_or(0, |mask| mask.len())) as u8);
+        if mask.is_some() {
+            packet.set_mask();
+        } else {
+            packet.unset_mask();
+        }
+        let buf = packet.value_mut();
+        buf[..value.len()].copy_from_slice(&value);
+        if let Some(mask) = mask {
+            buf[value.len()..].copy_from_slice(&mask);
+        }
+        Ok(())
+    }
+}
+
+/// Parse an NXM field from its `oxm_field`, its value and its optional mask.
+pub fn decode(field: u8, value: &[u8], mask: Option<&[u8]>) -> Result<NxmField> {
+    let mask = mask.unwrap_or(&[]);
+    let mut bytes = vec![0; OXM_HEADER_LEN + value.len() + mask.len()];
+    {
+        let mut packet = Packet::new(&mut bytes[..]);
+        packet.set_class(CLASS_NXM1);
+        packet.set_field(field);
+        if mask.is_empty() {
+            packet.unset_mask();
+        } else {
+            packet.set_mask();
+        }
+        packet.set_length((value.len() + mask.len()) as u8);
+        let buf = packet.value_mut();
+        buf[..value.len()].copy_from_slice(value);
+        buf[value.len()..].copy_from_slice(mask);
+    }
+    NxmField::parse(&Packet::new(&bytes[..]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(bytes: &[u8]) -> Result<NxmField> {
+        NxmField::parse(&Packet::new_checked(bytes)?)
+    }
+
+    fn emit(field: &NxmField) -> Vec<u8> {
+        let mut bytes = vec![0; field.buffer_len()];
+        field.emit(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_register() {
+        let bytes = [0x00, 0x01, 0x05, 0x08, 0, 0, 0, 0x2a, 0, 0, 0, 0xff];
+        let field = NxmField::Register {
+            index: 2,
+            value: 42,
+            mask: Some(0xff),
+        };
+        assert_eq!(parse(&bytes), Ok(field.clone()));
+        assert_eq!(emit(&field), bytes);
+    }
+
+    #[test]
+    fn test_tunnel_ipv4_source() {
+        let bytes = [0x00, 0x01, 0x3e, 0x04, 192, 168, 2, 3];
+        let field = NxmField::TunnelIpv4Source {
+            value: Ipv4Address([192, 168, 2, 3]),
+            mask: None,
+        };
+        assert_eq!(parse(&bytes), Ok(field.clone()));
+        assert_eq!(emit(&field), bytes);
+    }
+
+    #[test]
+    fn test_ct_fields() {
+        let bytes = [0x00, 0x01, 0xd3, 0x08, 0, 0, 0, 0x21, 0, 0, 0, 0x21];
+        let field = NxmField::CtState {
+            value: consts::CT_STATE_TRACKED | consts::CT_STATE_NEW,
+            mask: Some(consts::CT_STATE_TRACKED | consts::CT_STATE_NEW),
+        };
+        assert_eq!(parse(&bytes), Ok(field.clone()));
+        assert_eq!(emit(&field), bytes);
+
+        let bytes = [0x00, 0x01, 0xd4, 0x02, 0x00, 0x05];
+        assert_eq!(parse(&bytes), Ok(NxmField::CtZone(5)));
+        assert_eq!(emit(&NxmField::CtZone(5)), bytes);
+    }
+
+    #[test]
+    fn test_followed_by_other_fields() {
+        use oxm::{FlowMatch, FlowMatchField, InPort, Oxm};
+        use Repr;
+
+        let bytes = [
+            0x00, 0x01, // match type = OXM
+            0x00, 0x18, // length = 24
+            0x00, 0x01, 0x3f, 0x08, 192, 168, 2, 3, 255, 255, 255, 0, // tun_src with mask
+            0x80, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01, // in_port = 1
+        ];
+        let flow_match: FlowMatch = FlowMatch::parse(&bytes).unwrap();
+        assert_eq!(
+            flow_match.0,
+            vec![
+                Oxm::Nxm(NxmField::TunnelIpv4Source {
+                    value: Ipv4Address([192, 168, 2, 3]),
+                    mask: Some(Ipv4Address([255, 255, 255, 0])),
+                }),
+                Oxm::FlowMatchField(FlowMatchField::InPort(InPort::new(1))),
+            ]
+        );
+        let mut buffer = vec![0; flow_match.buffer_len()];
+        flow_match.emit(&mut buffer).unwrap();
+        assert_eq!(&buffer[..], &bytes[..]);
+    }
+
+    #[test]
+    fn test_errors() {
+        // unknown field
+        assert_eq!(parse(&[0x00, 0x01, 0x20, 0x04, 0, 0, 0, 0]), Err(Error::BadOxmField));
+        // masked ct_zone
+        assert_eq!(
+            parse(&[0x00, 0x01, 0xd5, 0x04, 0, 5, 0xff, 0xff]),
+            Err(Error::Malformed)
+        );
+        // wrong length
+        assert_eq!(parse(&[0x00, 0x01, 0x00, 0x02, 0, 0]), Err(Error::Malformed));
+    }
+}
@@ -60,7 +60,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     /// small.
     pub fn check_len(&self) -> Result<()> {
         let len = self.inner.as_ref().len();
-        if len < field::LENGTH || len < self.length() as usize + field::LENGTH {
+        if len < field::VALUE.start || len < self.length() as usize + field::VALUE.start {
             Err(Error::Truncated)
         } else {
             Ok(())
@@ -0,0 +1,195 @@
+//! Packet registers (`OXM_CLASS_PACKET_REGS`).
+//!
+//! Packet registers are pipeline fields that carry state between the tables of a pipeline. They
+//! are initially zero, are matched like any other field, and are written with set-field actions,
+//! optionally masked to update only some of their bits. The `oxm_field` `N` addresses the 64 bits
+//! register `N`.
+//!
+//! Open vSwitch also exposes each 64 bits register as two 32 bits registers: `xreg0` is made of
+//! `reg0` (the most significant half) and `reg1` (the least significant half), `xreg1` of `reg2`
+//! and `reg3`, _etc._ The `reg32` methods give access to these halves.
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use {Error, Result};
+use super::{Packet, CLASS_PACKET_REGISTERS, OXM_HEADER_LEN};
+
+/// A packet register field, with an optional mask.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PacketRegisters {
+    /// Index of the 64 bits register.
+    pub field: u8,
+    pub value: u64,
+    pub mask: Option<u64>,
+}
+
+impl PacketRegisters {
+    /// Return a field for the 64 bits register `index`.
+    pub fn new(index: u8, value: u64, mask: Option<u64>) -> Self {
+        PacketRegisters {
+            field: index,
+            value,
+            mask,
+        }
+    }
+
+    /// Return a field for the 32 bits register `index`, _i.e._ the 64 bits register `index / 2`,
+    /// masked so that only the corresponding half is matched or set.
+    pub fn from_reg32(index: u8, value: u32, mask: Option<u32>) -> Self {
+        let shift = reg32_shift(index);
+        let mask = mask.unwrap_or(!0);
+        PacketRegisters {
+            field: index / 2,
+            value: u64::from(value & mask) << shift,
+            mask: Some(u64::from(mask) << shift),
+        }
+    }
+
+    /// Return the value and the mask of the 32 bits register `index`, or `None` if this field
+    /// is not for the 64 bits register that contains it. The mask is zero if that half of the
+    /// register is fully wildcarded.
+    pub fn reg32(&self, index: u8) -> Option<(u32, u32)> {
+        if index / 2 != self.field {
+            return None;
+        }
+        let shift = reg32_shift(index);
+        let mask = self.mask.unwrap_or(!0);
+        Some(((self.value >> shift) as u32, (mask >> shift) as u32))
+    }
+
+    /// Return `true` if the value has bits set outside of the mask.
+    pub fn has_bad_wildcards(&self) -> bool {
+        self.value & !self.mask.unwrap_or(!0) != 0
+    }
+
+    pub fn parse<T: AsRef<[u8]> + ?Sized>(packet: &Packet<&T>) -> Result<Self> {
+        // check that the length field should be 8 or 16 depending on whether there is a mask.
+        let expected_len = if packet.has_mask() { 16 } else { 8 };
+        if packet.length() != expected_len {
+            return Err(Error::Malformed);
+        }
+        // We know that the length is correct per above check,
+        // and that the inner buffer has enough bytes since `Packet.check_len()` is called
+        // before this method is called.
+        let buf = packet.value();
+        let value = NetworkEndian::read_u64(&buf[0..8]);
+        let mask = if packet.has_mask() {
+            Some(NetworkEndian::read_u64(&buf[8..16]))
+        } else {
+            None
+        };
+        Ok(PacketRegisters {
+            field: packet.field(),
+            value,
+            mask,
+        })
+    }
+
+    fn value_len(&self) -> usize {
+        if self.mask.is_some() {
+            16
+        } else {
+            8
+        }
+    }
+
+    pub fn buffer_len(&self) -> usize {
+        OXM_HEADER_LEN + self.value_len()
+    }
+
+    pub fn emit(&self, buf: &mut [u8]) -> Result<()> {
+        if self.buffer_len() > buf.len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(&mut buf[..self.buffer_len()]);
+        packet.set_class(CLASS_PACKET_REGISTERS);
+        packet.set_field(self.field);
+        packet.set_length(self.value_len() as u8);
+        if self.mask.is_some() {
+            packet.set_mask();
+        } else {
+            packet.unset_mask();
+        }
+        let buf = packet.value_mut();
+        NetworkEndian::write_u64(&mut buf[0..8], self.value);
+        if let Some(mask) = self.mask {
+            NetworkEndian::write_u64(&mut buf[8..16], mask);
+        }
+        Ok(())
+    }
+}
+
+/// Return the position of the 32 bits register `index` in its 64 bits register.
+fn reg32_shift(index: u8) -> u32 {
+    if index & 1 == 0 {
+        32
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static MASKED: [u8; 20] = [
+        0x80, 0x01, // class = packet registers
+        0x05, // field = 2, with mask
+        0x10, // length = 16
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a, // value
+        0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, // mask
+    ];
+
+    fn parse(bytes: &[u8]) -> Result<PacketRegisters> {
+        PacketRegisters::parse(&Packet::new_checked(bytes)?)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(&MASKED), Ok(PacketRegisters::new(2, 42, Some(0xffff_ffff))));
+        assert_eq!(
+            parse(&[0x80, 0x01, 0x06, 0x08, 0, 0, 0, 0, 0, 0, 0, 7]),
+            Ok(PacketRegisters::new(3, 7, None))
+        );
+        // a mask of the wrong length
+        let mut bytes = MASKED;
+        bytes[3] = 0x08;
+        assert_eq!(parse(&bytes[..12]), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn test_emit() {
+        let register = PacketRegisters::new(2, 42, Some(0xffff_ffff));
+        let mut bytes = [0xff; 20];
+        register.emit(&mut bytes).unwrap();
+        assert_eq!(bytes, MASKED);
+
+        let register = PacketRegisters::new(3, 7, None);
+        let mut bytes = [0xff; 12];
+        register.emit(&mut bytes).unwrap();
+        assert_eq!(bytes, [0x80, 0x01, 0x06, 0x08, 0, 0, 0, 0, 0, 0, 0, 7]);
+    }
+
+    #[test]
+    fn test_reg32() {
+        // reg5 is the least significant half of xreg2
+        let register = PacketRegisters::from_reg32(5, 42, None);
+        assert_eq!(register, PacketRegisters::new(2, 42, Some(0xffff_ffff)));
+        assert_eq!(register.reg32(5), Some((42, 0xffff_ffff)));
+        assert_eq!(register.reg32(4), Some((0, 0)));
+        assert_eq!(register.reg32(3), None);
+
+        // reg4 is the most significant half of xreg2
+        let register = PacketRegisters::from_reg32(4, 0x1234, Some(0xff00));
+        assert_eq!(
+            register,
+            PacketRegisters::new(2, 0x1200_0000_0000, Some(0xff00_0000_0000))
+        );
+        assert_eq!(register.reg32(4), Some((0x1200, 0xff00)));
+        assert!(!register.has_bad_wildcards());
+
+        let register = PacketRegisters::new(2, 0x1234, None);
+        assert_eq!(register.reg32(4), Some((0, 0xffff_ffff)));
+        assert_eq!(register.reg32(5), Some((0x1234, 0xffff_ffff)));
+    }
+}
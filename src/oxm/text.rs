@@ -0,0 +1,869 @@
+//! Textual representation of flow matches, compatible with `ovs-ofctl`.
+//!
+//! A flow match is written as a comma separated list of `name=value` fields, where masked fields
+//! are written `name=value/mask`, for instance `tcp,nw_src=10.0.0.0/8,tp_dst=80`. Shorthands like
+//! `ip` or `tcp` stand for the corresponding `dl_type` and `nw_proto` fields.
+//!
+//! Packet registers are written `xregN=value[/mask]`, like in `ovs-ofctl`, and experimenter
+//! fields are written `experimenter=0x...` with the bytes of the whole OXM field in hexadecimal.
+//! [Unknown](enum.Oxm.html#variant.Unknown) fields are written `unknown=0x...` the same way.
+//! Fields that cannot be emitted, like an unknown field whose value is longer than 255 bytes, are
+//! still written, but cannot be parsed back.
+//! With the `nxm` feature, NXM fields are written with their `ovs-ofctl` names, like `reg0` or
+//! `ct_state`.
+
+use core::fmt;
+use core::str::FromStr;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use Repr;
+use port::PortNumber;
+use text::{self, ParseError, ParseErrorKind};
+use super::fields::{self, consts};
+use super::{prefix_len, prefix_mask, FlowMatch, FlowMatchField, NxmField, Oxm, Packet,
+            PacketRegisters, CLASS_NXM1, CLASS_OPEN_FLOW_BASIC, OXM_HEADER_LEN};
+#[cfg(feature = "nxm")]
+use super::nxm;
+
+/// How the value of a field is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// A port number, or a reserved port name.
+    Port,
+    /// An integer in decimal.
+    Decimal,
+    /// An integer in hexadecimal.
+    Hexadecimal,
+    /// An Ethernet address.
+    Ethernet,
+    /// An IPv4 address. A mask can be written as a prefix length.
+    Ipv4,
+    /// An IPv6 address. A mask can be written as a prefix length.
+    Ipv6,
+    /// A packet type, written `(namespace,type)`.
+    PacketType,
+}
+
+/// The name used to write each flow match field, and the format of its value.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+static FIELDS: &[(u8, &str, Format)] = &[
+    (consts::IN_PORT,               "in_port",       Format::Port),
+    (consts::IN_PHYSICAL_PORT,      "in_phy_port",   Format::Port),
+    (consts::METADATA,              "metadata",      Format::Hexadecimal),
+    (consts::ETHERNET_DESTINATION,  "dl_dst",        Format::Ethernet),
+    (consts::ETHERNET_SOURCE,       "dl_src",        Format::Ethernet),
+    (consts::ETHERNET_TYPE,         "dl_type",       Format::Hexadecimal),
+    (consts::VLAN_ID,               "vlan_vid",      Format::Hexadecimal),
+    (consts::VLAN_PRIORITY,         "vlan_pcp",      Format::Decimal),
+    (consts::IP_DSCP,               "ip_dscp",       Format::Decimal),
+    (consts::IP_ECN,                "nw_ecn",        Format::Decimal),
+    (consts::IP_PROTOCOL,           "nw_proto",      Format::Decimal),
+    (consts::IPV4_SOURCE,           "nw_src",        Format::Ipv4),
+    (consts::IPV4_DESTINATION,      "nw_dst",        Format::Ipv4),
+    (consts::TCP_SOURCE,            "tcp_src",       Format::Decimal),
+    (consts::TCP_DESTINATION,       "tcp_dst",       Format::Decimal),
+    (consts::UDP_SOURCE,            "udp_src",       Format::Decimal),
+    (consts::UDP_DESTINATION,       "udp_dst",       Format::Decimal),
+    (consts::SCTP_SOURCE,           "sctp_src",      Format::Decimal),
+    (consts::SCTP_DESTINATION,      "sctp_dst",      Format::Decimal),
+    (consts::ICMP_TYPE,             "icmp_type",     Format::Decimal),
+    (consts::ICMP_CODE,             "icmp_code",     Format::Decimal),
+    (consts::ARP_OP_CODE,           "arp_op",        Format::Decimal),
+    (consts::ARP_SPA,               "arp_spa",       Format::Ipv4),
+    (consts::ARP_TPA,               "arp_tpa",       Format::Ipv4),
+    (consts::ARP_SHA,               "arp_sha",       Format::Ethernet),
+    (consts::ARP_THA,               "arp_tha",       Format::Ethernet),
+    (consts::IPV6_SOURCE,           "ipv6_src",      Format::Ipv6),
+    (consts::IPV6_DESTINATION,      "ipv6_dst",      Format::Ipv6),
+    (consts::IPV6_FLOW_LABEL,       "ipv6_label",    Format::Hexadecimal),
+    (consts::ICMPV6_TYPE,           "icmpv6_type",   Format::Decimal),
+    (consts::ICMPV6_CODE,           "icmpv6_code",   Format::Decimal),
+    (consts::IPV6_ND_TARGET,        "nd_target",     Format::Ipv6),
+    (consts::IPV6_ND_SLL,           "nd_sll",        Format::Ethernet),
+    (consts::IPV6_ND_TLL,           "nd_tll",        Format::Ethernet),
+    (consts::MPLS_LABEL,            "mpls_label",    Format::Decimal),
+    (consts::MPLS_TC,               "mpls_tc",       Format::Decimal),
+    (consts::MPLS_BOS,              "mpls_bos",      Format::Decimal),
+    (consts::PBB_ISID,              "pbb_isid",      Format::Hexadecimal),
+    (consts::TUNNEL_ID,             "tun_id",        Format::Hexadecimal),
+    (consts::IPV6_EXTENSION_HEADER, "ipv6_exthdr",   Format::Hexadecimal),
+    (consts::PBB_UCA,               "pbb_uca",       Format::Decimal),
+    (consts::TCP_FLAGS,             "tcp_flags",     Format::Hexadecimal),
+    (consts::ACTION_SET_OUTPUT,     "actset_output", Format::Port),
+    (consts::PACKET_TYPE,           "packet_type",   Format::PacketType),
+];
+
+/// Other names accepted for the flow match fields.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+static ALIASES: &[(&str, u8)] = &[
+    ("eth_dst",     consts::ETHERNET_DESTINATION),
+    ("eth_src",     consts::ETHERNET_SOURCE),
+    ("eth_type",    consts::ETHERNET_TYPE),
+    ("dl_vlan_pcp", consts::VLAN_PRIORITY),
+    ("ip_ecn",      consts::IP_ECN),
+    ("ip_proto",    consts::IP_PROTOCOL),
+    ("ip_src",      consts::IPV4_SOURCE),
+    ("ip_dst",      consts::IPV4_DESTINATION),
+    ("icmpv4_type", consts::ICMP_TYPE),
+    ("icmpv4_code", consts::ICMP_CODE),
+    ("tunnel_id",   consts::TUNNEL_ID),
+];
+
+/// Shorthands for an Ethernet type, and optionally an IP protocol.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+static SHORTHANDS: &[(&str, u16, Option<u8>)] = &[
+    ("ip",    0x0800, None),
+    ("icmp",  0x0800, Some(1)),
+    ("tcp",   0x0800, Some(6)),
+    ("udp",   0x0800, Some(17)),
+    ("sctp",  0x0800, Some(132)),
+    ("ipv6",  0x86dd, None),
+    ("icmp6", 0x86dd, Some(58)),
+    ("tcp6",  0x86dd, Some(6)),
+    ("udp6",  0x86dd, Some(17)),
+    ("sctp6", 0x86dd, Some(132)),
+    ("arp",   0x0806, None),
+    ("rarp",  0x8035, None),
+    ("mpls",  0x8847, None),
+    ("mplsm", 0x8848, None),
+];
+
+fn field_by_code(code: u8) -> Option<(&'static str, Format)> {
+    FIELDS
+        .iter()
+        .find(|&&(c, _, _)| c == code)
+        .map(|&(_, name, format)| (name, format))
+}
+
+fn field_by_name(name: &str) -> Option<(u8, Format)> {
+    let code = FIELDS
+        .iter()
+        .find(|&&(_, n, _)| n == name)
+        .map(|&(code, _, _)| code)
+        .or_else(|| {
+            ALIASES
+                .iter()
+                .find(|&&(n, _)| n == name)
+                .map(|&(_, code)| code)
+        })?;
+    field_by_code(code).map(|(_, format)| (code, format))
+}
+
+fn format_value(format: Format, value: &[u8]) -> String {
+    match format {
+        Format::Port => text::format_port(PortNumber::from(text::to_u64(value) as u32)),
+        Format::Decimal => text::to_u64(value).to_string(),
+        Format::Hexadecimal if value.len() == 2 => format!("0x{:04x}", text::to_u64(value)),
+        Format::Hexadecimal => format!("0x{:x}", text::to_u64(value)),
+        Format::Ethernet => text::format_mac(value),
+        Format::Ipv4 => Ipv4Addr::new(value[0], value[1], value[2], value[3]).to_string(),
+        Format::Ipv6 => {
+            let mut address = [0; 16];
+            address.copy_from_slice(value);
+            Ipv6Addr::from(address).to_string()
+        }
+        Format::PacketType => format!(
+            "({},0x{:x})",
+            text::to_u64(&value[0..2]),
+            text::to_u64(&value[2..4])
+        ),
+    }
+}
+
+fn format_mask(format: Format, mask: &[u8]) -> String {
+    match (format, prefix_len(mask)) {
+        (Format::Ipv4, Some(len)) | (Format::Ipv6, Some(len)) => len.to_string(),
+        _ => format_value(format, mask),
+    }
+}
+
+fn parse_value(format: Format, s: &str, len: usize) -> Result<Vec<u8>, ParseError> {
+    match format {
+        Format::Port => Ok(text::to_bytes(u64::from(u32::from(text::parse_port(s)?)), len)),
+        Format::Decimal | Format::Hexadecimal => text::parse_uint(s, len),
+        Format::Ethernet => text::parse_mac(s),
+        Format::Ipv4 => s.parse::<Ipv4Addr>()
+            .map(|address| address.octets().to_vec())
+            .map_err(|_| ParseError::bad_value(s)),
+        Format::Ipv6 => s.parse::<Ipv6Addr>()
+            .map(|address| address.octets().to_vec())
+            .map_err(|_| ParseError::bad_value(s)),
+        Format::PacketType => {
+            let inner = s.trim()
+                .trim_start_matches('(')
+                .trim_end_matches(')');
+            let mut parts = inner.splitn(2, ',');
+            let namespace = text::parse_uint(parts.next().unwrap_or(""), 2)?;
+            let ns_type = parts.next().ok_or_else(|| ParseError::bad_value(s))?;
+            let ns_type = text::parse_uint(ns_type, 2)?;
+            Ok(namespace.into_iter().chain(ns_type).collect())
+        }
+    }
+}
+
+fn parse_mask(format: Format, s: &str, len: usize) -> Result<Vec<u8>, ParseError> {
+    match format {
+        Format::Ipv4 | Format::Ipv6 if !s.contains('.') && !s.contains(':') => {
+            s.parse::<usize>()
+                .ok()
+                .and_then(|prefix_len| prefix_mask(prefix_len, len))
+                .ok_or_else(|| ParseError::bad_value(s))
+        }
+        _ => parse_value(format, s, len),
+    }
+}
+
+/// Write a field from its wire format.
+fn format_field(f: &mut fmt::Formatter, name: &str, format: Format, bytes: &[u8]) -> fmt::Result {
+    let packet = Packet::new(bytes);
+    let value = packet.value();
+    if packet.has_mask() {
+        let (value, mask) = value.split_at(value.len() / 2);
+        write!(
+            f,
+            "{}={}/{}",
+            name,
+            format_value(format, value),
+            format_mask(format, mask)
+        )
+    } else {
+        write!(f, "{}={}", name, format_value(format, value))
+    }
+}
+
+/// Parse the value of a field written `name=value` or `name=value/mask`, and return the value and
+/// the mask, if any.
+fn parse_field(
+    s: &str,
+    value: &str,
+    format: Format,
+    (len, maskable): (usize, bool),
+) -> Result<(Vec<u8>, Option<Vec<u8>>), ParseError> {
+    let (value, mask) = match (format, value.find('/')) {
+        (Format::PacketType, _) | (_, None) => (parse_value(format, value, len)?, None),
+        (_, Some(i)) if maskable => (
+            parse_value(format, &value[..i], len)?,
+            Some(parse_mask(format, &value[i + 1..], len)?),
+        ),
+        (_, Some(_)) => return Err(ParseError::bad_value(s)),
+    };
+    if value.len() != len {
+        return Err(ParseError::bad_value(s));
+    }
+    Ok((value, mask))
+}
+
+/// Parse an OXM field written as its wire format in hexadecimal. An OpenFlow basic field whose
+/// `oxm_field` is not known is returned as a `FlowMatchField::Unknown` field.
+fn parse_unknown<E>(value: &str) -> Result<Oxm<E>, ParseError> {
+    let bytes = text::parse_hex(value)?;
+    let packet = Packet::new_checked(&bytes[..]).map_err(|_| ParseError::bad_value(value))?;
+    if packet.length() as usize != packet.value().len() {
+        return Err(ParseError::bad_value(value));
+    }
+    if packet.class() == CLASS_NXM1 {
+        if let Ok(field) = NxmField::parse(&packet) {
+            return Ok(Oxm::Nxm(field));
+        }
+    }
+    if packet.class() == CLASS_OPEN_FLOW_BASIC && fields::value_len(packet.field()).is_none() {
+        return Ok(Oxm::FlowMatchField(FlowMatchField::Unknown {
+            field: packet.field(),
+            has_mask: packet.has_mask(),
+            value: packet.value().to_vec(),
+        }));
+    }
+    Ok(Oxm::Unknown {
+        class: packet.class(),
+        field: packet.field(),
+        has_mask: packet.has_mask(),
+        value: packet.value().to_vec(),
+    })
+}
+
+/// A field whose `oxm_field` is not known is written `unknown=` followed by its wire format in
+/// hexadecimal.
+impl fmt::Display for FlowMatchField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let FlowMatchField::Unknown {
+            field,
+            has_mask,
+            ref value,
+        } = *self
+        {
+            let bytes = raw_bytes(CLASS_OPEN_FLOW_BASIC, field, has_mask, value);
+            return write!(f, "unknown={}", text::format_hex(&bytes));
+        }
+        // the other fields have a fixed length, so they can always be emitted
+        match fields::encode(self) {
+            Ok(bytes) => match field_by_code(Packet::new(&bytes[..]).field()) {
+                Some((name, format)) => format_field(f, name, format, &bytes),
+                None => write!(f, "unknown={}", text::format_hex(&bytes)),
+            },
+            Err(_) => write!(f, "unknown=?"),
+        }
+    }
+}
+
+/// Parse a field written `name=value` or `name=value/mask`. The `tp_src` and `tp_dst` names are
+/// not accepted, since the protocol they refer to cannot be determined.
+impl FromStr for FlowMatchField {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = split_field(s)?;
+        if name == "tp_src" || name == "tp_dst" {
+            return Err(ParseError::new(ParseErrorKind::AmbiguousField, name));
+        }
+        if name == "unknown" {
+            return match parse_unknown::<()>(value)? {
+                Oxm::FlowMatchField(field) => Ok(field),
+                _ => Err(ParseError::bad_value(value)),
+            };
+        }
+        let unknown_name = || ParseError::unknown_name(name);
+        let (code, format) = field_by_name(name).ok_or_else(unknown_name)?;
+        let value_len = fields::value_len(code).ok_or_else(unknown_name)?;
+        let (value, mask) = parse_field(s, value, format, value_len)?;
+        fields::decode(code, &value, mask.as_ref().map(|mask| &mask[..]))
+            .map_err(|_| ParseError::bad_value(s))
+    }
+}
+
+/// The name used to write each NXM field, and the format of its value. The registers are written
+/// `reg0` to `reg15`.
+#[cfg(feature = "nxm")]
+#[cfg_attr(rustfmt, rustfmt_skip)]
+static NXM_FIELDS: &[(u8, &str, Format)] = &[
+    (nxm::consts::TUN_IPV4_SRC, "tun_src",  Format::Ipv4),
+    (nxm::consts::TUN_IPV4_DST, "tun_dst",  Format::Ipv4),
+    (nxm::consts::CT_STATE,     "ct_state", Format::Hexadecimal),
+    (nxm::consts::CT_ZONE,      "ct_zone",  Format::Decimal),
+    (nxm::consts::CT_MARK,      "ct_mark",  Format::Hexadecimal),
+];
+
+#[cfg(feature = "nxm")]
+fn nxm_field_by_code(code: u8) -> Option<(String, Format)> {
+    if code <= nxm::consts::REG15 {
+        return Some((format!("reg{}", code - nxm::consts::REG0), Format::Hexadecimal));
+    }
+    NXM_FIELDS
+        .iter()
+        .find(|&&(c, _, _)| c == code)
+        .map(|&(_, name, format)| (name.to_string(), format))
+}
+
+#[cfg(feature = "nxm")]
+fn nxm_field_by_name(name: &str) -> Option<(u8, Format)> {
+    if name.starts_with("reg") {
+        return name["reg".len()..]
+            .parse::<u8>()
+            .ok()
+            .filter(|&index| index <= nxm::consts::REG15 - nxm::consts::REG0)
+            .map(|index| (nxm::consts::REG0 + index, Format::Hexadecimal));
+    }
+    NXM_FIELDS
+        .iter()
+        .find(|&&(_, n, _)| n == name)
+        .map(|&(code, _, format)| (code, format))
+}
+
+#[cfg(feature = "nxm")]
+impl fmt::Display for NxmField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut bytes = vec![0; self.buffer_len()];
+        match (self.emit(&mut bytes), nxm_field_by_code(self.code())) {
+            (Ok(()), Some((name, format))) => format_field(f, &name, format, &bytes),
+            // only a register whose index is out of range cannot be emitted
+            _ => write!(f, "unknown=?"),
+        }
+    }
+}
+
+/// Parse an NXM field written `name=value` or `name=value/mask`, with the names used by
+/// `ovs-ofctl`: `reg0` to `reg15`, `tun_src`, `tun_dst`, `ct_state`, `ct_zone` and `ct_mark`.
+#[cfg(feature = "nxm")]
+impl FromStr for NxmField {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = split_field(s)?;
+        let unknown_name = || ParseError::unknown_name(name);
+        let (code, format) = nxm_field_by_name(name).ok_or_else(unknown_name)?;
+        let value_len = nxm::value_len(code).ok_or_else(unknown_name)?;
+        let (value, mask) = parse_field(s, value, format, value_len)?;
+        nxm::decode(code, &value, mask.as_ref().map(|mask| &mask[..]))
+            .map_err(|_| ParseError::bad_value(s))
+    }
+}
+
+#[cfg(feature = "nxm")]
+fn format_nxm(f: &mut fmt::Formatter, field: &NxmField) -> fmt::Result {
+    write!(f, "{}", field)
+}
+
+/// Without the `nxm` feature, NXM fields have no name, and are written like unknown fields.
+#[cfg(not(feature = "nxm"))]
+fn format_nxm(f: &mut fmt::Formatter, field: &NxmField) -> fmt::Result {
+    format_emitted(f, "unknown", field.buffer_len(), |bytes| field.emit(bytes))
+}
+
+/// Return the wire format of a field from its header fields and its raw value. Unlike when the
+/// field is emitted, a value longer than 255 bytes is accepted: the `oxm_length` field is then
+/// set to 255, so that the field can still be written, but not parsed back.
+fn raw_bytes(class: u16, field: u8, has_mask: bool, value: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0; OXM_HEADER_LEN + value.len()];
+    {
+        let mut packet = Packet::new(&mut bytes[..]);
+        packet.set_class(class);
+        packet.set_field(field);
+        packet.set_length(value.len().min(0xff) as u8);
+        if has_mask {
+            packet.set_mask();
+        } else {
+            packet.unset_mask();
+        }
+        packet.value_mut().copy_from_slice(value);
+    }
+    bytes
+}
+
+/// Write `name=` followed by the bytes written by `emit` in hexadecimal, or by `?` if the field
+/// cannot be emitted, like an experimenter field whose value is longer than 255 bytes.
+fn format_emitted<F>(f: &mut fmt::Formatter, name: &str, len: usize, emit: F) -> fmt::Result
+where
+    F: FnOnce(&mut [u8]) -> ::Result<()>,
+{
+    let mut bytes = vec![0; len];
+    match emit(&mut bytes) {
+        Ok(()) => write!(f, "{}={}", name, text::format_hex(&bytes)),
+        Err(_) => write!(f, "{}=?", name),
+    }
+}
+
+/// Split `name=value` into its name and its value.
+fn split_field(s: &str) -> Result<(&str, &str), ParseError> {
+    let i = s.find('=').ok_or_else(|| ParseError::bad_value(s))?;
+    Ok((s[..i].trim(), s[i + 1..].trim()))
+}
+
+impl<E: Repr> fmt::Display for Oxm<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Oxm::FlowMatchField(ref field) => write!(f, "{}", field),
+            Oxm::PacketRegisters(ref registers) => match registers.mask {
+                Some(mask) => write!(
+                    f,
+                    "xreg{}=0x{:x}/0x{:x}",
+                    registers.field, registers.value, mask
+                ),
+                None => write!(f, "xreg{}=0x{:x}", registers.field, registers.value),
+            },
+            Oxm::Experimenter(ref repr) => {
+                format_emitted(f, "experimenter", repr.buffer_len(), |bytes| repr.emit(bytes))
+            }
+            Oxm::Nxm(ref field) => format_nxm(f, field),
+            Oxm::Unknown {
+                class,
+                field,
+                has_mask,
+                ref value,
+            } => write!(
+                f,
+                "unknown={}",
+                text::format_hex(&raw_bytes(class, field, has_mask, value))
+            ),
+        }
+    }
+}
+
+impl<E: Repr> FromStr for Oxm<E> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = split_field(s)?;
+        if name.starts_with("xreg") {
+            let field = text::parse_u8(&name["xreg".len()..])
+                .map_err(|_| ParseError::unknown_name(name))?;
+            let (value, mask) = match value.find('/') {
+                Some(i) => (
+                    text::parse_u64(&value[..i])?,
+                    Some(text::parse_u64(&value[i + 1..])?),
+                ),
+                None => (text::parse_u64(value)?, None),
+            };
+            return Ok(Oxm::PacketRegisters(PacketRegisters { field, value, mask }));
+        }
+        if name == "experimenter" {
+            let bytes = text::parse_hex(value)?;
+            return E::parse(&bytes)
+                .map(Oxm::Experimenter)
+                .map_err(|_| ParseError::bad_value(value));
+        }
+        if name == "unknown" {
+            return parse_unknown(value);
+        }
+        #[cfg(feature = "nxm")]
+        {
+            if nxm_field_by_name(name).is_some() {
+                return s.parse().map(Oxm::Nxm);
+            }
+        }
+        s.parse().map(Oxm::FlowMatchField)
+    }
+}
+
+/// Return the value of the given flow match field, if `oxm` is this field and is not masked.
+fn exact_value<E>(oxm: Option<&Oxm<E>>, code: u8) -> Option<u64> {
+    match oxm {
+        Some(Oxm::FlowMatchField(field)) => {
+            let bytes = fields::encode(field).ok()?;
+            let packet = Packet::new(&bytes[..]);
+            if packet.field() == code && !packet.has_mask() {
+                Some(text::to_u64(packet.value()))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Flow matches are written with the `ovs-ofctl` shorthands when possible: a `dl_type` field
+/// followed by a `nw_proto` field are written `tcp`, `udp6`, _etc._
+impl<E: Repr> fmt::Display for FlowMatch<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let oxms = &self.0;
+        let mut parts = Vec::new();
+        let mut i = 0;
+        while i < oxms.len() {
+            let eth_type = exact_value(oxms.get(i), consts::ETHERNET_TYPE);
+            let ip_proto = exact_value(oxms.get(i + 1), consts::IP_PROTOCOL);
+            let shorthand = |ip_proto| {
+                SHORTHANDS
+                    .iter()
+                    .find(|&&(_, e, p)| Some(u64::from(e)) == eth_type && p == ip_proto)
+                    .map(|&(name, _, _)| name)
+            };
+            if let Some(name) = ip_proto.and_then(|p| shorthand(Some(p as u8))) {
+                parts.push(name.to_string());
+                i += 2;
+            } else if let Some(name) = shorthand(None) {
+                parts.push(name.to_string());
+                i += 1;
+            } else {
+                parts.push(oxms[i].to_string());
+                i += 1;
+            }
+        }
+        write!(f, "{}", parts.join(","))
+    }
+}
+
+/// Return the value of the first field of `oxms` that matches exactly the field `code`.
+fn previous_value<E>(oxms: &[Oxm<E>], code: u8) -> Option<u64> {
+    oxms.iter()
+        .filter_map(|oxm| exact_value(Some(oxm), code))
+        .next()
+}
+
+/// Push the `name=value` field implied by the shorthand `token`, unless a preceding field already
+/// sets it to the same value.
+fn push_implied<E: Repr>(
+    oxms: &mut Vec<Oxm<E>>,
+    token: &str,
+    code: u8,
+    name: &str,
+    value: u64,
+) -> Result<(), ParseError> {
+    match previous_value(oxms, code) {
+        Some(previous) if previous == value => Ok(()),
+        Some(_) => Err(ParseError::new(ParseErrorKind::ConflictingField, token)),
+        None => {
+            oxms.push(format!("{}={}", name, value).parse()?);
+            Ok(())
+        }
+    }
+}
+
+/// Parse a comma separated list of fields and shorthands. `tp_src` and `tp_dst` are accepted
+/// after a field or a shorthand that sets the IP protocol to TCP, UDP or SCTP. A shorthand only
+/// adds the `dl_type` and `nw_proto` fields that are not set yet, so `ip,tcp` is the same as
+/// `tcp`, but contradicting shorthands or fields, like `tcp,udp`, are rejected.
+///
+/// Only the match part of an `ovs-ofctl` flow is parsed: `priority` and the `actions` are not
+/// fields. Actions and instructions are parsed on their own, as
+/// [`Action`](../action/enum.Action.html) and
+/// [`Instruction`](../instruction/enum.Instruction.html).
+impl<E: Repr> FromStr for FlowMatch<E> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut oxms = Vec::new();
+        let mut ip_proto = None;
+        for token in text::split(s) {
+            if let Some(&(_, eth_type, proto)) =
+                SHORTHANDS.iter().find(|&&(name, _, _)| name == token)
+            {
+                let eth_type = u64::from(eth_type);
+                push_implied(&mut oxms, token, consts::ETHERNET_TYPE, "dl_type", eth_type)?;
+                if let Some(proto) = proto {
+                    push_implied(&mut oxms, token, consts::IP_PROTOCOL, "nw_proto", proto.into())?;
+                    ip_proto = Some(proto);
+                }
+                continue;
+            }
+            let (name, value) = split_field(token)?;
+            let oxm = if name == "tp_src" || name == "tp_dst" {
+                let protocol = match ip_proto {
+                    Some(6) => "tcp",
+                    Some(17) => "udp",
+                    Some(132) => "sctp",
+                    _ => return Err(ParseError::new(ParseErrorKind::AmbiguousField, name)),
+                };
+                format!("{}_{}={}", protocol, &name[3..], value).parse()?
+            } else {
+                token.parse()?
+            };
+            for &code in &[consts::ETHERNET_TYPE, consts::IP_PROTOCOL] {
+                if let (Some(previous), Some(value)) =
+                    (previous_value(&oxms, code), exact_value(Some(&oxm), code))
+                {
+                    if previous != value {
+                        return Err(ParseError::new(ParseErrorKind::ConflictingField, token));
+                    }
+                }
+            }
+            if let Oxm::FlowMatchField(FlowMatchField::IpProtocol(_)) = oxm {
+                ip_proto = Some(text::parse_u8(value)?);
+            }
+            oxms.push(oxm);
+        }
+        Ok(FlowMatch(oxms))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use oxm::*;
+    use smoltcp::wire::{EthernetAddress, EthernetProtocol, Ipv4Address};
+
+    type Match = FlowMatch;
+
+    fn round_trip(s: &str) {
+        let repr: Match = s.parse().unwrap();
+        assert_eq!(repr.to_string(), s);
+        let reparsed: Match = repr.to_string().parse().unwrap();
+        assert_eq!(reparsed.0, repr.0);
+    }
+
+    #[test]
+    fn test_field() {
+        let field = FlowMatchField::Ipv4Source(Ipv4Source::new(
+            Ipv4Address([10, 0, 0, 0]),
+            Some(Ipv4Address([255, 0, 0, 0])),
+        ));
+        assert_eq!(field.to_string(), "nw_src=10.0.0.0/8");
+        assert_eq!("nw_src=10.0.0.0/8".parse(), Ok(field.clone()));
+        assert_eq!("ip_src=10.0.0.0/255.0.0.0".parse(), Ok(field));
+
+        let field = FlowMatchField::EthernetDestination(EthernetDestination::new(
+            EthernetAddress([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]),
+            None,
+        ));
+        assert_eq!(field.to_string(), "dl_dst=00:11:22:33:44:55");
+        assert_eq!("eth_dst=00:11:22:33:44:55".parse(), Ok(field));
+
+        let field = FlowMatchField::InPort(InPort::new(0xffff_fffe));
+        assert_eq!(field.to_string(), "in_port=LOCAL");
+        assert_eq!("in_port=LOCAL".parse(), Ok(field));
+
+        let field = FlowMatchField::PacketType(PacketType::new(0x0001_0800));
+        assert_eq!(field.to_string(), "packet_type=(1,0x800)");
+        assert_eq!("packet_type=(1,0x800)".parse(), Ok(field));
+
+        let field = FlowMatchField::Unknown {
+            field: 45,
+            has_mask: false,
+            value: vec![0xab, 0xcd],
+        };
+        assert_eq!(field.to_string(), "unknown=0x80005a02abcd");
+        assert_eq!("unknown=0x80005a02abcd".parse(), Ok(field));
+    }
+
+    #[test]
+    fn test_too_long() {
+        // these fields cannot be emitted, but are still written
+        let field = FlowMatchField::Unknown {
+            field: 45,
+            has_mask: false,
+            value: vec![0xab; 256],
+        };
+        let s = field.to_string();
+        assert!(s.starts_with("unknown=0x80005affabab"));
+        assert_eq!(s.parse::<FlowMatchField>(), Err(ParseError::bad_value(&s[8..])));
+
+        let oxm = Oxm::Experimenter(ExperimenterOxm::Unknown {
+            experimenter: 0x2320,
+            field: 1,
+            has_mask: false,
+            value: vec![0; 252],
+        });
+        assert_eq!(oxm.to_string(), "experimenter=?");
+    }
+
+    #[test]
+    fn test_field_errors() {
+        assert_eq!(
+            "foo=1".parse::<FlowMatchField>(),
+            Err(ParseError::unknown_name("foo"))
+        );
+        assert_eq!(
+            "nw_proto=300".parse::<FlowMatchField>(),
+            Err(ParseError::bad_value("300"))
+        );
+        assert_eq!(
+            "nw_proto=6/0xff".parse::<FlowMatchField>(),
+            Err(ParseError::bad_value("nw_proto=6/0xff"))
+        );
+        assert_eq!(
+            "tp_src=80".parse::<FlowMatchField>(),
+            Err(ParseError::new(ParseErrorKind::AmbiguousField, "tp_src"))
+        );
+        // not an OpenFlow basic field
+        assert_eq!(
+            "unknown=0x0001400400000000".parse::<FlowMatchField>(),
+            Err(ParseError::bad_value("0x0001400400000000"))
+        );
+    }
+
+    #[test]
+    fn test_flow_match() {
+        let repr: Match = "ip,nw_src=10.0.0.0/8,tcp,tp_dst=80".parse().unwrap();
+        assert_eq!(
+            repr.0,
+            vec![
+                Oxm::FlowMatchField(FlowMatchField::EthernetType(EthernetType::new(
+                    EthernetProtocol::Ipv4,
+                ))),
+                Oxm::FlowMatchField(FlowMatchField::Ipv4Source(Ipv4Source::new(
+                    Ipv4Address([10, 0, 0, 0]),
+                    Some(Ipv4Address([255, 0, 0, 0])),
+                ))),
+                Oxm::FlowMatchField(FlowMatchField::IpProtocol(IpProtocol::new(6))),
+                Oxm::FlowMatchField(FlowMatchField::TcpDestination(TcpDestination::new(80))),
+            ]
+        );
+        assert_eq!(repr.to_string(), "ip,nw_src=10.0.0.0/8,nw_proto=6,tcp_dst=80");
+
+        assert_eq!(
+            "udp6,tp_src=53".parse::<Match>().map(|repr| repr.to_string()),
+            Ok("udp6,udp_src=53".to_string())
+        );
+        assert_eq!(
+            "ip,tp_src=53".parse::<Match>(),
+            Err(ParseError::new(ParseErrorKind::AmbiguousField, "tp_src"))
+        );
+    }
+
+    #[test]
+    fn test_conflicting_shorthands() {
+        assert_eq!(
+            "ip,tcp".parse::<Match>().map(|repr| repr.to_string()),
+            Ok("tcp".to_string())
+        );
+        assert_eq!(
+            "tcp,udp".parse::<Match>(),
+            Err(ParseError::new(ParseErrorKind::ConflictingField, "udp"))
+        );
+        assert_eq!(
+            "ip,ipv6".parse::<Match>(),
+            Err(ParseError::new(ParseErrorKind::ConflictingField, "ipv6"))
+        );
+        assert_eq!(
+            "tcp,nw_proto=17".parse::<Match>(),
+            Err(ParseError::new(ParseErrorKind::ConflictingField, "nw_proto=17"))
+        );
+        assert_eq!(
+            "dl_type=0x0806,ip".parse::<Match>(),
+            Err(ParseError::new(ParseErrorKind::ConflictingField, "ip"))
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip("");
+        round_trip("in_port=1,tcp,nw_src=10.0.0.1,nw_dst=192.168.0.0/255.255.0.255,tcp_dst=80");
+        round_trip("dl_type=0x88cc,dl_src=00:11:22:33:44:55/ff:ff:ff:00:00:00");
+        round_trip("arp,arp_op=1,arp_spa=10.0.0.1,arp_sha=00:11:22:33:44:55");
+        round_trip("icmp6,icmpv6_type=135,nd_target=fe80::1,nd_sll=00:11:22:33:44:55");
+        round_trip("ipv6,ipv6_src=2001:db8::/32,ipv6_label=0x12345/0xfffff,ipv6_exthdr=0x0001");
+        round_trip("vlan_vid=0x1064/0x1fff,vlan_pcp=3,metadata=0x1/0xff,tun_id=0x2a");
+        round_trip("mpls,mpls_label=16,mpls_tc=1,mpls_bos=1,actset_output=CONTROLLER");
+        round_trip("xreg0=0x1/0xffff,xreg3=0x2a");
+        round_trip("experimenter=0xffff000400002320");
+        round_trip("in_port=1,unknown=0x80005a02abcd");
+    }
+
+    #[test]
+    fn test_all_fields() {
+        for (_, field, masked) in fields::test::all_fields() {
+            for field in Some(field).into_iter().chain(masked) {
+                let s = field.to_string();
+                assert_eq!(s.parse::<FlowMatchField>(), Ok(field), "{}", s);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "nxm")]
+    fn test_nxm() {
+        let flow_match: Match = "reg3=0x2a/0xff,tun_src=192.168.0.0/16,ct_state=0x21/0x21,ct_zone=5"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            flow_match.0,
+            vec![
+                Oxm::Nxm(NxmField::Register {
+                    index: 3,
+                    value: 0x2a,
+                    mask: Some(0xff),
+                }),
+                Oxm::Nxm(NxmField::TunnelIpv4Source {
+                    value: Ipv4Address([192, 168, 0, 0]),
+                    mask: Some(Ipv4Address([255, 255, 0, 0])),
+                }),
+                Oxm::Nxm(NxmField::CtState {
+                    value: 0x21,
+                    mask: Some(0x21),
+                }),
+                Oxm::Nxm(NxmField::CtZone(5)),
+            ]
+        );
+        assert_eq!(
+            flow_match.to_string(),
+            "reg3=0x2a/0xff,tun_src=192.168.0.0/16,ct_state=0x21/0x21,ct_zone=5"
+        );
+        assert_eq!(
+            "reg16=1".parse::<NxmField>(),
+            Err(ParseError::unknown_name("reg16"))
+        );
+        assert_eq!(
+            "ct_zone=1/1".parse::<NxmField>(),
+            Err(ParseError::bad_value("ct_zone=1/1"))
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "nxm"))]
+    fn test_nxm_without_names() {
+        let flow_match: Match = FlowMatch(vec![Oxm::Nxm(NxmField::CtZone(5))]);
+        assert_eq!(flow_match.to_string(), "unknown=0x0001d4020005");
+        assert_eq!("unknown=0x0001d4020005".parse(), Ok(flow_match));
+        assert_eq!(
+            "ct_zone=5".parse::<Match>(),
+            Err(ParseError::unknown_name("ct_zone"))
+        );
+    }
+}
@@ -0,0 +1,492 @@
+//! Validation of flow matches, before they are sent to a switch.
+//!
+//! A switch rejects a flow match with a `BadMatch` error when one of its fields appears twice,
+//! has an illegal mask, or when the prerequisites of a field are not met. For instance, matching
+//! on `OXM_OF_TCP_SRC` requires the match to also contain, before it, `OXM_OF_IP_PROTO=6` and
+//! either `OXM_OF_ETH_TYPE=0x0800`, `OXM_OF_ETH_TYPE=0x86dd` or the equivalent
+//! `OXM_OF_PACKET_TYPE`.
+//! [`FlowMatch::validate`](type.FlowMatch.html#method.validate) performs these checks locally.
+
+use core::{fmt, result};
+use std::error::Error as StdError;
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+use Repr;
+use packets::error::BadMatchCode;
+use super::fields::{self, consts, ETH_TYPE_PBB, VLAN_PRESENT};
+use super::{FlowMatch, FlowMatchField, Oxm, Packet, CLASS_OPEN_FLOW_BASIC, CLASS_PACKET_REGISTERS};
+
+/// Why a field of a flow match is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationErrorKind {
+    /// The prerequisites of the field are not met.
+    BadPrerequisite,
+    /// The field appears more than once in the flow match.
+    DuplicateField,
+    /// The field has a mask, but it is not maskable.
+    BadMask,
+    /// The value of the field has bits set where its mask is zero.
+    BadWildcards,
+    /// The value of the field is invalid, for instance an address that cannot be parsed.
+    BadValue,
+}
+
+/// The error returned when validating a flow match. It identifies the first offending field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationError {
+    pub kind: ValidationErrorKind,
+    /// `oxm_class` of the offending field.
+    pub class: u16,
+    /// `oxm_field` of the offending field. For the flow match fields, see the [`consts`] module.
+    ///
+    /// [`consts`]: consts/index.html
+    pub field: u8,
+}
+
+impl ValidationError {
+    /// Return the code of the `BadMatch` error a switch would reply with for this flow match.
+    pub fn code(&self) -> BadMatchCode {
+        match self.kind {
+            ValidationErrorKind::BadPrerequisite => BadMatchCode::BadPrerequities,
+            ValidationErrorKind::DuplicateField => BadMatchCode::DupplicateField,
+            ValidationErrorKind::BadMask => BadMatchCode::BadMask,
+            ValidationErrorKind::BadWildcards => BadMatchCode::BadWildcards,
+            ValidationErrorKind::BadValue => BadMatchCode::BadValue,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self.kind {
+            ValidationErrorKind::BadPrerequisite => "prerequisites not met",
+            ValidationErrorKind::DuplicateField => "duplicate field",
+            ValidationErrorKind::BadMask => "field is not maskable",
+            ValidationErrorKind::BadWildcards => "value has bits set outside of the mask",
+            ValidationErrorKind::BadValue => "invalid value",
+        };
+        match consts::name(self.field) {
+            Some(name) if self.class == CLASS_OPEN_FLOW_BASIC => write!(f, "{}: {}", name, reason),
+            _ => write!(f, "oxm field {:#06x}:{}: {}", self.class, self.field, reason),
+        }
+    }
+}
+
+impl StdError for ValidationError {}
+
+/// Ethernet types, as used in the prerequisites.
+const ETH_TYPE_IPV4: u16 = 0x0800;
+const ETH_TYPE_ARP: u16 = 0x0806;
+const ETH_TYPE_IPV6: u16 = 0x86dd;
+const ETH_TYPE_MPLS: u16 = 0x8847;
+const ETH_TYPE_MPLS_MULTICAST: u16 = 0x8848;
+
+/// IP protocols, as used in the prerequisites.
+const IP_PROTO_ICMP: u8 = 1;
+const IP_PROTO_TCP: u8 = 6;
+const IP_PROTO_UDP: u8 = 17;
+const IP_PROTO_ICMPV6: u8 = 58;
+const IP_PROTO_SCTP: u8 = 132;
+
+/// The `OFPHTN_ETHERTYPE` packet type namespace.
+const PACKET_TYPE_NS_ETHERTYPE: u16 = 1;
+
+/// Some flow match fields of a flow match, in their wire format.
+struct Fields<'a>(&'a [Vec<u8>]);
+
+impl<'a> Fields<'a> {
+    fn find(&self, field: u8) -> Option<Packet<&[u8]>> {
+        self.0
+            .iter()
+            .map(|bytes| Packet::new(&bytes[..]))
+            .find(|packet| packet.field() == field)
+    }
+
+    /// Return the value of the given field, if it is present and not masked.
+    fn exact(&self, field: u8) -> Option<&[u8]> {
+        match self.find(field) {
+            Some(ref packet) if !packet.has_mask() => Some(packet.value()),
+            _ => None,
+        }
+    }
+
+    /// Return the Ethernet type the flow match matches exactly, if any. If `packet_type` is
+    /// `true`, an `OXM_OF_PACKET_TYPE` field in the Ethernet type namespace is also considered.
+    fn eth_type(&self, packet_type: bool) -> Option<u16> {
+        if let Some(value) = self.exact(consts::ETHERNET_TYPE) {
+            return Some(NetworkEndian::read_u16(value));
+        }
+        if !packet_type {
+            return None;
+        }
+        self.exact(consts::PACKET_TYPE).and_then(|value| {
+            if NetworkEndian::read_u16(&value[0..2]) == PACKET_TYPE_NS_ETHERTYPE {
+                Some(NetworkEndian::read_u16(&value[2..4]))
+            } else {
+                None
+            }
+        })
+    }
+
+    fn is_eth_type(&self, packet_type: bool, eth_types: &[u16]) -> bool {
+        match self.eth_type(packet_type) {
+            Some(eth_type) => eth_types.contains(&eth_type),
+            None => false,
+        }
+    }
+
+    fn is_ip(&self, eth_types: &[u16], ip_proto: u8) -> bool {
+        self.is_eth_type(true, eth_types)
+            && self.exact(consts::IP_PROTOCOL).map(|value| value[0]) == Some(ip_proto)
+    }
+
+    fn is_icmpv6(&self, icmpv6_types: &[u8]) -> bool {
+        if !self.is_ip(&[ETH_TYPE_IPV6], IP_PROTO_ICMPV6) {
+            return false;
+        }
+        match self.exact(consts::ICMPV6_TYPE) {
+            Some(value) => icmpv6_types.contains(&value[0]),
+            None => false,
+        }
+    }
+
+    fn has_vlan(&self) -> bool {
+        match self.find(consts::VLAN_ID) {
+            Some(packet) => {
+                let value = packet.value();
+                let present = NetworkEndian::read_u16(&value[0..2]) & VLAN_PRESENT != 0;
+                let masked_out =
+                    packet.has_mask() && NetworkEndian::read_u16(&value[2..4]) & VLAN_PRESENT == 0;
+                present && !masked_out
+            }
+            None => false,
+        }
+    }
+
+    /// Return `true` if the prerequisites of the given field are met.
+    fn prerequisites_met(&self, field: u8) -> bool {
+        const IP: &[u16] = &[ETH_TYPE_IPV4, ETH_TYPE_IPV6];
+        const MPLS: &[u16] = &[ETH_TYPE_MPLS, ETH_TYPE_MPLS_MULTICAST];
+        match field {
+            consts::IN_PHYSICAL_PORT => self.find(consts::IN_PORT).is_some(),
+            consts::VLAN_PRIORITY => self.has_vlan(),
+            consts::IP_DSCP
+            | consts::IP_ECN
+            | consts::IP_PROTOCOL => self.is_eth_type(true, IP),
+            consts::IPV4_SOURCE
+            | consts::IPV4_DESTINATION => self.is_eth_type(true, &[ETH_TYPE_IPV4]),
+            consts::TCP_SOURCE
+            | consts::TCP_DESTINATION
+            | consts::TCP_FLAGS => self.is_ip(IP, IP_PROTO_TCP),
+            consts::UDP_SOURCE
+            | consts::UDP_DESTINATION => self.is_ip(IP, IP_PROTO_UDP),
+            consts::SCTP_SOURCE
+            | consts::SCTP_DESTINATION => self.is_ip(IP, IP_PROTO_SCTP),
+            consts::ICMP_TYPE
+            | consts::ICMP_CODE => self.is_ip(&[ETH_TYPE_IPV4], IP_PROTO_ICMP),
+            consts::ARP_OP_CODE
+            | consts::ARP_SPA
+            | consts::ARP_TPA
+            | consts::ARP_SHA
+            | consts::ARP_THA => self.is_eth_type(false, &[ETH_TYPE_ARP]),
+            consts::IPV6_SOURCE
+            | consts::IPV6_DESTINATION
+            | consts::IPV6_FLOW_LABEL
+            | consts::IPV6_EXTENSION_HEADER => self.is_eth_type(true, &[ETH_TYPE_IPV6]),
+            consts::ICMPV6_TYPE
+            | consts::ICMPV6_CODE => self.is_ip(&[ETH_TYPE_IPV6], IP_PROTO_ICMPV6),
+            consts::IPV6_ND_TARGET => self.is_icmpv6(&[135, 136]),
+            consts::IPV6_ND_SLL => self.is_icmpv6(&[135]),
+            consts::IPV6_ND_TLL => self.is_icmpv6(&[136]),
+            consts::MPLS_LABEL
+            | consts::MPLS_TC
+            | consts::MPLS_BOS => self.is_eth_type(false, MPLS),
+            consts::PBB_ISID
+            | consts::PBB_UCA => self.is_eth_type(false, &[ETH_TYPE_PBB]),
+            _ => true,
+        }
+    }
+}
+
+/// Return `true` if the value of the given field has bits set where its mask is zero.
+fn has_bad_wildcards(packet: &Packet<&[u8]>) -> bool {
+    let bytes = packet.value();
+    let (value, mask) = bytes.split_at(bytes.len() / 2);
+    value.iter().zip(mask).any(|(value, mask)| value & !mask != 0)
+}
+
+impl<E: Repr> FlowMatch<E> {
+    /// Check that this flow match would be accepted by a switch: no field appears twice, masks
+    /// are only used on maskable fields and do not hide bits of the value, and the prerequisites
+    /// of every field are met by the fields that precede it. Packet registers are always
+    /// maskable and have no prerequisites.
+    ///
+    /// Experimenter fields are not checked.
+    pub fn validate(&self) -> result::Result<(), ValidationError> {
+        let mut encoded = Vec::new();
+        for oxm in &self.0 {
+            if let Oxm::FlowMatchField(ref field) = *oxm {
+                let bytes = fields::encode(field).map_err(|_| ValidationError {
+                    kind: ValidationErrorKind::BadValue,
+                    class: CLASS_OPEN_FLOW_BASIC,
+                    // Only an unknown field can fail to be encoded, when its value is too long.
+                    field: match *field {
+                        FlowMatchField::Unknown { field, .. } => field,
+                        _ => 0,
+                    },
+                })?;
+                encoded.push(bytes);
+            }
+        }
+
+        let mut registers = Vec::new();
+        for oxm in &self.0 {
+            if let Oxm::PacketRegisters(ref register) = *oxm {
+                if registers.contains(&register.field) {
+                    return Err(ValidationError {
+                        kind: ValidationErrorKind::DuplicateField,
+                        class: CLASS_PACKET_REGISTERS,
+                        field: register.field,
+                    });
+                }
+                if register.has_bad_wildcards() {
+                    return Err(ValidationError {
+                        kind: ValidationErrorKind::BadWildcards,
+                        class: CLASS_PACKET_REGISTERS,
+                        field: register.field,
+                    });
+                }
+                registers.push(register.field);
+            }
+        }
+
+        for (index, bytes) in encoded.iter().enumerate() {
+            let packet = Packet::new(&bytes[..]);
+            let field = packet.field();
+            let previous = Fields(&encoded[..index]);
+            let error = |kind| {
+                Err(ValidationError {
+                    kind,
+                    class: CLASS_OPEN_FLOW_BASIC,
+                    field,
+                })
+            };
+            if previous.find(field).is_some() {
+                return error(ValidationErrorKind::DuplicateField);
+            }
+            if packet.has_mask() {
+                if fields::value_len(field).map(|(_, maskable)| maskable) != Some(true) {
+                    return error(ValidationErrorKind::BadMask);
+                }
+                if has_bad_wildcards(&packet) {
+                    return error(ValidationErrorKind::BadWildcards);
+                }
+            }
+            if !previous.prerequisites_met(field) {
+                return error(ValidationErrorKind::BadPrerequisite);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use oxm::*;
+    use smoltcp::wire::{EthernetProtocol, Ipv4Address};
+
+    fn flow_match(fields: Vec<FlowMatchField>) -> FlowMatch {
+        FlowMatch(fields.into_iter().map(Oxm::FlowMatchField).collect())
+    }
+
+    fn error(kind: ValidationErrorKind, field: u8) -> result::Result<(), ValidationError> {
+        Err(ValidationError {
+            kind,
+            class: CLASS_OPEN_FLOW_BASIC,
+            field,
+        })
+    }
+
+    #[test]
+    fn test_valid() {
+        let repr = flow_match(vec![
+            FlowMatchField::EthernetType(EthernetType::new(EthernetProtocol::Ipv4)),
+            FlowMatchField::IpProtocol(IpProtocol::new(6)),
+            FlowMatchField::TcpDestination(TcpDestination::new(80)),
+            FlowMatchField::Ipv4Source(Ipv4Source::new(
+                Ipv4Address([10, 0, 0, 0]),
+                Some(Ipv4Address([255, 0, 0, 0])),
+            )),
+        ]);
+        assert_eq!(repr.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_prerequisite_order() {
+        let repr = flow_match(vec![
+            FlowMatchField::TcpDestination(TcpDestination::new(80)),
+            FlowMatchField::IpProtocol(IpProtocol::new(6)),
+            FlowMatchField::EthernetType(EthernetType::new(EthernetProtocol::Ipv4)),
+        ]);
+        assert_eq!(
+            repr.validate(),
+            error(ValidationErrorKind::BadPrerequisite, consts::TCP_DESTINATION)
+        );
+
+        let repr = flow_match(vec![
+            FlowMatchField::EthernetType(EthernetType::new(EthernetProtocol::Ipv4)),
+            FlowMatchField::TcpDestination(TcpDestination::new(80)),
+            FlowMatchField::IpProtocol(IpProtocol::new(6)),
+        ]);
+        assert_eq!(
+            repr.validate(),
+            error(ValidationErrorKind::BadPrerequisite, consts::TCP_DESTINATION)
+        );
+    }
+
+    #[test]
+    fn test_packet_type_prerequisite() {
+        let repr = flow_match(vec![
+            FlowMatchField::PacketType(PacketType::new(0x0001_86dd)),
+            FlowMatchField::IpProtocol(IpProtocol::new(17)),
+            FlowMatchField::UdpSource(UdpSource::new(53)),
+        ]);
+        assert_eq!(repr.validate(), Ok(()));
+
+        // Packet type is not an alternative to the Ethernet type for ARP fields
+        let repr = flow_match(vec![
+            FlowMatchField::PacketType(PacketType::new(0x0001_0806)),
+            FlowMatchField::ArpOpCode(ArpOpCode::new(::smoltcp::wire::ArpOperation::Request)),
+        ]);
+        assert_eq!(
+            repr.validate(),
+            error(ValidationErrorKind::BadPrerequisite, consts::ARP_OP_CODE)
+        );
+    }
+
+    #[test]
+    fn test_missing_prerequisite() {
+        let repr = flow_match(vec![
+            FlowMatchField::EthernetType(EthernetType::new(EthernetProtocol::Ipv4)),
+            FlowMatchField::TcpSource(TcpSource::new(22)),
+        ]);
+        let err = repr.validate().unwrap_err();
+        assert_eq!(err, ValidationError {
+            kind: ValidationErrorKind::BadPrerequisite,
+            class: CLASS_OPEN_FLOW_BASIC,
+            field: consts::TCP_SOURCE,
+        });
+        assert_eq!(err.code(), BadMatchCode::BadPrerequities);
+        assert_eq!(format!("{}", err), "OXM_OF_TCP_SRC: prerequisites not met");
+    }
+
+    #[test]
+    fn test_wrong_prerequisite() {
+        let repr = flow_match(vec![
+            FlowMatchField::EthernetType(EthernetType::new(EthernetProtocol::Ipv6)),
+            FlowMatchField::IpProtocol(IpProtocol::new(17)),
+            FlowMatchField::TcpSource(TcpSource::new(22)),
+        ]);
+        assert_eq!(
+            repr.validate(),
+            error(ValidationErrorKind::BadPrerequisite, consts::TCP_SOURCE)
+        );
+    }
+
+    #[test]
+    fn test_vlan_priority_prerequisite() {
+        let repr = flow_match(vec![
+            FlowMatchField::VlanId(VlanId::new(0x1000, Some(0x1000))),
+            FlowMatchField::VlanPriority(VlanPriority::new(3)),
+        ]);
+        assert_eq!(repr.validate(), Ok(()));
+
+        let repr = flow_match(vec![
+            FlowMatchField::VlanId(VlanId::new(0, None)),
+            FlowMatchField::VlanPriority(VlanPriority::new(3)),
+        ]);
+        assert_eq!(
+            repr.validate(),
+            error(ValidationErrorKind::BadPrerequisite, consts::VLAN_PRIORITY)
+        );
+    }
+
+    #[test]
+    fn test_duplicate_field() {
+        let repr = flow_match(vec![
+            FlowMatchField::InPort(InPort::new(1)),
+            FlowMatchField::InPort(InPort::new(2)),
+        ]);
+        assert_eq!(
+            repr.validate(),
+            error(ValidationErrorKind::DuplicateField, consts::IN_PORT)
+        );
+
+        let register = PacketRegisters {
+            field: 3,
+            value: 1,
+            mask: None,
+        };
+        let repr = FlowMatch::<ExperimenterOxm>(vec![
+            Oxm::PacketRegisters(register.clone()),
+            Oxm::PacketRegisters(register),
+        ]);
+        assert_eq!(
+            repr.validate(),
+            Err(ValidationError {
+                kind: ValidationErrorKind::DuplicateField,
+                class: CLASS_PACKET_REGISTERS,
+                field: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_bad_wildcards() {
+        let repr = flow_match(vec![
+            FlowMatchField::EthernetType(EthernetType::new(EthernetProtocol::Ipv4)),
+            FlowMatchField::Ipv4Destination(Ipv4Destination::new(
+                Ipv4Address([10, 0, 0, 1]),
+                Some(Ipv4Address([255, 255, 255, 0])),
+            )),
+        ]);
+        assert_eq!(
+            repr.validate(),
+            error(ValidationErrorKind::BadWildcards, consts::IPV4_DESTINATION)
+        );
+
+        let repr = FlowMatch::<ExperimenterOxm>(vec![Oxm::PacketRegisters(
+            PacketRegisters::new(1, 0x1ff, Some(0xff)),
+        )]);
+        assert_eq!(
+            repr.validate(),
+            Err(ValidationError {
+                kind: ValidationErrorKind::BadWildcards,
+                class: CLASS_PACKET_REGISTERS,
+                field: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_value_too_long() {
+        let repr = flow_match(vec![FlowMatchField::Unknown {
+            field: 90,
+            has_mask: false,
+            value: vec![0; 256],
+        }]);
+        assert_eq!(repr.validate(), error(ValidationErrorKind::BadValue, 90));
+    }
+
+    #[test]
+    fn test_registers() {
+        // no prerequisites
+        let repr = FlowMatch::<ExperimenterOxm>(vec![
+            Oxm::PacketRegisters(PacketRegisters::from_reg32(0, 1, None)),
+            Oxm::PacketRegisters(PacketRegisters::new(1, 0xff, Some(0xff))),
+        ]);
+        assert_eq!(repr.validate(), Ok(()));
+    }
+}
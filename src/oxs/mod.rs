@@ -0,0 +1,234 @@
+//! Implementation of OXS (OpenFlow eXtensible Statistics)
+//!
+//! OXS TLVs are used to report flow statistics, for instance in aggregate statistics replies. They
+//! share the header format of [OXM](../oxm/index.html) TLVs:
+//!
+//! ```no_rust
+//! 0                       16             23  24             32
+//! +------------------------+-------------+----+-------------+
+//! |       oxs_class        |  oxs_field  |rsvd|  oxs_length |
+//! +------------------------+-------------+----+-------------+
+//! |                          value                          |
+//! |                     (variable size)                     |
+//! +---------------------------------------------------------+
+//! ```
+//!
+//! A list of OXS TLVs is wrapped in a "stats" structure, represented by [`Stats`](struct.Stats.html)
+//!
+//! ```no_rust
+//! +---------------+---------------+
+//! |    reserved   |     length    |
+//! +---------------+---------------+
+//! |          oxs fields           |
+//! |   (variable       +-----------+
+//! |     length)       |  padding  |
+//! +-------------------+-----------+
+//! ```
+//!
+//! - The `length` includes the header but not the padding
+//! - The `padding` field is for 8 bytes alignment
+
+use {Error, Repr, Result};
+use byteorder::{ByteOrder, NetworkEndian};
+use oxm::Packet;
+
+/// Basic class for OpenFlow statistics
+pub const CLASS_OPEN_FLOW_BASIC: u16 = 0x8002;
+/// Experimenter class
+pub const CLASS_EXPERIMENTER: u16 = 0xFFFF;
+
+/// Time flow entry has been alive
+pub const DURATION: u8 = 0;
+/// Time flow entry has been idle
+pub const IDLE_TIME: u8 = 1;
+/// Number of aggregated flow entries
+pub const FLOW_COUNT: u8 = 3;
+/// Number of packets in flow entry
+pub const PACKET_COUNT: u8 = 4;
+/// Number of bytes in flow entry
+pub const BYTE_COUNT: u8 = 5;
+
+const OXS_HEADER_LEN: usize = 4;
+
+mod field {
+    #![allow(non_snake_case)]
+    use field::*;
+    use packets::property::padded_len;
+
+    pub const RESERVED: Field = 0..2;
+    pub const LENGTH: Field = 2..4;
+
+    pub fn OXS_FIELDS(length: usize) -> Field {
+        LENGTH.end..length
+    }
+
+    pub fn PADDING(length: usize) -> Field {
+        length..padded_len(length)
+    }
+}
+
+/// Represent an OXS field
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Stat {
+    /// Time the flow entry has been alive, in seconds and nanoseconds beyond `sec`.
+    Duration { sec: u32, nsec: u32 },
+    /// Time the flow entry has been idle, in seconds and nanoseconds beyond `sec`.
+    IdleTime { sec: u32, nsec: u32 },
+    /// Number of aggregated flow entries
+    FlowCount(u32),
+    /// Number of packets matched by the flow entry
+    PacketCount(u64),
+    /// Number of bytes matched by the flow entry
+    ByteCount(u64),
+    /// An OXS field unknown to this library, or belonging to an experimenter class. It is kept as
+    /// is so that it can be emitted back.
+    Unknown { class: u16, field: u8, value: Vec<u8> },
+}
+
+impl Stat {
+    fn value_len(&self) -> usize {
+        match *self {
+            Stat::Duration { .. } | Stat::IdleTime { .. } => 8,
+            Stat::FlowCount(_) => 4,
+            Stat::PacketCount(_) | Stat::ByteCount(_) => 8,
+            Stat::Unknown { ref value, .. } => value.len(),
+        }
+    }
+}
+
+impl Repr for Stat {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        let value = &packet.value()[..packet.length() as usize];
+        if packet.class() != CLASS_OPEN_FLOW_BASIC {
+            return Ok(Stat::Unknown {
+                class: packet.class(),
+                field: packet.field(),
+                value: value.to_vec(),
+            });
+        }
+        let expected_len = match packet.field() {
+            DURATION | IDLE_TIME | PACKET_COUNT | BYTE_COUNT => 8,
+            FLOW_COUNT => 4,
+            _ => value.len(),
+        };
+        if value.len() != expected_len {
+            return Err(Error::Malformed);
+        }
+        Ok(match packet.field() {
+            DURATION => Stat::Duration {
+                sec: NetworkEndian::read_u32(&value[0..4]),
+                nsec: NetworkEndian::read_u32(&value[4..8]),
+            },
+            IDLE_TIME => Stat::IdleTime {
+                sec: NetworkEndian::read_u32(&value[0..4]),
+                nsec: NetworkEndian::read_u32(&value[4..8]),
+            },
+            FLOW_COUNT => Stat::FlowCount(NetworkEndian::read_u32(value)),
+            PACKET_COUNT => Stat::PacketCount(NetworkEndian::read_u64(value)),
+            BYTE_COUNT => Stat::ByteCount(NetworkEndian::read_u64(value)),
+            field => Stat::Unknown {
+                class: CLASS_OPEN_FLOW_BASIC,
+                field: field,
+                value: value.to_vec(),
+            },
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        OXS_HEADER_LEN + self.value_len()
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let (class, field) = match *self {
+            Stat::Duration { .. } => (CLASS_OPEN_FLOW_BASIC, DURATION),
+            Stat::IdleTime { .. } => (CLASS_OPEN_FLOW_BASIC, IDLE_TIME),
+            Stat::FlowCount(_) => (CLASS_OPEN_FLOW_BASIC, FLOW_COUNT),
+            Stat::PacketCount(_) => (CLASS_OPEN_FLOW_BASIC, PACKET_COUNT),
+            Stat::ByteCount(_) => (CLASS_OPEN_FLOW_BASIC, BYTE_COUNT),
+            Stat::Unknown { class, field, .. } => (class, field),
+        };
+        let value_len = self.value_len();
+        if value_len > 0xff {
+            return Err(Error::Malformed);
+        }
+        let mut packet = Packet::new(&mut buffer[..OXS_HEADER_LEN + value_len]);
+        packet.set_class(class);
+        // the reserved bit shares its position with the OXM `HM` bit
+        packet.unset_mask();
+        packet.set_field(field);
+        packet.set_length(value_len as u8);
+        let value = packet.value_mut();
+        match *self {
+            Stat::Duration { sec, nsec } | Stat::IdleTime { sec, nsec } => {
+                NetworkEndian::write_u32(&mut value[0..4], sec);
+                NetworkEndian::write_u32(&mut value[4..8], nsec);
+            }
+            Stat::FlowCount(count) => NetworkEndian::write_u32(value, count),
+            Stat::PacketCount(count) | Stat::ByteCount(count) => {
+                NetworkEndian::write_u64(value, count)
+            }
+            Stat::Unknown { value: ref bytes, .. } => value.copy_from_slice(bytes),
+        }
+        Ok(())
+    }
+}
+
+/// Represent a list of OXS fields, as found in aggregate statistics replies for instance.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Stats(pub Vec<Stat>);
+
+impl Stats {
+    fn fields_len(&self) -> usize {
+        self.0.iter().fold(0, |acc, stat| acc + stat.buffer_len())
+    }
+}
+
+impl Repr for Stats {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        if buffer.len() < field::LENGTH.end {
+            return Err(Error::Truncated);
+        }
+        let length = NetworkEndian::read_u16(&buffer[field::LENGTH]) as usize;
+        if length < field::LENGTH.end {
+            return Err(Error::Malformed);
+        }
+        if buffer.len() < field::PADDING(length).end {
+            return Err(Error::Truncated);
+        }
+        let bytes = &buffer[field::OXS_FIELDS(length)];
+        let mut stats = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let stat = Stat::parse(&bytes[offset..])?;
+            offset += stat.buffer_len();
+            stats.push(stat);
+        }
+        Ok(Stats(stats))
+    }
+
+    fn buffer_len(&self) -> usize {
+        field::PADDING(field::LENGTH.end + self.fields_len()).end
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let length = field::LENGTH.end + self.fields_len();
+        NetworkEndian::write_u16(&mut buffer[field::RESERVED], 0);
+        NetworkEndian::write_u16(&mut buffer[field::LENGTH], length as u16);
+        let mut offset = field::LENGTH.end;
+        for stat in &self.0 {
+            stat.emit(&mut buffer[offset..offset + stat.buffer_len()])?;
+            offset += stat.buffer_len();
+        }
+        for byte in &mut buffer[field::PADDING(length)] {
+            *byte = 0;
+        }
+        Ok(())
+    }
+}
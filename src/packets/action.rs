@@ -0,0 +1,538 @@
+//! Types to parse and emit actions.
+//!
+//! Actions are found in instructions, group buckets and packet-out messages. They all start with
+//! the same header:
+//!
+//! ```no_rust
+//! +--------+--------+--------+--------+
+//! |       type      |      length     |
+//! +--------+--------+--------+--------+
+//! |                body               |
+//! |         (variable length)         |
+//! +--------+--------+--------+--------+
+//! ```
+//!
+//! - The type determines how the body is interpreted. See [Kind] for the different types.
+//! - The length includes the header and is always a multiple of 8.
+//!
+//! [Kind]: enum.Kind.html
+use core::{fmt, result};
+use core::str::FromStr;
+
+use {Error, Repr, Result};
+use byteorder::{ByteOrder, NetworkEndian};
+use oxm::{ExperimenterOxm, Oxm};
+use packets::list;
+use packets::property::padded_len;
+use port::PortNumber;
+use text::{self, ParseError};
+
+/// Special `max_len` value for output actions, indicating that no buffering should be applied and
+/// the whole packet is to be sent to the controller.
+pub const CONTROLLER_NO_BUFFER: u16 = 0xffff;
+
+enum_with_unknown! {
+    /// Type of an action.
+    pub doc enum Kind(u16) {
+        /// Output to switch port.
+        Output = 0,
+        /// Copy TTL "outwards" -- from next-to-outermost to outermost.
+        CopyTtlOut = 11,
+        /// Copy TTL "inwards" -- from outermost to next-to-outermost.
+        CopyTtlIn = 12,
+        /// MPLS TTL.
+        SetMplsTtl = 15,
+        /// Decrement MPLS TTL.
+        DecMplsTtl = 16,
+        /// Push a new VLAN tag.
+        PushVlan = 17,
+        /// Pop the outer VLAN tag.
+        PopVlan = 18,
+        /// Push a new MPLS tag.
+        PushMpls = 19,
+        /// Pop the outer MPLS tag.
+        PopMpls = 20,
+        /// Set queue id when outputting to a port.
+        SetQueue = 21,
+        /// Apply group.
+        Group = 22,
+        /// IP TTL.
+        SetNwTtl = 23,
+        /// Decrement IP TTL.
+        DecNwTtl = 24,
+        /// Set a header field using OXM TLV format.
+        SetField = 25,
+        /// Push a new PBB service tag (I-TAG).
+        PushPbb = 26,
+        /// Pop the outer PBB service tag (I-TAG).
+        PopPbb = 27,
+        /// Copy value between header and register.
+        CopyField = 28,
+        /// Apply meter (rate limiter).
+        Meter = 29,
+        /// Experimenter action.
+        Experimenter = 0xffff
+    }
+}
+
+/// A wrapper to read and write a buffer representing an action.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod field {
+    #![allow(non_snake_case)]
+    use field::*;
+
+    pub const KIND: Field = 0..2;
+    pub const LENGTH: Field = 2..4;
+
+    pub fn BODY(length: usize) -> Field {
+        LENGTH.end..length
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with an action structure.
+    pub fn new(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new] and [check_len].
+    ///
+    /// [new]: #method.new
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error::Truncated)` if the buffer is too short, and `Err(Error::Malformed)` if
+    /// the length field is invalid.
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < field::LENGTH.end {
+            return Err(Error::Truncated);
+        }
+        let length = self.length() as usize;
+        if length < field::LENGTH.end || padded_len(length) != length {
+            Err(Error::Malformed)
+        } else if len < length {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `type` field.
+    pub fn kind(&self) -> Kind {
+        let data = self.buffer.as_ref();
+        Kind::from(NetworkEndian::read_u16(&data[field::KIND]))
+    }
+
+    /// Return the `length` field.
+    pub fn length(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::LENGTH])
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to the body of the action.
+    pub fn body(&self) -> &'a [u8] {
+        let length = self.length() as usize;
+        &self.buffer.as_ref()[field::BODY(length)]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the `type` field.
+    pub fn set_kind(&mut self, value: Kind) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::KIND], value.into())
+    }
+
+    /// Set the `length` field.
+    pub fn set_length(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::LENGTH], value)
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + AsMut<[u8]> + ?Sized> Packet<&'a mut T> {
+    /// Return a mutable pointer to the body of the action. The `length` field must be set
+    /// already.
+    pub fn body_mut(&mut self) -> &mut [u8] {
+        let length = self.length() as usize;
+        &mut self.buffer.as_mut()[field::BODY(length)]
+    }
+}
+
+/// A high-level representation of an action.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Action<E = ExperimenterOxm> {
+    /// Output the packet to a port. `max_len` is the maximum number of bytes to send when the
+    /// port is [`PortNumber::Controller`](../enum.PortNumber.html), or
+    /// [`CONTROLLER_NO_BUFFER`](constant.CONTROLLER_NO_BUFFER.html).
+    Output { port: PortNumber, max_len: u16 },
+    /// Copy the TTL from the next-to-outermost header to the outermost header.
+    CopyTtlOut,
+    /// Copy the TTL from the outermost header to the next-to-outermost header.
+    CopyTtlIn,
+    /// Set the MPLS TTL.
+    SetMplsTtl(u8),
+    /// Decrement the MPLS TTL.
+    DecMplsTtl,
+    /// Push a new VLAN tag with the given ethertype.
+    PushVlan(u16),
+    /// Pop the outer VLAN tag.
+    PopVlan,
+    /// Push a new MPLS tag with the given ethertype.
+    PushMpls(u16),
+    /// Pop the outer MPLS tag. The value is the ethertype of the payload.
+    PopMpls(u16),
+    /// Set the queue used when outputting the packet to a port.
+    SetQueue(u32),
+    /// Process the packet through the given group.
+    Group(u32),
+    /// Set the IP TTL.
+    SetNwTtl(u8),
+    /// Decrement the IP TTL.
+    DecNwTtl,
+    /// Set a header or pipeline field.
+    SetField(Oxm<E>),
+    /// Push a new PBB service tag with the given ethertype.
+    PushPbb(u16),
+    /// Pop the outer PBB service tag.
+    PopPbb,
+    /// Apply the given meter.
+    Meter(u32),
+    /// An experimenter action.
+    Experimenter { experimenter: u32, data: Vec<u8> },
+    /// An action unknown to this library. The body is kept as is, so that it can be emitted back.
+    Unknown { kind: u16, data: Vec<u8> },
+}
+
+/// Length of an action made of the header and 4 bytes.
+const SHORT_LEN: usize = 8;
+/// Length of an output action.
+const OUTPUT_LEN: usize = 16;
+
+impl<E> Action<E> {
+    /// Return the type of this action.
+    pub fn kind(&self) -> Kind {
+        use self::Action::*;
+        match *self {
+            Output { .. } => Kind::Output,
+            CopyTtlOut => Kind::CopyTtlOut,
+            CopyTtlIn => Kind::CopyTtlIn,
+            SetMplsTtl(_) => Kind::SetMplsTtl,
+            DecMplsTtl => Kind::DecMplsTtl,
+            PushVlan(_) => Kind::PushVlan,
+            PopVlan => Kind::PopVlan,
+            PushMpls(_) => Kind::PushMpls,
+            PopMpls(_) => Kind::PopMpls,
+            SetQueue(_) => Kind::SetQueue,
+            Group(_) => Kind::Group,
+            SetNwTtl(_) => Kind::SetNwTtl,
+            DecNwTtl => Kind::DecNwTtl,
+            SetField(_) => Kind::SetField,
+            PushPbb(_) => Kind::PushPbb,
+            PopPbb => Kind::PopPbb,
+            Meter(_) => Kind::Meter,
+            Experimenter { .. } => Kind::Experimenter,
+            Unknown { kind, .. } => Kind::from(kind),
+        }
+    }
+}
+
+impl<E: Repr> list::Entry for Action<E> {
+    const LENGTH_OFFSET: usize = field::LENGTH.start;
+}
+
+impl<E: Repr> Repr for Action<E> {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        use self::Action::*;
+        let packet = Packet::new_checked(buffer)?;
+        let body = packet.body();
+        let kind = packet.kind();
+        let expected_len = match kind {
+            Kind::Output => OUTPUT_LEN,
+            Kind::CopyTtlOut | Kind::CopyTtlIn | Kind::SetMplsTtl | Kind::DecMplsTtl
+            | Kind::PushVlan | Kind::PopVlan | Kind::PushMpls | Kind::PopMpls | Kind::SetQueue
+            | Kind::Group | Kind::SetNwTtl | Kind::DecNwTtl | Kind::PushPbb | Kind::PopPbb
+            | Kind::Meter => SHORT_LEN,
+            Kind::Experimenter => {
+                if body.len() < 4 {
+                    return Err(Error::Malformed);
+                }
+                packet.length() as usize
+            }
+            _ => packet.length() as usize,
+        };
+        if packet.length() as usize != expected_len {
+            return Err(Error::Malformed);
+        }
+        Ok(match kind {
+            Kind::Output => Output {
+                port: PortNumber::from(NetworkEndian::read_u32(&body[0..4])),
+                max_len: NetworkEndian::read_u16(&body[4..6]),
+            },
+            Kind::CopyTtlOut => CopyTtlOut,
+            Kind::CopyTtlIn => CopyTtlIn,
+            Kind::SetMplsTtl => SetMplsTtl(body[0]),
+            Kind::DecMplsTtl => DecMplsTtl,
+            Kind::PushVlan => PushVlan(NetworkEndian::read_u16(&body[0..2])),
+            Kind::PopVlan => PopVlan,
+            Kind::PushMpls => PushMpls(NetworkEndian::read_u16(&body[0..2])),
+            Kind::PopMpls => PopMpls(NetworkEndian::read_u16(&body[0..2])),
+            Kind::SetQueue => SetQueue(NetworkEndian::read_u32(&body[0..4])),
+            Kind::Group => Group(NetworkEndian::read_u32(&body[0..4])),
+            Kind::SetNwTtl => SetNwTtl(body[0]),
+            Kind::DecNwTtl => DecNwTtl,
+            Kind::SetField => SetField(Oxm::parse(body)?),
+            Kind::PushPbb => PushPbb(NetworkEndian::read_u16(&body[0..2])),
+            Kind::PopPbb => PopPbb,
+            Kind::Meter => Meter(NetworkEndian::read_u32(&body[0..4])),
+            Kind::Experimenter => Experimenter {
+                experimenter: NetworkEndian::read_u32(&body[0..4]),
+                data: body[4..].to_vec(),
+            },
+            kind => Unknown {
+                kind: kind.into(),
+                data: body.to_vec(),
+            },
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        use self::Action::*;
+        match *self {
+            Output { .. } => OUTPUT_LEN,
+            SetField(ref oxm) => padded_len(field::LENGTH.end + oxm.buffer_len()),
+            Experimenter { ref data, .. } => padded_len(field::LENGTH.end + 4 + data.len()),
+            Unknown { ref data, .. } => padded_len(field::LENGTH.end + data.len()),
+            _ => SHORT_LEN,
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        use self::Action::*;
+        let length = self.buffer_len();
+        if length > 0xffff {
+            return Err(Error::Malformed);
+        }
+        if let Unknown { kind, .. } = *self {
+            // an unknown action would be parsed back as the known action of the same type
+            match Kind::from(kind) {
+                Kind::CopyField | Kind::_Unknown(_) => {}
+                _ => return Err(Error::Malformed),
+            }
+        }
+        if buffer.len() < length {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(&mut buffer[..length]);
+        packet.set_kind(self.kind());
+        packet.set_length(length as u16);
+        let body = packet.body_mut();
+        for byte in body.iter_mut() {
+            *byte = 0;
+        }
+        match *self {
+            Output { port, max_len } => {
+                NetworkEndian::write_u32(&mut body[0..4], port.into());
+                NetworkEndian::write_u16(&mut body[4..6], max_len);
+            }
+            SetMplsTtl(ttl) | SetNwTtl(ttl) => body[0] = ttl,
+            PushVlan(ethertype) | PushMpls(ethertype) | PopMpls(ethertype)
+            | PushPbb(ethertype) => NetworkEndian::write_u16(&mut body[0..2], ethertype),
+            SetQueue(id) | Group(id) | Meter(id) => NetworkEndian::write_u32(&mut body[0..4], id),
+            SetField(ref oxm) => oxm.emit(body)?,
+            Experimenter {
+                experimenter,
+                ref data,
+            } => {
+                NetworkEndian::write_u32(&mut body[0..4], experimenter);
+                body[4..4 + data.len()].copy_from_slice(data);
+            }
+            Unknown { ref data, .. } => body[..data.len()].copy_from_slice(data),
+            CopyTtlOut | CopyTtlIn | DecMplsTtl | PopVlan | DecNwTtl | PopPbb => {}
+        }
+        Ok(())
+    }
+}
+
+/// Actions are written with the `ovs-ofctl` syntax, for instance `output:2`, `NORMAL`,
+/// `CONTROLLER:128`, `push_vlan:0x8100` or `set_field:10.0.0.1->nw_src`. Output actions with a
+/// `max_len` to a regular port are written `output(port=2,max_len=128)`, experimenter actions
+/// `experimenter(0x00002320,0x...)` and unknown actions `unknown(28,0x...)`.
+impl<E: Repr> fmt::Display for Action<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Action::*;
+        match *self {
+            Output {
+                port: PortNumber::Controller,
+                max_len,
+            } => write!(f, "CONTROLLER:{}", max_len),
+            Output { port, max_len: 0 } => match port {
+                PortNumber::InPort
+                | PortNumber::Table
+                | PortNumber::Normal
+                | PortNumber::Flood
+                | PortNumber::All
+                | PortNumber::Local => write!(f, "{}", text::format_port(port)),
+                port => write!(f, "output:{}", text::format_port(port)),
+            },
+            Output { port, max_len } => write!(
+                f,
+                "output(port={},max_len={})",
+                text::format_port(port),
+                max_len
+            ),
+            CopyTtlOut => write!(f, "copy_ttl_out"),
+            CopyTtlIn => write!(f, "copy_ttl_in"),
+            SetMplsTtl(ttl) => write!(f, "set_mpls_ttl:{}", ttl),
+            DecMplsTtl => write!(f, "dec_mpls_ttl"),
+            PushVlan(ethertype) => write!(f, "push_vlan:0x{:04x}", ethertype),
+            PopVlan => write!(f, "pop_vlan"),
+            PushMpls(ethertype) => write!(f, "push_mpls:0x{:04x}", ethertype),
+            PopMpls(ethertype) => write!(f, "pop_mpls:0x{:04x}", ethertype),
+            SetQueue(queue_id) => write!(f, "set_queue:{}", queue_id),
+            Group(group_id) => write!(f, "group:{}", group_id),
+            SetNwTtl(ttl) => write!(f, "mod_nw_ttl:{}", ttl),
+            DecNwTtl => write!(f, "dec_ttl"),
+            SetField(ref oxm) => {
+                let oxm = oxm.to_string();
+                match oxm.find('=') {
+                    Some(i) => write!(f, "set_field:{}->{}", &oxm[i + 1..], &oxm[..i]),
+                    None => write!(f, "set_field:{}", oxm),
+                }
+            }
+            PushPbb(ethertype) => write!(f, "push_pbb:0x{:04x}", ethertype),
+            PopPbb => write!(f, "pop_pbb"),
+            Meter(meter_id) => write!(f, "meter:{}", meter_id),
+            Experimenter {
+                experimenter,
+                ref data,
+            } => write!(
+                f,
+                "experimenter(0x{:08x},{})",
+                experimenter,
+                text::format_hex(data)
+            ),
+            Unknown { kind, ref data } => write!(f, "unknown({},{})", kind, text::format_hex(data)),
+        }
+    }
+}
+
+/// Return the `max_len` of an output action to `port` when it is not given: the whole packet is
+/// sent to the controller, and `max_len` is ignored for the other ports.
+fn default_max_len(port: PortNumber) -> u16 {
+    match port {
+        PortNumber::Controller => CONTROLLER_NO_BUFFER,
+        _ => 0,
+    }
+}
+
+impl<E: Repr> FromStr for Action<E> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        use self::Action::*;
+        let s = s.trim();
+        if let Some(args) = text::strip_call(s, "output") {
+            let mut port = None;
+            let mut max_len = None;
+            for arg in text::split(args) {
+                let i = arg.find('=').ok_or_else(|| ParseError::bad_value(arg))?;
+                match arg[..i].trim() {
+                    "port" => port = Some(text::parse_port(&arg[i + 1..])?),
+                    "max_len" => max_len = Some(text::parse_u16(&arg[i + 1..])?),
+                    name => return Err(ParseError::unknown_name(name)),
+                }
+            }
+            let port = port.ok_or_else(|| ParseError::bad_value(s))?;
+            let max_len = max_len.unwrap_or_else(|| default_max_len(port));
+            return Ok(Output { port, max_len });
+        }
+        if let Some(args) = text::strip_call(s, "experimenter") {
+            let args = text::split(args);
+            let experimenter = text::parse_u32(args.first().unwrap_or(&""))?;
+            let data = text::parse_hex(args.get(1).unwrap_or(&""))?;
+            return Ok(Experimenter { experimenter, data });
+        }
+        if let Some(args) = text::strip_call(s, "unknown") {
+            let args = text::split(args);
+            let kind = text::parse_u16(args.first().unwrap_or(&""))?;
+            let data = text::parse_hex(args.get(1).unwrap_or(&""))?;
+            return Ok(Unknown { kind, data });
+        }
+
+        let (name, arg) = match s.find(':') {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+        let arg = || arg.ok_or_else(|| ParseError::bad_value(s));
+        Ok(match name {
+            "output" => {
+                let port = text::parse_port(arg()?)?;
+                Output {
+                    port,
+                    max_len: default_max_len(port),
+                }
+            }
+            "CONTROLLER" | "controller" => Output {
+                port: PortNumber::Controller,
+                max_len: match arg() {
+                    Ok(max_len) => text::parse_u16(max_len)?,
+                    Err(_) => CONTROLLER_NO_BUFFER,
+                },
+            },
+            "IN_PORT" | "in_port" | "TABLE" | "table" | "NORMAL" | "normal" | "FLOOD"
+            | "flood" | "ALL" | "all" | "LOCAL" | "local" => Output {
+                port: text::parse_port(name)?,
+                max_len: 0,
+            },
+            "copy_ttl_out" => CopyTtlOut,
+            "copy_ttl_in" => CopyTtlIn,
+            "set_mpls_ttl" => SetMplsTtl(text::parse_u8(arg()?)?),
+            "dec_mpls_ttl" => DecMplsTtl,
+            "push_vlan" => PushVlan(text::parse_u16(arg()?)?),
+            "pop_vlan" => PopVlan,
+            "push_mpls" => PushMpls(text::parse_u16(arg()?)?),
+            "pop_mpls" => PopMpls(text::parse_u16(arg()?)?),
+            "set_queue" => SetQueue(text::parse_u32(arg()?)?),
+            "group" => Group(text::parse_u32(arg()?)?),
+            "mod_nw_ttl" | "set_nw_ttl" => SetNwTtl(text::parse_u8(arg()?)?),
+            "dec_ttl" => DecNwTtl,
+            "set_field" => {
+                let arg = arg()?;
+                let i = arg.find("->").ok_or_else(|| ParseError::bad_value(arg))?;
+                SetField(format!("{}={}", &arg[i + 2..], &arg[..i]).parse()?)
+            }
+            "push_pbb" => PushPbb(text::parse_u16(arg()?)?),
+            "pop_pbb" => PopPbb,
+            "meter" => Meter(text::parse_u32(arg()?)?),
+            _ => match text::parse_u32(s) {
+                // A bare port number is an output action.
+                Ok(port) => {
+                    let port = PortNumber::from(port);
+                    Output {
+                        port,
+                        max_len: default_max_len(port),
+                    }
+                }
+                Err(_) => return Err(ParseError::unknown_name(name)),
+            },
+        })
+    }
+}
@@ -0,0 +1,166 @@
+//! A [`tokio_util`](https://docs.rs/tokio-util) codec that decodes and encodes OpenFlow messages.
+//!
+//! ```rust,ignore
+//! let mut messages = Framed::new(stream, OpenFlowCodec::<ExperimenterOxm>::new());
+//! while let Some(message) = messages.next().await {
+//!     let message = message?;
+//!     // ...
+//! }
+//! ```
+//!
+//! This module is only available with the `tokio` feature.
+
+use std::io;
+use std::marker::PhantomData;
+
+use bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+
+use {Error, Repr};
+use framer::{message_len, DEFAULT_MAX_LEN};
+use openflow::PacketRepr;
+use oxm::ExperimenterOxm;
+
+/// Decode and encode [OpenFlow messages](../openflow/struct.PacketRepr.html).
+///
+/// Errors are returned as `io::Error`. The ones returned by the parser have the
+/// `InvalidData` kind, and wrap the [`Error`](../enum.Error.html). Messages that are too long or
+/// too short make the stream impossible to decode, so the connection should then be closed.
+#[derive(Debug, Clone)]
+pub struct OpenFlowCodec<E = ExperimenterOxm> {
+    max_len: usize,
+    experimenter: PhantomData<E>,
+}
+
+impl<E> Default for OpenFlowCodec<E> {
+    fn default() -> Self {
+        OpenFlowCodec::new()
+    }
+}
+
+impl<E> OpenFlowCodec<E> {
+    /// Return a codec that accepts messages of any length.
+    pub fn new() -> Self {
+        OpenFlowCodec::with_max_len(DEFAULT_MAX_LEN)
+    }
+
+    /// Return a codec that rejects the received messages longer than `max_len` bytes, header
+    /// included.
+    pub fn with_max_len(max_len: usize) -> Self {
+        OpenFlowCodec {
+            max_len,
+            experimenter: PhantomData,
+        }
+    }
+
+    /// Return the maximum length of a received message.
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+}
+
+fn invalid_data(err: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+impl<E: Repr> Decoder for OpenFlowCodec<E> {
+    type Item = PacketRepr<E>;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        let len = match message_len(src, self.max_len).map_err(invalid_data)? {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+        if src.len() < len {
+            src.reserve(len - src.len());
+            return Ok(None);
+        }
+        let message = src.split_to(len);
+        PacketRepr::parse(&message).map(Some).map_err(invalid_data)
+    }
+}
+
+/// The `length` field of the message is
+/// [set automatically](../openflow/struct.PacketRepr.html#method.set_length_auto).
+impl<E: Repr> Encoder<PacketRepr<E>> for OpenFlowCodec<E> {
+    type Error = io::Error;
+
+    fn encode(&mut self, mut item: PacketRepr<E>, dst: &mut BytesMut) -> io::Result<()> {
+        let len = item.buffer_len();
+        if len > DEFAULT_MAX_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, Error::Exhausted));
+        }
+        item.set_length_auto();
+        let start = dst.len();
+        dst.resize(start + len, 0);
+        if let Err(err) = item.emit(&mut dst[start..]) {
+            dst.truncate(start);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, err));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use openflow::{Kind, PayloadRepr, Version};
+
+    static BYTES: [u8; 20] = [
+        // echo request, xid = 1, payload = "ping"
+        0x06, 0x02, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x01, 0x70, 0x69, 0x6e, 0x67,
+        // features request, xid = 2
+        0x06, 0x05, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02,
+    ];
+
+    fn messages() -> Vec<PacketRepr> {
+        vec![
+            PacketRepr {
+                version: Version::OpenFlow1Dot5,
+                length: 12,
+                kind: Kind::EchoRequest,
+                xid: 1,
+                payload: PayloadRepr::EchoRequest(b"ping".to_vec()),
+            },
+            PacketRepr {
+                version: Version::OpenFlow1Dot5,
+                length: 8,
+                kind: Kind::FeaturesRequest,
+                xid: 2,
+                payload: PayloadRepr::FeaturesRequest,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut codec = OpenFlowCodec::new();
+        let mut src = BytesMut::new();
+        let mut decoded = Vec::new();
+        for byte in BYTES.iter() {
+            src.extend_from_slice(&[*byte]);
+            while let Some(message) = codec.decode(&mut src).unwrap() {
+                decoded.push(message);
+            }
+        }
+        assert_eq!(decoded, messages());
+        assert!(src.is_empty());
+
+        let mut codec = OpenFlowCodec::<ExperimenterOxm>::with_max_len(11);
+        let mut src = BytesMut::from(&BYTES[..]);
+        let err = codec.decode(&mut src).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut codec = OpenFlowCodec::new();
+        let mut dst = BytesMut::new();
+        for mut message in messages() {
+            message.length = 0;
+            codec.encode(message, &mut dst).unwrap();
+        }
+        assert_eq!(&dst[..], &BYTES[..]);
+    }
+}
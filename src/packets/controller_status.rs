@@ -0,0 +1,305 @@
+//! Types to parse and emit controller status messages (`OFPT_CONTROLLER_STATUS`).
+//!
+//! A controller status describes one of the OpenFlow channels of a switch. The same structure is
+//! used in asynchronous controller status messages, sent when the status of a channel changes, and
+//! in the body of controller status multipart replies (`OFPMP_CONTROLLER_STATUS`), that list all
+//! the channels of the switch.
+//!
+//! ```no_rust
+//! +--------+--------+--------+--------+
+//! |      length     |     short_id    |
+//! +--------+--------+--------+--------+
+//! |                role               |
+//! +--------+--------+--------+--------+
+//! | reason |channel |                 |
+//! +--------+--------+                 +
+//! |              padding              |
+//! +--------+--------+--------+--------+
+//! |             properties            |
+//! |         (variable length)         |
+//! +--------+--------+--------+--------+
+//! ```
+use {Error, Repr, Result};
+use byteorder::{ByteOrder, NetworkEndian};
+use packets::list;
+use property::{self, ExperimenterRepr, UnknownRepr};
+
+const PROPERTY_URI: u16 = 0;
+
+enum_with_unknown! {
+    /// Role of a controller.
+    pub doc enum Role(u32) {
+        /// Don't change current role.
+        NoChange = 0,
+        /// Default role, full access.
+        Equal = 1,
+        /// Full access, at most one master.
+        Master = 2,
+        /// Read-only access.
+        Slave = 3
+    }
+}
+
+enum_with_unknown! {
+    /// Why the controller status is sent.
+    pub doc enum Reason(u8) {
+        /// Controller status was requested.
+        Request = 0,
+        /// Oper status of channel changed.
+        ChannelStatus = 1,
+        /// Controller role changed.
+        Role = 2,
+        /// New controller added.
+        ControllerAdded = 3,
+        /// Controller removed from config.
+        ControllerRemoved = 4,
+        /// Short id has changed.
+        ShortId = 5,
+        /// Experimenter data changed.
+        Experimenter = 6
+    }
+}
+
+enum_with_unknown! {
+    /// Status of an OpenFlow channel.
+    pub doc enum ChannelStatus(u8) {
+        /// Connection is setup, Hello messages exchanged.
+        Up = 0,
+        /// Connection is down.
+        Down = 1
+    }
+}
+
+/// A property of a controller status.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PropertyRepr {
+    /// URI of the controller, for instance `tls:192.168.34.23:6653`. A URI that is not valid
+    /// UTF-8 is [`Malformed`](../enum.Error.html#variant.Malformed).
+    Uri(String),
+    /// Experimenter defined property.
+    Experimenter(ExperimenterRepr),
+    /// A property this library does not know about.
+    Unknown(UnknownRepr),
+}
+
+impl Repr for PropertyRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = property::Packet::new_checked(buffer)?;
+        Ok(match packet.kind() {
+            PROPERTY_URI => PropertyRepr::Uri(
+                String::from_utf8(packet.payload().to_vec()).map_err(|_| Error::Malformed)?,
+            ),
+            property::EXPERIMENTER => PropertyRepr::Experimenter(ExperimenterRepr::parse(buffer)?),
+            _ => PropertyRepr::Unknown(UnknownRepr::parse(buffer)?),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        match *self {
+            // type (2 bytes) + length (2 bytes) + uri
+            PropertyRepr::Uri(ref uri) => 4 + uri.len(),
+            PropertyRepr::Experimenter(ref repr) => repr.buffer_len(),
+            PropertyRepr::Unknown(ref repr) => repr.buffer_len(),
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        let uri = match *self {
+            PropertyRepr::Uri(ref uri) => uri,
+            PropertyRepr::Experimenter(ref repr) => return repr.emit(buffer),
+            PropertyRepr::Unknown(ref repr) => return repr.emit(buffer),
+        };
+        let length = self.buffer_len();
+        if buffer.len() < length {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = property::Packet::new(&mut buffer[..length]);
+        packet.set_kind(PROPERTY_URI);
+        packet.set_length(length as u16);
+        packet.payload_mut().copy_from_slice(uri.as_bytes());
+        Ok(())
+    }
+}
+
+/// A buffer representing a controller status.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod field {
+    #![allow(non_snake_case)]
+    use field::*;
+
+    pub const LENGTH: Field = 0..2;
+    pub const SHORT_ID: Field = 2..4;
+    pub const ROLE: Field = 4..8;
+    pub const REASON: usize = 8;
+    pub const CHANNEL_STATUS: usize = 9;
+    pub const PADDING: Field = 10..16;
+
+    pub fn PROPERTIES(length: usize) -> Field {
+        PADDING.end..length
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub fn new(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < field::LENGTH.end || len < self.length() as usize {
+            Err(Error::Truncated)
+        } else if (self.length() as usize) < field::PADDING.end {
+            Err(Error::Malformed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `length` field.
+    pub fn length(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::LENGTH])
+    }
+
+    /// Return the `short_id` field.
+    pub fn short_id(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::SHORT_ID])
+    }
+
+    /// Return the `role` field.
+    pub fn role(&self) -> Role {
+        let data = self.buffer.as_ref();
+        Role::from(NetworkEndian::read_u32(&data[field::ROLE]))
+    }
+
+    /// Return the `reason` field.
+    pub fn reason(&self) -> Reason {
+        Reason::from(self.buffer.as_ref()[field::REASON])
+    }
+
+    /// Return the `channel_status` field.
+    pub fn channel_status(&self) -> ChannelStatus {
+        ChannelStatus::from(self.buffer.as_ref()[field::CHANNEL_STATUS])
+    }
+
+    /// Return the `properties` field.
+    pub fn properties(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[field::PROPERTIES(self.length() as usize)]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the `length` field.
+    pub fn set_length(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::LENGTH], value)
+    }
+
+    /// Set the `short_id` field.
+    pub fn set_short_id(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::SHORT_ID], value)
+    }
+
+    /// Set the `role` field.
+    pub fn set_role(&mut self, value: Role) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::ROLE], value.into())
+    }
+
+    /// Set the `reason` field.
+    pub fn set_reason(&mut self, value: Reason) {
+        self.buffer.as_mut()[field::REASON] = value.into();
+    }
+
+    /// Set the `channel_status` field.
+    pub fn set_channel_status(&mut self, value: ChannelStatus) {
+        self.buffer.as_mut()[field::CHANNEL_STATUS] = value.into();
+    }
+
+    /// Set the `padding` field.
+    pub fn set_padding(&mut self) {
+        let data = self.buffer.as_mut();
+        for byte in &mut data[field::PADDING] {
+            *byte = 0;
+        }
+    }
+
+    /// Return a mutable pointer to the `properties` field. Note that the length field must be set
+    /// already.
+    pub fn properties_mut(&mut self) -> &mut [u8] {
+        let length = self.length() as usize;
+        let data = self.buffer.as_mut();
+        &mut data[field::PROPERTIES(length)]
+    }
+}
+
+/// A high-level representation of a controller status.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PacketRepr {
+    /// ID number the switch uses to identify the controller.
+    pub short_id: u16,
+    /// Role of the controller.
+    pub role: Role,
+    /// Why the status is sent.
+    pub reason: Reason,
+    /// Status of the OpenFlow channel.
+    pub channel_status: ChannelStatus,
+    pub properties: Vec<PropertyRepr>,
+}
+
+impl list::Entry for PacketRepr {
+    const LENGTH_OFFSET: usize = field::LENGTH.start;
+}
+
+impl Repr for PacketRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        // the properties must fill the message exactly
+        if packet.length() as usize != buffer.len() {
+            return Err(Error::Malformed);
+        }
+        Ok(PacketRepr {
+            short_id: packet.short_id(),
+            role: packet.role(),
+            reason: packet.reason(),
+            channel_status: packet.channel_status(),
+            properties: property::parse_list(packet.properties())?,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        field::PADDING.end + property::list_len(&self.properties)
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(buffer);
+        packet.set_length(self.buffer_len() as u16);
+        packet.set_short_id(self.short_id);
+        packet.set_role(self.role);
+        packet.set_reason(self.reason);
+        packet.set_channel_status(self.channel_status);
+        packet.set_padding();
+        property::emit_list(&self.properties, packet.properties_mut())
+    }
+}
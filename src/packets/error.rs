@@ -2,6 +2,7 @@
 //!
 use {Error, Repr, Result};
 use byteorder::{ByteOrder, NetworkEndian};
+use openflow::Version;
 
 enum_with_unknown! {
     /// Represent the type of the error
@@ -664,18 +665,29 @@ pub struct PacketRepr {
     pub data: Vec<u8>,
 }
 
-impl Repr for PacketRepr {
-    fn buffer_len(&self) -> usize {
-        self.data.len() + Packet::<&[u8]>::header_len()
+impl PacketRepr {
+    /// Return the `HelloFailed(Incompatible)` error to send when the version negotiation failed.
+    /// As recommended by the specification, the data is an ASCII string that lists the
+    /// supported versions.
+    pub fn hello_incompatible(versions: &[Version]) -> Self {
+        let versions: Vec<String> = versions
+            .iter()
+            .map(|version| format!("0x{:02x}", u8::from(*version)))
+            .collect();
+        PacketRepr {
+            header: Header::HelloFailed(HelloFailedCode::Incompatible),
+            data: format!("supported versions: {}", versions.join(", ")).into_bytes(),
+        }
     }
+}
 
+impl Header {
     #[cfg_attr(rustfmt, rustfmt_skip)]
-    fn parse(buffer: &[u8]) -> Result<Self> {
+    fn parse<T: AsRef<[u8]>>(packet: &Packet<T>) -> Result<Self> {
         use self::Kind::*;
 
-        let packet = Packet::new_checked(buffer)?;
         let code = packet.code();
-        let header = match packet.kind() {
+        Ok(match packet.kind() {
             HelloFailed         => Header::HelloFailed(HelloFailedCode::from(code)),
             BadRequest          => Header::BadRequest(BadRequestCode::from(code)),
             BadAction           => Header::BadAction(BadActionCode::from(code)),
@@ -696,26 +708,15 @@ impl Repr for PacketRepr {
             BundleFailed        => Header::BundleFailed(BundleFailedCode::from(code)),
             Experimenter        => Header::Experimenter(code),
             _Unknown(_)         => return Err(Error::Malformed),
-        };
-        let repr = PacketRepr {
-            header: header,
-            data: packet.payload().to_vec(),
-        };
-        Ok(repr)
+        })
     }
 
     #[cfg_attr(rustfmt, rustfmt_skip)]
-    /// Emit a high-level representation into an error message into a buffer
-    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+    /// Return the `type` and `code` fields of an error message with this header.
+    fn kind_and_code(&self) -> (Kind, u16) {
         use self::Header::*;
 
-        if buffer.len() < self.buffer_len() {
-            return Err(Error::Exhausted);
-        }
-
-        let mut packet = Packet::new(buffer);
-
-        let (kind, code) = match self.header {
+        match *self {
             HelloFailed(code)           => (Kind::HelloFailed,          u16::from(code)),
             BadRequest(code)            => (Kind::BadRequest,           u16::from(code)),
             BadAction(code)             => (Kind::BadAction,            u16::from(code)),
@@ -735,10 +736,76 @@ impl Repr for PacketRepr {
             FlowMonitorFailed(code)     => (Kind::FlowMonitorFailed,    u16::from(code)),
             BundleFailed(code)          => (Kind::BundleFailed,         u16::from(code)),
             Experimenter(code)          => (Kind::Experimenter,         code),
+        }
+    }
+}
+
+impl Repr for PacketRepr {
+    fn buffer_len(&self) -> usize {
+        self.data.len() + Packet::<&[u8]>::header_len()
+    }
+
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        let repr = PacketRepr {
+            header: Header::parse(&packet)?,
+            data: packet.payload().to_vec(),
         };
+        Ok(repr)
+    }
+
+    /// Emit a high-level representation into an error message into a buffer
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+
+        let mut packet = Packet::new(buffer);
+        let (kind, code) = self.header.kind_and_code();
         packet.set_kind(kind);
         packet.set_code(code);
         packet.payload_mut().copy_from_slice(self.data.as_slice());
         Ok(())
     }
 }
+
+/// An error message that borrows its data from the buffer it was parsed from.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PacketReprRef<'a> {
+    pub header: Header,
+    pub data: &'a [u8],
+}
+
+impl<'a> PacketReprRef<'a> {
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        Ok(PacketReprRef {
+            header: Header::parse(&packet)?,
+            data: packet.payload(),
+        })
+    }
+
+    pub fn buffer_len(&self) -> usize {
+        self.data.len() + Packet::<&[u8]>::header_len()
+    }
+
+    pub fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(buffer);
+        let (kind, code) = self.header.kind_and_code();
+        packet.set_kind(kind);
+        packet.set_code(code);
+        packet.payload_mut().copy_from_slice(self.data);
+        Ok(())
+    }
+
+    /// Copy the data into an owned error message.
+    pub fn to_repr(&self) -> PacketRepr {
+        PacketRepr {
+            header: self.header.clone(),
+            data: self.data.to_vec(),
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Split a byte stream, typically read from a TCP connection, into OpenFlow messages.
+//!
+//! ```rust,ignore
+//! let mut framer = Framer::new();
+//! loop {
+//!     let n = stream.read(&mut chunk)?;
+//!     framer.push(&chunk[..n]);
+//!     while let Some(packet) = framer.next_message()? {
+//!         let repr = PacketRepr::parse(packet.into_inner())?;
+//!         // ...
+//!     }
+//! }
+//! ```
+
+use {Error, Result};
+use openflow::{Packet, HEADER_LEN};
+
+/// The largest message an OpenFlow header can describe.
+pub const DEFAULT_MAX_LEN: usize = 0xffff;
+
+/// Buffer the chunks of a byte stream, and return the OpenFlow messages they contain once they
+/// are complete.
+///
+/// The `length` field of the header is the only way to find where a message ends, so once a
+/// header with an invalid length is found, the stream cannot be split anymore: every following
+/// call to [`next_message()`](#method.next_message) returns the same error, and the connection
+/// should be closed.
+#[derive(Debug, Clone)]
+pub struct Framer {
+    buffer: Vec<u8>,
+    /// Offset of the first byte that has not been returned yet.
+    start: usize,
+    max_len: usize,
+}
+
+impl Default for Framer {
+    fn default() -> Self {
+        Framer::new()
+    }
+}
+
+impl Framer {
+    /// Return a framer that accepts messages of any length.
+    pub fn new() -> Self {
+        Framer::with_max_len(DEFAULT_MAX_LEN)
+    }
+
+    /// Return a framer that rejects the messages longer than `max_len` bytes, header included.
+    pub fn with_max_len(max_len: usize) -> Self {
+        Framer {
+            buffer: Vec::new(),
+            start: 0,
+            max_len,
+        }
+    }
+
+    /// Return the maximum length of a message.
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Return the number of bytes that have been pushed but not returned as a message yet.
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len() - self.start
+    }
+
+    /// Append a chunk of the stream.
+    pub fn push(&mut self, bytes: &[u8]) {
+        if self.start > 0 {
+            self.buffer.drain(..self.start);
+            self.start = 0;
+        }
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Return the next complete message, or `None` if more bytes are needed.
+    ///
+    /// Return [`Error::Malformed`](../enum.Error.html#variant.Malformed) if the `length` field of
+    /// the next message is shorter than the header, and
+    /// [`Error::Exhausted`](../enum.Error.html#variant.Exhausted) if it exceeds the
+    /// [maximum length](#method.max_len).
+    pub fn next_message(&mut self) -> Result<Option<Packet<&[u8]>>> {
+        let pending = &self.buffer[self.start..];
+        match message_len(pending, self.max_len)? {
+            Some(len) if len <= pending.len() => {
+                self.start += len;
+                Ok(Some(Packet::new(&pending[..len])))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Return the length of the message that starts at the beginning of `buffer`, as found in its
+/// header, or `None` if the header is not complete yet.
+///
+/// Return [`Error::Malformed`](../enum.Error.html#variant.Malformed) if that length is shorter
+/// than the header, and [`Error::Exhausted`](../enum.Error.html#variant.Exhausted) if it exceeds
+/// `max_len`.
+pub fn message_len(buffer: &[u8], max_len: usize) -> Result<Option<usize>> {
+    let packet = match Packet::new_checked(buffer) {
+        Ok(packet) => packet,
+        Err(_) => return Ok(None),
+    };
+    let len = packet.length() as usize;
+    if len < HEADER_LEN {
+        return Err(Error::Malformed);
+    }
+    if len > max_len {
+        return Err(Error::Exhausted);
+    }
+    Ok(Some(len))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use openflow::Kind;
+
+    static BYTES: [u8; 20] = [
+        // echo request, xid = 1, payload = "ping"
+        0x06, 0x02, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x01, 0x70, 0x69, 0x6e, 0x67,
+        // features request, xid = 2
+        0x06, 0x05, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02,
+    ];
+
+    fn messages(framer: &mut Framer) -> Vec<(Kind, u32, Vec<u8>)> {
+        let mut messages = Vec::new();
+        while let Some(packet) = framer.next_message().unwrap() {
+            messages.push((packet.kind(), packet.xid(), packet.payload().to_vec()));
+        }
+        messages
+    }
+
+    #[test]
+    fn test_chunks() {
+        let expected = vec![
+            (Kind::EchoRequest, 1, b"ping".to_vec()),
+            (Kind::FeaturesRequest, 2, vec![]),
+        ];
+
+        let mut framer = Framer::new();
+        framer.push(&BYTES);
+        assert_eq!(messages(&mut framer), expected);
+        assert_eq!(framer.buffered_len(), 0);
+
+        let mut framer = Framer::new();
+        let mut received = Vec::new();
+        for byte in BYTES.iter() {
+            framer.push(&[*byte]);
+            received.extend(messages(&mut framer));
+        }
+        assert_eq!(received, expected);
+
+        let mut framer = Framer::new();
+        framer.push(&BYTES[..15]);
+        assert_eq!(messages(&mut framer), &expected[..1]);
+        assert_eq!(framer.buffered_len(), 3);
+        framer.push(&BYTES[15..]);
+        assert_eq!(messages(&mut framer), &expected[1..]);
+    }
+
+    #[test]
+    fn test_bad_length() {
+        let mut framer = Framer::new();
+        framer.push(&[0x06, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(framer.next_message().unwrap_err(), Error::Malformed);
+        assert_eq!(framer.next_message().unwrap_err(), Error::Malformed);
+
+        let mut framer = Framer::with_max_len(11);
+        framer.push(&BYTES[..8]);
+        assert_eq!(framer.next_message().unwrap_err(), Error::Exhausted);
+
+        let mut framer = Framer::with_max_len(12);
+        framer.push(&BYTES);
+        assert_eq!(messages(&mut framer).len(), 2);
+    }
+}
@@ -0,0 +1,565 @@
+//! Types to parse and emit group modification messages (`OFPT_GROUP_MOD`).
+//!
+//! ```no_rust
+//! +--------+--------+--------+--------+
+//! |     command     |  type  |padding |
+//! +--------+--------+--------+--------+
+//! |              group_id             |
+//! +--------+--------+--------+--------+
+//! |bucket_array_len |     padding     |
+//! +--------+--------+--------+--------+
+//! |         command_bucket_id         |
+//! +--------+--------+--------+--------+
+//! |              buckets              |
+//! |         (bucket_array_len)        |
+//! +--------+--------+--------+--------+
+//! |             properties            |
+//! |         (variable length)         |
+//! +--------+--------+--------+--------+
+//! ```
+use {Error, Repr, Result};
+use byteorder::{ByteOrder, NetworkEndian};
+use oxm::ExperimenterOxm;
+use packets::action::Action;
+use packets::list;
+use property::{self, ExperimenterRepr, UnknownRepr};
+
+/// Last usable group number.
+pub const GROUP_MAX: u32 = 0xffff_ff00;
+/// Represents all groups for group delete commands.
+pub const GROUP_ALL: u32 = 0xffff_fffc;
+
+/// Last usable bucket id.
+pub const BUCKET_MAX: u32 = 0xffff_ff00;
+/// First bucket id in the list of buckets of a group.
+pub const BUCKET_FIRST: u32 = 0xffff_fffd;
+/// Last bucket id in the list of buckets of a group.
+pub const BUCKET_LAST: u32 = 0xffff_fffe;
+/// All the buckets in a group.
+pub const BUCKET_ALL: u32 = 0xffff_ffff;
+
+const BUCKET_PROPERTY_WEIGHT: u16 = 0;
+const BUCKET_PROPERTY_WATCH_PORT: u16 = 1;
+const BUCKET_PROPERTY_WATCH_GROUP: u16 = 2;
+
+enum_with_unknown! {
+    /// Group modification command.
+    pub doc enum Command(u16) {
+        /// New group.
+        Add = 0,
+        /// Modify all matching groups.
+        Modify = 1,
+        /// Delete all matching groups.
+        Delete = 2,
+        /// Insert action buckets to the already available list of action buckets in a matching
+        /// group.
+        InsertBucket = 3,
+        /// Remove all action buckets or any specific action bucket from matching group.
+        RemoveBucket = 5
+    }
+}
+
+enum_with_unknown! {
+    /// Group type.
+    pub doc enum GroupType(u8) {
+        /// All (multicast/broadcast) group.
+        All = 0,
+        /// Select group.
+        Select = 1,
+        /// Indirect group.
+        Indirect = 2,
+        /// Fast failover group.
+        FastFailover = 3
+    }
+}
+
+/// A property of a bucket.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BucketPropertyRepr {
+    /// Relative weight of the bucket. Only defined for select groups.
+    Weight(u16),
+    /// Port whose state affects whether this bucket is live. Only required for fast failover
+    /// groups.
+    WatchPort(u32),
+    /// Group whose state affects whether this bucket is live. Only required for fast failover
+    /// groups.
+    WatchGroup(u32),
+    /// Experimenter defined property.
+    Experimenter(ExperimenterRepr),
+    /// A property this library does not know about.
+    Unknown(UnknownRepr),
+}
+
+impl Repr for BucketPropertyRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = property::Packet::new_checked(buffer)?;
+        Ok(match packet.kind() {
+            BUCKET_PROPERTY_WEIGHT | BUCKET_PROPERTY_WATCH_PORT | BUCKET_PROPERTY_WATCH_GROUP => {
+                // value (4 bytes, or weight (2 bytes) + padding (2 bytes))
+                if packet.length() != 8 {
+                    return Err(Error::Malformed);
+                }
+                let payload = packet.payload();
+                match packet.kind() {
+                    BUCKET_PROPERTY_WEIGHT => {
+                        BucketPropertyRepr::Weight(NetworkEndian::read_u16(&payload[0..2]))
+                    }
+                    BUCKET_PROPERTY_WATCH_PORT => {
+                        BucketPropertyRepr::WatchPort(NetworkEndian::read_u32(&payload[0..4]))
+                    }
+                    _ => BucketPropertyRepr::WatchGroup(NetworkEndian::read_u32(&payload[0..4])),
+                }
+            }
+            property::EXPERIMENTER => {
+                BucketPropertyRepr::Experimenter(ExperimenterRepr::parse(buffer)?)
+            }
+            _ => BucketPropertyRepr::Unknown(UnknownRepr::parse(buffer)?),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        match *self {
+            BucketPropertyRepr::Weight(_)
+            | BucketPropertyRepr::WatchPort(_)
+            | BucketPropertyRepr::WatchGroup(_) => 8,
+            BucketPropertyRepr::Experimenter(ref repr) => repr.buffer_len(),
+            BucketPropertyRepr::Unknown(ref repr) => repr.buffer_len(),
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        let kind = match *self {
+            BucketPropertyRepr::Weight(_) => BUCKET_PROPERTY_WEIGHT,
+            BucketPropertyRepr::WatchPort(_) => BUCKET_PROPERTY_WATCH_PORT,
+            BucketPropertyRepr::WatchGroup(_) => BUCKET_PROPERTY_WATCH_GROUP,
+            BucketPropertyRepr::Experimenter(ref repr) => return repr.emit(buffer),
+            BucketPropertyRepr::Unknown(ref repr) => return repr.emit(buffer),
+        };
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = property::Packet::new(buffer);
+        packet.set_kind(kind);
+        packet.set_length(8);
+        let payload = packet.payload_mut();
+        match *self {
+            BucketPropertyRepr::Weight(weight) => {
+                NetworkEndian::write_u16(&mut payload[0..2], weight);
+                NetworkEndian::write_u16(&mut payload[2..4], 0);
+            }
+            BucketPropertyRepr::WatchPort(value) | BucketPropertyRepr::WatchGroup(value) => {
+                NetworkEndian::write_u32(&mut payload[0..4], value)
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+}
+
+/// A buffer representing a bucket.
+///
+/// ```no_rust
+/// +--------+--------+--------+--------+
+/// |      length     |action_array_len |
+/// +--------+--------+--------+--------+
+/// |             bucket_id             |
+/// +--------+--------+--------+--------+
+/// |              actions              |
+/// |         (action_array_len)        |
+/// +--------+--------+--------+--------+
+/// |             properties            |
+/// |         (variable length)         |
+/// +--------+--------+--------+--------+
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Bucket<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod bucket_field {
+    #![allow(non_snake_case)]
+    use field::*;
+
+    pub const LENGTH: Field = 0..2;
+    pub const ACTION_ARRAY_LEN: Field = 2..4;
+    pub const BUCKET_ID: Field = 4..8;
+
+    pub fn ACTIONS(action_array_len: usize) -> Field {
+        BUCKET_ID.end..BUCKET_ID.end + action_array_len
+    }
+
+    pub fn PROPERTIES(action_array_len: usize, length: usize) -> Field {
+        ACTIONS(action_array_len).end..length
+    }
+}
+
+impl<T: AsRef<[u8]>> Bucket<T> {
+    pub fn new(buffer: T) -> Bucket<T> {
+        Bucket { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Bucket<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < bucket_field::BUCKET_ID.end || len < self.length() as usize {
+            return Err(Error::Truncated);
+        }
+        let actions = bucket_field::ACTIONS(self.action_array_len() as usize);
+        if (self.length() as usize) < actions.end {
+            Err(Error::Malformed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `len` field.
+    pub fn length(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[bucket_field::LENGTH])
+    }
+
+    /// Return the `action_array_len` field.
+    pub fn action_array_len(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[bucket_field::ACTION_ARRAY_LEN])
+    }
+
+    /// Return the `bucket_id` field.
+    pub fn bucket_id(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[bucket_field::BUCKET_ID])
+    }
+
+    /// Return the `actions` field.
+    pub fn actions(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[bucket_field::ACTIONS(self.action_array_len() as usize)]
+    }
+
+    /// Return the `properties` field.
+    pub fn properties(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[bucket_field::PROPERTIES(self.action_array_len() as usize, self.length() as usize)]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Bucket<T> {
+    /// Set the `len` field.
+    pub fn set_length(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[bucket_field::LENGTH], value)
+    }
+
+    /// Set the `action_array_len` field.
+    pub fn set_action_array_len(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[bucket_field::ACTION_ARRAY_LEN], value)
+    }
+
+    /// Set the `bucket_id` field.
+    pub fn set_bucket_id(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[bucket_field::BUCKET_ID], value)
+    }
+
+    /// Return a mutable pointer to the `actions` field. Note that the `action_array_len` field
+    /// must be set already.
+    pub fn actions_mut(&mut self) -> &mut [u8] {
+        let action_array_len = self.action_array_len() as usize;
+        let data = self.buffer.as_mut();
+        &mut data[bucket_field::ACTIONS(action_array_len)]
+    }
+
+    /// Return a mutable pointer to the `properties` field. Note that the `len` and
+    /// `action_array_len` fields must be set already.
+    pub fn properties_mut(&mut self) -> &mut [u8] {
+        let action_array_len = self.action_array_len() as usize;
+        let length = self.length() as usize;
+        let data = self.buffer.as_mut();
+        &mut data[bucket_field::PROPERTIES(action_array_len, length)]
+    }
+}
+
+/// A high-level representation of a bucket.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BucketRepr<E = ExperimenterOxm> {
+    /// Bucket id, unique within the group.
+    pub bucket_id: u32,
+    /// Actions applied to the packets processed by this bucket.
+    pub actions: Vec<Action<E>>,
+    pub properties: Vec<BucketPropertyRepr>,
+}
+
+impl<E: Repr> list::Entry for BucketRepr<E> {
+    const LENGTH_OFFSET: usize = bucket_field::LENGTH.start;
+}
+
+impl<E: Repr> Repr for BucketRepr<E> {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Bucket::new_checked(buffer)?;
+        Ok(BucketRepr {
+            bucket_id: packet.bucket_id(),
+            actions: list::parse(packet.actions())?,
+            properties: property::parse_list(packet.properties())?,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        bucket_field::BUCKET_ID.end + list::buffer_len(&self.actions)
+            + property::list_len(&self.properties)
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Bucket::new(buffer);
+        packet.set_length(self.buffer_len() as u16);
+        packet.set_action_array_len(list::buffer_len(&self.actions) as u16);
+        packet.set_bucket_id(self.bucket_id);
+        list::emit(&self.actions, packet.actions_mut())?;
+        property::emit_list(&self.properties, packet.properties_mut())
+    }
+}
+
+/// A property of a group.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PropertyRepr {
+    /// Experimenter defined property.
+    Experimenter(ExperimenterRepr),
+    /// A property this library does not know about.
+    Unknown(UnknownRepr),
+}
+
+impl Repr for PropertyRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = property::Packet::new_checked(buffer)?;
+        Ok(match packet.kind() {
+            property::EXPERIMENTER => PropertyRepr::Experimenter(ExperimenterRepr::parse(buffer)?),
+            _ => PropertyRepr::Unknown(UnknownRepr::parse(buffer)?),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        match *self {
+            PropertyRepr::Experimenter(ref repr) => repr.buffer_len(),
+            PropertyRepr::Unknown(ref repr) => repr.buffer_len(),
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        match *self {
+            PropertyRepr::Experimenter(ref repr) => repr.emit(buffer),
+            PropertyRepr::Unknown(ref repr) => repr.emit(buffer),
+        }
+    }
+}
+
+/// A buffer representing a group modification message.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod field {
+    #![allow(non_snake_case)]
+    use field::*;
+
+    pub const COMMAND: Field = 0..2;
+    pub const GROUP_TYPE: usize = 2;
+    pub const PADDING_1: usize = 3;
+    pub const GROUP_ID: Field = 4..8;
+    pub const BUCKET_ARRAY_LEN: Field = 8..10;
+    pub const PADDING_2: Field = 10..12;
+    pub const COMMAND_BUCKET_ID: Field = 12..16;
+
+    pub fn BUCKETS(bucket_array_len: usize) -> Field {
+        COMMAND_BUCKET_ID.end..COMMAND_BUCKET_ID.end + bucket_array_len
+    }
+
+    pub fn PROPERTIES(bucket_array_len: usize) -> Rest {
+        BUCKETS(bucket_array_len).end..
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub fn new(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < field::COMMAND_BUCKET_ID.end
+            || len < field::BUCKETS(self.bucket_array_len() as usize).end
+        {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `command` field.
+    pub fn command(&self) -> Command {
+        let data = self.buffer.as_ref();
+        Command::from(NetworkEndian::read_u16(&data[field::COMMAND]))
+    }
+
+    /// Return the `type` field.
+    pub fn group_type(&self) -> GroupType {
+        GroupType::from(self.buffer.as_ref()[field::GROUP_TYPE])
+    }
+
+    /// Return the `group_id` field.
+    pub fn group_id(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[field::GROUP_ID])
+    }
+
+    /// Return the `bucket_array_len` field.
+    pub fn bucket_array_len(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::BUCKET_ARRAY_LEN])
+    }
+
+    /// Return the `command_bucket_id` field.
+    pub fn command_bucket_id(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[field::COMMAND_BUCKET_ID])
+    }
+
+    /// Return the `buckets` field.
+    pub fn buckets(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[field::BUCKETS(self.bucket_array_len() as usize)]
+    }
+
+    /// Return the `properties` field.
+    pub fn properties(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[field::PROPERTIES(self.bucket_array_len() as usize)]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the `command` field.
+    pub fn set_command(&mut self, value: Command) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::COMMAND], value.into())
+    }
+
+    /// Set the `type` field.
+    pub fn set_group_type(&mut self, value: GroupType) {
+        self.buffer.as_mut()[field::GROUP_TYPE] = value.into();
+    }
+
+    /// Set the `group_id` field.
+    pub fn set_group_id(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::GROUP_ID], value)
+    }
+
+    /// Set the `bucket_array_len` field.
+    pub fn set_bucket_array_len(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::BUCKET_ARRAY_LEN], value)
+    }
+
+    /// Set the `command_bucket_id` field.
+    pub fn set_command_bucket_id(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::COMMAND_BUCKET_ID], value)
+    }
+
+    /// Set the padding fields.
+    pub fn set_padding(&mut self) {
+        let data = self.buffer.as_mut();
+        data[field::PADDING_1] = 0;
+        NetworkEndian::write_u16(&mut data[field::PADDING_2], 0);
+    }
+
+    /// Return a mutable pointer to the `buckets` field. Note that the `bucket_array_len` field
+    /// must be set already.
+    pub fn buckets_mut(&mut self) -> &mut [u8] {
+        let bucket_array_len = self.bucket_array_len() as usize;
+        let data = self.buffer.as_mut();
+        &mut data[field::BUCKETS(bucket_array_len)]
+    }
+
+    /// Return a mutable pointer to the `properties` field. Note that the `bucket_array_len` field
+    /// must be set already.
+    pub fn properties_mut(&mut self) -> &mut [u8] {
+        let bucket_array_len = self.bucket_array_len() as usize;
+        let data = self.buffer.as_mut();
+        &mut data[field::PROPERTIES(bucket_array_len)]
+    }
+}
+
+/// A high-level representation of a group modification message.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PacketRepr<E = ExperimenterOxm> {
+    pub command: Command,
+    pub group_type: GroupType,
+    /// Group identifier.
+    pub group_id: u32,
+    /// Bucket id used by the `InsertBucket` and `RemoveBucket` commands. See the `BUCKET_XXX`
+    /// constants for the special values.
+    pub command_bucket_id: u32,
+    pub buckets: Vec<BucketRepr<E>>,
+    pub properties: Vec<PropertyRepr>,
+}
+
+impl<E: Repr> Repr for PacketRepr<E> {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        Ok(PacketRepr {
+            command: packet.command(),
+            group_type: packet.group_type(),
+            group_id: packet.group_id(),
+            command_bucket_id: packet.command_bucket_id(),
+            buckets: list::parse(packet.buckets())?,
+            properties: property::parse_list(packet.properties())?,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        field::COMMAND_BUCKET_ID.end + list::buffer_len(&self.buckets)
+            + property::list_len(&self.properties)
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        let length = self.buffer_len();
+        if buffer.len() < length {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(&mut buffer[..length]);
+        packet.set_command(self.command);
+        packet.set_group_type(self.group_type);
+        packet.set_group_id(self.group_id);
+        packet.set_bucket_array_len(list::buffer_len(&self.buckets) as u16);
+        packet.set_command_bucket_id(self.command_bucket_id);
+        packet.set_padding();
+        list::emit(&self.buckets, packet.buckets_mut())?;
+        property::emit_list(&self.properties, packet.properties_mut())
+    }
+}
@@ -66,14 +66,12 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Bitmap<T> {
         NetworkEndian::write_u32(&mut data[0..4], value)
     }
 
-    // Compute the bitmap from the given versions and set it.
+    /// Compute the bitmap from the given versions and set it. Versions that do not fit in a 32 bits
+    /// bitmap are ignored, see [`BitmapRepr::from_versions`].
+    ///
+    /// [`BitmapRepr::from_versions`]: struct.BitmapRepr.html#method.from_versions
     pub fn set_bitmap_from_versions(&mut self, versions: &[Version]) {
-        let mut bitmap: u32 = 0;
-        for version in versions {
-            // FIXME: this will panic for version > 31
-            bitmap |= (1 as u32) << u8::from(*version) as usize;
-        }
-        self.set_bitmap(bitmap)
+        self.set_bitmap(BitmapRepr::from_versions(versions).0)
     }
 }
 
@@ -82,6 +80,23 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Bitmap<T> {
 pub struct BitmapRepr(pub u32);
 
 impl BitmapRepr {
+    /// Return the bitmap of the given versions. Versions that do not fit in a 32 bits bitmap are
+    /// ignored.
+    pub fn from_versions(versions: &[Version]) -> Self {
+        let bitmap = versions
+            .iter()
+            .map(|version| u8::from(*version))
+            .filter(|version| *version < 32)
+            .fold(0, |bitmap, version| bitmap | 1 << version);
+        BitmapRepr(bitmap)
+    }
+
+    /// Return `true` if the given version is set in this bitmap.
+    pub fn contains(&self, version: Version) -> bool {
+        let version = u8::from(version);
+        version < 32 && self.0 & 1 << version != 0
+    }
+
     /// Parse a bitmap buffer and return a high-level representation.
     pub fn parse<T>(buffer: &Bitmap<T>) -> Result<Self>
     where
@@ -232,7 +232,12 @@ mod test {
         buf.set_length(8);
         {
             let mut payload_buf = Bitmap::new(buf.payload_mut());
-            payload_buf.set_bitmap_from_versions(&[Version::OpenFlow1Dot0, Version::OpenFlow1Dot3]);
+            // versions that do not fit in the bitmap are ignored
+            payload_buf.set_bitmap_from_versions(&[
+                Version::OpenFlow1Dot0,
+                Version::OpenFlow1Dot3,
+                Version::_Unknown(40),
+            ]);
         }
         assert_eq!(&buf.into_inner()[..], &BYTES[..]);
     }
@@ -2,11 +2,14 @@
 
 mod bitmap;
 mod element;
+mod negotiation;
 
 use {Error, Repr, Result};
 use byteorder::{ByteOrder, NetworkEndian};
+use openflow::Version;
 pub use self::bitmap::{Bitmap, BitmapRepr};
 pub use self::element::{Element, ElementData, ElementRepr, Kind};
+pub use self::negotiation::{incompatible_reply, negotiate};
 
 
 /// An octet buffer representing a Hello message. A Hello message is made of multiple Hello
@@ -108,6 +111,25 @@ impl PacketRepr {
     pub fn new(elements: Vec<ElementRepr>) -> Self {
         PacketRepr(elements)
     }
+
+    /// Return a Hello message with a single bitmap element, that advertises the given versions.
+    pub fn from_versions(versions: &[Version]) -> Self {
+        PacketRepr(vec![ElementRepr {
+            kind: Kind::Bitmap,
+            payload: ElementData::Bitmap(BitmapRepr::from_versions(versions)),
+        }])
+    }
+
+    /// Return the first version bitmap element of this Hello message, if any.
+    pub fn bitmap(&self) -> Option<BitmapRepr> {
+        self.0
+            .iter()
+            .filter_map(|element| match element.payload {
+                ElementData::Bitmap(bitmap) => Some(bitmap),
+                ElementData::Unknown(_) => None,
+            })
+            .next()
+    }
 }
 
 impl Repr for PacketRepr {
@@ -0,0 +1,156 @@
+//! Negotiation of the OpenFlow version of a connection.
+//!
+//! Both ends of a connection start by sending a Hello message. Its header carries the highest
+//! version supported by the sender, and it may contain a version bitmap element that lists all
+//! the supported versions. Once the Hello message of the other end is received:
+//!
+//! - if both Hello messages contain a bitmap, the negotiated version is the highest version set
+//!   in both bitmaps;
+//! - otherwise, the negotiated version is the smallest of the two header versions.
+//!
+//! If the negotiated version is not supported, the connection must be terminated after sending a
+//! [`HelloFailed(Incompatible)`](fn.incompatible_reply.html) error.
+
+use {Error, Repr, Result};
+use error;
+use openflow::{self, Version};
+use super::PacketRepr;
+
+/// Negotiate the version of a connection, given the versions we support and the Hello message
+/// we received.
+///
+/// `local` are the versions advertised in our own Hello message, which is expected to be built
+/// with [`PacketRepr::from_versions`](struct.PacketRepr.html#method.from_versions) and sent with
+/// the highest of these versions in its header. `remote_version` is the version in the header of
+/// the received Hello message, and `remote` its payload.
+///
+/// Return [`Error::IncompatibleVersion`](../enum.Error.html#variant.IncompatibleVersion) if no
+/// version can be agreed on.
+pub fn negotiate(
+    local: &[Version],
+    remote_version: Version,
+    remote: &PacketRepr,
+) -> Result<Version> {
+    let highest = local
+        .iter()
+        .map(|version| u8::from(*version))
+        .max()
+        .ok_or(Error::IncompatibleVersion)?;
+    let version = match remote.bitmap() {
+        Some(bitmap) => local
+            .iter()
+            .filter(|version| bitmap.contains(**version))
+            .map(|version| u8::from(*version))
+            .max(),
+        None => {
+            let version = highest.min(u8::from(remote_version));
+            local
+                .iter()
+                .map(|version| u8::from(*version))
+                .find(|supported| *supported == version)
+        }
+    };
+    version.map(Version::from).ok_or(Error::IncompatibleVersion)
+}
+
+/// Return the `HelloFailed(Incompatible)` error message to send in reply to a Hello message with
+/// the given `xid`, when the [negotiation](fn.negotiate.html) failed. Its header carries the
+/// highest supported version, and its length is already set.
+pub fn incompatible_reply<E: Repr>(local: &[Version], xid: u32) -> openflow::PacketRepr<E> {
+    let version = local
+        .iter()
+        .map(|version| u8::from(*version))
+        .max()
+        .map_or(Version::OpenFlow1Dot5, Version::from);
+    let mut repr = openflow::PacketRepr {
+        version,
+        length: 0,
+        kind: openflow::Kind::Error,
+        xid,
+        payload: openflow::PayloadRepr::Error(error::PacketRepr::hello_incompatible(local)),
+    };
+    repr.set_length_auto();
+    repr
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use error::{HelloFailedCode, Header};
+    use hello::{BitmapRepr, ElementData, ElementRepr, Kind};
+    use oxm::ExperimenterOxm;
+
+    use openflow::Version::*;
+
+    #[test]
+    fn test_bitmaps() {
+        let local = [OpenFlow1Dot0, OpenFlow1Dot3, OpenFlow1Dot5];
+        let remote = PacketRepr::from_versions(&[OpenFlow1Dot3, OpenFlow1Dot4]);
+        assert_eq!(negotiate(&local, OpenFlow1Dot4, &remote), Ok(OpenFlow1Dot3));
+
+        // the header versions are ignored
+        let remote = PacketRepr::from_versions(&[OpenFlow1Dot0, OpenFlow1Dot5]);
+        assert_eq!(negotiate(&local, OpenFlow1Dot0, &remote), Ok(OpenFlow1Dot5));
+
+        let remote = PacketRepr::from_versions(&[OpenFlow1Dot4]);
+        assert_eq!(
+            negotiate(&local, OpenFlow1Dot4, &remote),
+            Err(Error::IncompatibleVersion)
+        );
+    }
+
+    #[test]
+    fn test_header_versions() {
+        let local = [OpenFlow1Dot3, OpenFlow1Dot5];
+        let remote = PacketRepr::new(vec![]);
+        assert_eq!(negotiate(&local, OpenFlow1Dot6, &remote), Ok(OpenFlow1Dot5));
+        assert_eq!(negotiate(&local, OpenFlow1Dot3, &remote), Ok(OpenFlow1Dot3));
+        assert_eq!(
+            negotiate(&local, OpenFlow1Dot4, &remote),
+            Err(Error::IncompatibleVersion)
+        );
+        assert_eq!(
+            negotiate(&[], OpenFlow1Dot4, &remote),
+            Err(Error::IncompatibleVersion)
+        );
+
+        // elements that are not bitmaps are ignored
+        let remote = PacketRepr::new(vec![ElementRepr {
+            kind: Kind::_Unknown(0xffff),
+            payload: ElementData::Unknown(vec![0; 4]),
+        }]);
+        assert_eq!(negotiate(&local, OpenFlow1Dot6, &remote), Ok(OpenFlow1Dot5));
+    }
+
+    #[test]
+    fn test_from_versions() {
+        let hello = PacketRepr::from_versions(&[OpenFlow1Dot0, OpenFlow1Dot3]);
+        assert_eq!(hello.bitmap(), Some(BitmapRepr(0x12)));
+        assert!(BitmapRepr(0x12).contains(OpenFlow1Dot3));
+        assert!(!BitmapRepr(0x12).contains(OpenFlow1Dot4));
+        assert!(!BitmapRepr(!0).contains(Version::_Unknown(32)));
+    }
+
+    #[test]
+    fn test_incompatible_reply() {
+        let repr = incompatible_reply::<ExperimenterOxm>(&[OpenFlow1Dot3, OpenFlow1Dot5], 42);
+        let data = b"supported versions: 0x04, 0x06".to_vec();
+        assert_eq!(
+            repr,
+            openflow::PacketRepr {
+                version: OpenFlow1Dot5,
+                length: 8 + 4 + data.len() as u16,
+                kind: openflow::Kind::Error,
+                xid: 42,
+                payload: openflow::PayloadRepr::Error(error::PacketRepr {
+                    header: Header::HelloFailed(HelloFailedCode::Incompatible),
+                    data,
+                }),
+            }
+        );
+
+        let mut bytes = vec![0; repr.buffer_len()];
+        repr.emit(&mut bytes).unwrap();
+        assert_eq!(openflow::PacketRepr::parse(&bytes), Ok(repr));
+    }
+}
@@ -0,0 +1,400 @@
+//! Types to parse and emit instructions.
+//!
+//! Instructions are attached to flow entries, and are executed when a packet matches the entry.
+//! Like [actions](../action/index.html), they all start with the same header:
+//!
+//! ```no_rust
+//! +--------+--------+--------+--------+
+//! |       type      |      length     |
+//! +--------+--------+--------+--------+
+//! |                body               |
+//! |         (variable length)         |
+//! +--------+--------+--------+--------+
+//! ```
+//!
+//! - The type determines how the body is interpreted. See [Kind] for the different types.
+//! - The length includes the header and is always a multiple of 8.
+//!
+//! [Kind]: enum.Kind.html
+use core::{fmt, result};
+use core::str::FromStr;
+
+use {Error, Repr, Result};
+use byteorder::{ByteOrder, NetworkEndian};
+use oxm::ExperimenterOxm;
+use packets::action::Action;
+use packets::list;
+use packets::property::padded_len;
+use text::{self, ParseError};
+
+enum_with_unknown! {
+    /// Type of an instruction.
+    pub doc enum Kind(u16) {
+        /// Setup the next table in the lookup pipeline.
+        GotoTable = 1,
+        /// Setup the metadata field for use later in pipeline.
+        WriteMetadata = 2,
+        /// Write the action(s) onto the datapath action set.
+        WriteActions = 3,
+        /// Applies the action(s) immediately.
+        ApplyActions = 4,
+        /// Clears all actions from the datapath action set.
+        ClearActions = 5,
+        /// Deprecated (since OpenFlow 1.5). Apply meter (rate limiter).
+        Meter = 6,
+        /// Statistics triggers.
+        StatTrigger = 7,
+        /// Experimenter instruction.
+        Experimenter = 0xffff
+    }
+}
+
+/// A wrapper to read and write a buffer representing an instruction.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod field {
+    #![allow(non_snake_case)]
+    use field::*;
+
+    pub const KIND: Field = 0..2;
+    pub const LENGTH: Field = 2..4;
+
+    pub fn BODY(length: usize) -> Field {
+        LENGTH.end..length
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with an instruction structure.
+    pub fn new(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new] and [check_len].
+    ///
+    /// [new]: #method.new
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error::Truncated)` if the buffer is too short, and `Err(Error::Malformed)` if
+    /// the length field is invalid.
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < field::LENGTH.end {
+            return Err(Error::Truncated);
+        }
+        let length = self.length() as usize;
+        if length < field::LENGTH.end || padded_len(length) != length {
+            Err(Error::Malformed)
+        } else if len < length {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `type` field.
+    pub fn kind(&self) -> Kind {
+        let data = self.buffer.as_ref();
+        Kind::from(NetworkEndian::read_u16(&data[field::KIND]))
+    }
+
+    /// Return the `length` field.
+    pub fn length(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[field::LENGTH])
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to the body of the instruction.
+    pub fn body(&self) -> &'a [u8] {
+        let length = self.length() as usize;
+        &self.buffer.as_ref()[field::BODY(length)]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the `type` field.
+    pub fn set_kind(&mut self, value: Kind) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::KIND], value.into())
+    }
+
+    /// Set the `length` field.
+    pub fn set_length(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::LENGTH], value)
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + AsMut<[u8]> + ?Sized> Packet<&'a mut T> {
+    /// Return a mutable pointer to the body of the instruction. The `length` field must be set
+    /// already.
+    pub fn body_mut(&mut self) -> &mut [u8] {
+        let length = self.length() as usize;
+        &mut self.buffer.as_mut()[field::BODY(length)]
+    }
+}
+
+/// A high-level representation of an instruction.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Instruction<E = ExperimenterOxm> {
+    /// Send the packet to the given table.
+    GotoTable(u8),
+    /// Write the bits of `metadata` selected by `mask` into the metadata field.
+    WriteMetadata { metadata: u64, mask: u64 },
+    /// Merge the actions into the action set.
+    WriteActions(Vec<Action<E>>),
+    /// Apply the actions immediately.
+    ApplyActions(Vec<Action<E>>),
+    /// Clear the action set.
+    ClearActions,
+    /// Apply the given meter. Deprecated since OpenFlow 1.5, in favor of the meter action.
+    Meter(u32),
+    /// An experimenter instruction.
+    Experimenter { experimenter: u32, data: Vec<u8> },
+    /// An instruction unknown to this library. The body is kept as is, so that it can be emitted
+    /// back.
+    Unknown { kind: u16, data: Vec<u8> },
+}
+
+/// Length of an instruction made of the header and 4 bytes.
+const SHORT_LEN: usize = 8;
+/// Length of a write metadata instruction.
+const WRITE_METADATA_LEN: usize = 24;
+/// Offset of the actions in the body of write actions and apply actions instructions.
+const ACTIONS_OFFSET: usize = 4;
+
+impl<E> Instruction<E> {
+    /// Return the type of this instruction.
+    pub fn kind(&self) -> Kind {
+        use self::Instruction::*;
+        match *self {
+            GotoTable(_) => Kind::GotoTable,
+            WriteMetadata { .. } => Kind::WriteMetadata,
+            WriteActions(_) => Kind::WriteActions,
+            ApplyActions(_) => Kind::ApplyActions,
+            ClearActions => Kind::ClearActions,
+            Meter(_) => Kind::Meter,
+            Experimenter { .. } => Kind::Experimenter,
+            Unknown { kind, .. } => Kind::from(kind),
+        }
+    }
+}
+
+impl<E: Repr> list::Entry for Instruction<E> {
+    const LENGTH_OFFSET: usize = field::LENGTH.start;
+}
+
+impl<E: Repr> Repr for Instruction<E> {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        use self::Instruction::*;
+        let packet = Packet::new_checked(buffer)?;
+        let body = packet.body();
+        let length = packet.length() as usize;
+        let kind = packet.kind();
+        let valid_len = match kind {
+            Kind::GotoTable | Kind::ClearActions | Kind::Meter => length == SHORT_LEN,
+            Kind::WriteMetadata => length == WRITE_METADATA_LEN,
+            Kind::WriteActions | Kind::ApplyActions | Kind::Experimenter => {
+                body.len() >= ACTIONS_OFFSET
+            }
+            _ => true,
+        };
+        if !valid_len {
+            return Err(Error::Malformed);
+        }
+        Ok(match kind {
+            Kind::GotoTable => GotoTable(body[0]),
+            Kind::WriteMetadata => WriteMetadata {
+                metadata: NetworkEndian::read_u64(&body[4..12]),
+                mask: NetworkEndian::read_u64(&body[12..20]),
+            },
+            Kind::WriteActions => WriteActions(list::parse(&body[ACTIONS_OFFSET..])?),
+            Kind::ApplyActions => ApplyActions(list::parse(&body[ACTIONS_OFFSET..])?),
+            Kind::ClearActions => ClearActions,
+            Kind::Meter => Meter(NetworkEndian::read_u32(&body[0..4])),
+            Kind::Experimenter => Experimenter {
+                experimenter: NetworkEndian::read_u32(&body[0..4]),
+                data: body[4..].to_vec(),
+            },
+            kind => Unknown {
+                kind: kind.into(),
+                data: body.to_vec(),
+            },
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        use self::Instruction::*;
+        match *self {
+            GotoTable(_) | ClearActions | Meter(_) => SHORT_LEN,
+            WriteMetadata { .. } => WRITE_METADATA_LEN,
+            WriteActions(ref actions) | ApplyActions(ref actions) => {
+                field::LENGTH.end + ACTIONS_OFFSET + list::buffer_len(actions)
+            }
+            Experimenter { ref data, .. } => padded_len(field::LENGTH.end + 4 + data.len()),
+            Unknown { ref data, .. } => padded_len(field::LENGTH.end + data.len()),
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        use self::Instruction::*;
+        let length = self.buffer_len();
+        if length > 0xffff {
+            return Err(Error::Malformed);
+        }
+        if let Unknown { kind, .. } = *self {
+            // an unknown instruction would be parsed back as the known instruction of the same
+            // type
+            match Kind::from(kind) {
+                Kind::StatTrigger | Kind::_Unknown(_) => {}
+                _ => return Err(Error::Malformed),
+            }
+        }
+        if buffer.len() < length {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(&mut buffer[..length]);
+        packet.set_kind(self.kind());
+        packet.set_length(length as u16);
+        let body = packet.body_mut();
+        for byte in body.iter_mut() {
+            *byte = 0;
+        }
+        match *self {
+            GotoTable(table_id) => body[0] = table_id,
+            WriteMetadata { metadata, mask } => {
+                NetworkEndian::write_u64(&mut body[4..12], metadata);
+                NetworkEndian::write_u64(&mut body[12..20], mask);
+            }
+            WriteActions(ref actions) | ApplyActions(ref actions) => {
+                list::emit(actions, &mut body[ACTIONS_OFFSET..])?
+            }
+            ClearActions => {}
+            Meter(meter_id) => NetworkEndian::write_u32(&mut body[0..4], meter_id),
+            Experimenter {
+                experimenter,
+                ref data,
+            } => {
+                NetworkEndian::write_u32(&mut body[0..4], experimenter);
+                body[4..4 + data.len()].copy_from_slice(data);
+            }
+            Unknown { ref data, .. } => body[..data.len()].copy_from_slice(data),
+        }
+        Ok(())
+    }
+}
+
+/// Instructions are written with the `ovs-ofctl` syntax: `goto_table:1`,
+/// `write_metadata:0x1/0xff`, `write_actions(output:2)`, `apply_actions(pop_vlan,output:2)`,
+/// `clear_actions` and `meter:1`. Experimenter instructions are written
+/// `experimenter(0x00002320,0x...)` and unknown instructions `unknown(7,0x...)`.
+impl<E: Repr> fmt::Display for Instruction<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Instruction::*;
+        match *self {
+            GotoTable(table_id) => write!(f, "goto_table:{}", table_id),
+            WriteMetadata { metadata, mask } if mask == !0 => {
+                write!(f, "write_metadata:0x{:x}", metadata)
+            }
+            WriteMetadata { metadata, mask } => {
+                write!(f, "write_metadata:0x{:x}/0x{:x}", metadata, mask)
+            }
+            WriteActions(ref actions) => write!(f, "write_actions({})", format_actions(actions)),
+            ApplyActions(ref actions) => write!(f, "apply_actions({})", format_actions(actions)),
+            ClearActions => write!(f, "clear_actions"),
+            Meter(meter_id) => write!(f, "meter:{}", meter_id),
+            Experimenter {
+                experimenter,
+                ref data,
+            } => write!(
+                f,
+                "experimenter(0x{:08x},{})",
+                experimenter,
+                text::format_hex(data)
+            ),
+            Unknown { kind, ref data } => {
+                write!(f, "unknown({},{})", kind, text::format_hex(data))
+            }
+        }
+    }
+}
+
+fn format_actions<E: Repr>(actions: &[Action<E>]) -> String {
+    actions
+        .iter()
+        .map(|action| action.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_actions<E: Repr>(s: &str) -> result::Result<Vec<Action<E>>, ParseError> {
+    text::split(s).into_iter().map(str::parse).collect()
+}
+
+impl<E: Repr> FromStr for Instruction<E> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        use self::Instruction::*;
+        let s = s.trim();
+        if let Some(args) = text::strip_call(s, "write_actions") {
+            return Ok(WriteActions(parse_actions(args)?));
+        }
+        if let Some(args) = text::strip_call(s, "apply_actions") {
+            return Ok(ApplyActions(parse_actions(args)?));
+        }
+        if let Some(args) = text::strip_call(s, "experimenter") {
+            let args = text::split(args);
+            let experimenter = text::parse_u32(args.first().unwrap_or(&""))?;
+            let data = text::parse_hex(args.get(1).unwrap_or(&""))?;
+            return Ok(Experimenter { experimenter, data });
+        }
+        if let Some(args) = text::strip_call(s, "unknown") {
+            let args = text::split(args);
+            let kind = text::parse_u16(args.first().unwrap_or(&""))?;
+            let data = text::parse_hex(args.get(1).unwrap_or(&""))?;
+            return Ok(Unknown { kind, data });
+        }
+
+        let (name, arg) = match s.find(':') {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+        let arg = || arg.ok_or_else(|| ParseError::bad_value(s));
+        Ok(match name {
+            "goto_table" => GotoTable(text::parse_u8(arg()?)?),
+            "write_metadata" => {
+                let arg = arg()?;
+                match arg.find('/') {
+                    Some(i) => WriteMetadata {
+                        metadata: text::parse_u64(&arg[..i])?,
+                        mask: text::parse_u64(&arg[i + 1..])?,
+                    },
+                    None => WriteMetadata {
+                        metadata: text::parse_u64(arg)?,
+                        mask: !0,
+                    },
+                }
+            }
+            "clear_actions" => ClearActions,
+            "meter" => Meter(text::parse_u32(arg()?)?),
+            _ => return Err(ParseError::unknown_name(name)),
+        })
+    }
+}
@@ -0,0 +1,97 @@
+//! Helpers to parse and emit lists of structures that carry their own length, such as actions,
+//! instructions, or multipart reply entries.
+use {Error, Repr, Result};
+use byteorder::{ByteOrder, NetworkEndian};
+
+/// An entry of a list, that carries its own length.
+pub trait Entry: Repr {
+    /// Offset of the 16 bits `length` field of the entry. The length includes the whole entry,
+    /// padding included.
+    const LENGTH_OFFSET: usize;
+}
+
+/// Parse a list of entries that fills the whole buffer. Each entry is parsed from the number of
+/// bytes given by its `length` field, which is also where the next entry starts.
+pub fn parse<R: Entry>(buffer: &[u8]) -> Result<Vec<R>> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while offset < buffer.len() {
+        let bytes = &buffer[offset..];
+        let length_end = R::LENGTH_OFFSET + 2;
+        if bytes.len() < length_end {
+            return Err(Error::Truncated);
+        }
+        let length = NetworkEndian::read_u16(&bytes[R::LENGTH_OFFSET..length_end]) as usize;
+        if length < length_end {
+            return Err(Error::Malformed);
+        }
+        if length > bytes.len() {
+            return Err(Error::Truncated);
+        }
+        entries.push(R::parse(&bytes[..length])?);
+        offset += length;
+    }
+    Ok(entries)
+}
+
+/// Return the length of a list of entries.
+pub fn buffer_len<R: Repr>(entries: &[R]) -> usize {
+    entries.iter().fold(0, |acc, entry| acc + entry.buffer_len())
+}
+
+/// Emit a list of entries one after the other.
+pub fn emit<R: Repr>(entries: &[R], buffer: &mut [u8]) -> Result<()> {
+    if buffer.len() < buffer_len(entries) {
+        return Err(Error::Exhausted);
+    }
+    let mut offset = 0;
+    for entry in entries {
+        let length = entry.buffer_len();
+        entry.emit(&mut buffer[offset..offset + length])?;
+        offset += length;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// An entry with a 16 bits value after its length.
+    #[derive(Debug, PartialEq, Eq)]
+    struct Value(u16);
+
+    impl Repr for Value {
+        fn parse(buffer: &[u8]) -> Result<Self> {
+            if buffer.len() < 4 {
+                return Err(Error::Truncated);
+            }
+            Ok(Value(NetworkEndian::read_u16(&buffer[2..4])))
+        }
+        fn buffer_len(&self) -> usize {
+            4
+        }
+        fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+            NetworkEndian::write_u16(&mut buffer[0..2], 4);
+            NetworkEndian::write_u16(&mut buffer[2..4], self.0);
+            Ok(())
+        }
+    }
+
+    impl Entry for Value {
+        const LENGTH_OFFSET: usize = 0;
+    }
+
+    #[test]
+    fn test_parse() {
+        let bytes = [
+            // the first entry is longer than the fields it is parsed into
+            0x00, 0x08, 0x00, 0x01, 0xff, 0xff, 0xff, 0xff,
+            0x00, 0x04, 0x00, 0x02,
+        ];
+        assert_eq!(parse::<Value>(&bytes), Ok(vec![Value(1), Value(2)]));
+        assert_eq!(parse::<Value>(&bytes[..10]), Err(Error::Truncated));
+        assert_eq!(parse::<Value>(&bytes[..9]), Err(Error::Truncated));
+        assert_eq!(parse::<Value>(&[0x00, 0x01, 0x00, 0x00]), Err(Error::Malformed));
+    }
+}
@@ -0,0 +1,466 @@
+//! Types to parse and emit meter modification messages (`OFPT_METER_MOD`).
+//!
+//! ```no_rust
+//! +--------+--------+--------+--------+
+//! |     command     |      flags      |
+//! +--------+--------+--------+--------+
+//! |              meter_id             |
+//! +--------+--------+--------+--------+
+//! |               bands               |
+//! |         (variable length)         |
+//! +--------+--------+--------+--------+
+//! ```
+use {Error, Repr, Result};
+use byteorder::{ByteOrder, NetworkEndian};
+use packets::list;
+
+/// Last usable meter.
+pub const METER_MAX: u32 = 0xffff_0000;
+/// Meter for slow datapath.
+pub const METER_SLOWPATH: u32 = 0xffff_fffd;
+/// Meter for controller connection.
+pub const METER_CONTROLLER: u32 = 0xffff_fffe;
+/// Represents all meters for stat requests commands.
+pub const METER_ALL: u32 = 0xffff_ffff;
+
+enum_with_unknown! {
+    /// Meter modification command.
+    pub doc enum Command(u16) {
+        /// New meter.
+        Add = 0,
+        /// Modify specified meter.
+        Modify = 1,
+        /// Delete specified meter.
+        Delete = 2
+    }
+}
+
+/// Rate value in kb/s (kilo-bit per second).
+const FLAG_KBPS: u16 = 1;
+/// Rate value in packet/sec.
+const FLAG_PKTPS: u16 = 1 << 1;
+/// Do burst size.
+const FLAG_BURST: u16 = 1 << 2;
+/// Collect statistics.
+const FLAG_STATS: u16 = 1 << 3;
+
+/// Flags of a meter.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Flags(u16);
+
+impl Flags {
+    pub fn new(bitmap: u16) -> Self {
+        Flags(bitmap)
+    }
+
+    pub fn kbps(&self) -> bool {
+        (self.0 & FLAG_KBPS) == FLAG_KBPS
+    }
+
+    pub fn set_kbps(&mut self) {
+        self.0 |= FLAG_KBPS
+    }
+
+    pub fn pktps(&self) -> bool {
+        (self.0 & FLAG_PKTPS) == FLAG_PKTPS
+    }
+
+    pub fn set_pktps(&mut self) {
+        self.0 |= FLAG_PKTPS
+    }
+
+    pub fn burst(&self) -> bool {
+        (self.0 & FLAG_BURST) == FLAG_BURST
+    }
+
+    pub fn set_burst(&mut self) {
+        self.0 |= FLAG_BURST
+    }
+
+    pub fn stats(&self) -> bool {
+        (self.0 & FLAG_STATS) == FLAG_STATS
+    }
+
+    pub fn set_stats(&mut self) {
+        self.0 |= FLAG_STATS
+    }
+}
+
+enum_with_unknown! {
+    /// Type of a meter band.
+    pub doc enum BandKind(u16) {
+        /// Drop packet.
+        Drop = 1,
+        /// Remark DSCP in the IP header.
+        DscpRemark = 2,
+        /// Experimenter meter band.
+        Experimenter = 0xffff
+    }
+}
+
+/// A buffer representing a meter band.
+///
+/// ```no_rust
+/// +--------+--------+--------+--------+
+/// |       type      |      length     |
+/// +--------+--------+--------+--------+
+/// |                rate               |
+/// +--------+--------+--------+--------+
+/// |             burst_size            |
+/// +--------+--------+--------+--------+
+/// |             band data             |
+/// |         (variable length)         |
+/// +--------+--------+--------+--------+
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Band<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod band_field {
+    #![allow(non_snake_case)]
+    use field::*;
+
+    pub const KIND: Field = 0..2;
+    pub const LENGTH: Field = 2..4;
+    pub const RATE: Field = 4..8;
+    pub const BURST_SIZE: Field = 8..12;
+
+    pub fn DATA(length: usize) -> Field {
+        BURST_SIZE.end..length
+    }
+}
+
+impl<T: AsRef<[u8]>> Band<T> {
+    pub fn new(buffer: T) -> Band<T> {
+        Band { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Band<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < band_field::LENGTH.end || len < self.length() as usize {
+            Err(Error::Truncated)
+        } else if (self.length() as usize) < band_field::BURST_SIZE.end {
+            Err(Error::Malformed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `type` field.
+    pub fn kind(&self) -> BandKind {
+        let data = self.buffer.as_ref();
+        BandKind::from(NetworkEndian::read_u16(&data[band_field::KIND]))
+    }
+
+    /// Return the `len` field.
+    pub fn length(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[band_field::LENGTH])
+    }
+
+    /// Return the `rate` field.
+    pub fn rate(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[band_field::RATE])
+    }
+
+    /// Return the `burst_size` field.
+    pub fn burst_size(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[band_field::BURST_SIZE])
+    }
+
+    /// Return the type-specific data of the band.
+    pub fn data(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[band_field::DATA(self.length() as usize)]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Band<T> {
+    /// Set the `type` field.
+    pub fn set_kind(&mut self, value: BandKind) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[band_field::KIND], value.into())
+    }
+
+    /// Set the `len` field.
+    pub fn set_length(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[band_field::LENGTH], value)
+    }
+
+    /// Set the `rate` field.
+    pub fn set_rate(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[band_field::RATE], value)
+    }
+
+    /// Set the `burst_size` field.
+    pub fn set_burst_size(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[band_field::BURST_SIZE], value)
+    }
+
+    /// Return a mutable pointer to the type-specific data of the band. Note that the length field
+    /// must be set already.
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        let length = self.length() as usize;
+        let data = self.buffer.as_mut();
+        &mut data[band_field::DATA(length)]
+    }
+}
+
+/// The type-specific part of a meter band.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BandKindRepr {
+    /// Drop the packets that exceed the band rate.
+    Drop,
+    /// Increase the drop precedence of the DSCP field of the packets that exceed the band rate,
+    /// by `prec_level`.
+    DscpRemark { prec_level: u8 },
+    /// Experimenter defined band.
+    Experimenter { experimenter: u32, data: Vec<u8> },
+    /// A band this library does not know about.
+    Unknown { kind: u16, data: Vec<u8> },
+}
+
+/// A high-level representation of a meter band.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BandRepr {
+    /// Rate for this band.
+    pub rate: u32,
+    /// Size of bursts.
+    pub burst_size: u32,
+    pub band: BandKindRepr,
+}
+
+/// Length of the drop and DSCP remark bands.
+const BAND_LEN: usize = 16;
+
+impl list::Entry for BandRepr {
+    const LENGTH_OFFSET: usize = band_field::LENGTH.start;
+}
+
+impl Repr for BandRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Band::new_checked(buffer)?;
+        let data = packet.data();
+        let band = match packet.kind() {
+            BandKind::Drop | BandKind::DscpRemark if packet.length() as usize != BAND_LEN => {
+                return Err(Error::Malformed)
+            }
+            BandKind::Drop => BandKindRepr::Drop,
+            BandKind::DscpRemark => BandKindRepr::DscpRemark {
+                prec_level: data[0],
+            },
+            BandKind::Experimenter => {
+                if data.len() < 4 {
+                    return Err(Error::Malformed);
+                }
+                BandKindRepr::Experimenter {
+                    experimenter: NetworkEndian::read_u32(&data[0..4]),
+                    data: data[4..].to_vec(),
+                }
+            }
+            BandKind::_Unknown(kind) => BandKindRepr::Unknown {
+                kind,
+                data: data.to_vec(),
+            },
+        };
+        Ok(BandRepr {
+            rate: packet.rate(),
+            burst_size: packet.burst_size(),
+            band,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        match self.band {
+            BandKindRepr::Drop | BandKindRepr::DscpRemark { .. } => BAND_LEN,
+            BandKindRepr::Experimenter { ref data, .. } => {
+                band_field::BURST_SIZE.end + 4 + data.len()
+            }
+            BandKindRepr::Unknown { ref data, .. } => band_field::BURST_SIZE.end + data.len(),
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        let length = self.buffer_len();
+        let kind = match self.band {
+            BandKindRepr::Drop => BandKind::Drop,
+            BandKindRepr::DscpRemark { .. } => BandKind::DscpRemark,
+            BandKindRepr::Experimenter { .. } => BandKind::Experimenter,
+            BandKindRepr::Unknown { kind, .. } => match BandKind::from(kind) {
+                kind @ BandKind::_Unknown(_) => kind,
+                // it would be parsed back as the known band of the same type
+                _ => return Err(Error::Malformed),
+            },
+        };
+        if length > 0xffff {
+            return Err(Error::Malformed);
+        }
+        if buffer.len() < length {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Band::new(&mut buffer[..length]);
+        packet.set_kind(kind);
+        packet.set_length(length as u16);
+        packet.set_rate(self.rate);
+        packet.set_burst_size(self.burst_size);
+        let data = packet.data_mut();
+        for byte in data.iter_mut() {
+            *byte = 0;
+        }
+        match self.band {
+            BandKindRepr::Drop => {}
+            BandKindRepr::DscpRemark { prec_level } => data[0] = prec_level,
+            BandKindRepr::Experimenter {
+                experimenter,
+                data: ref bytes,
+            } => {
+                NetworkEndian::write_u32(&mut data[0..4], experimenter);
+                data[4..].copy_from_slice(bytes);
+            }
+            BandKindRepr::Unknown { data: ref bytes, .. } => data.copy_from_slice(bytes),
+        }
+        Ok(())
+    }
+}
+
+/// A buffer representing a meter modification message.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod field {
+    use field::*;
+
+    pub const COMMAND: Field = 0..2;
+    pub const FLAGS: Field = 2..4;
+    pub const METER_ID: Field = 4..8;
+    pub const BANDS: Rest = 8..;
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub fn new(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    pub fn check_len(&self) -> Result<()> {
+        if self.buffer.as_ref().len() < field::METER_ID.end {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `command` field.
+    pub fn command(&self) -> Command {
+        let data = self.buffer.as_ref();
+        Command::from(NetworkEndian::read_u16(&data[field::COMMAND]))
+    }
+
+    /// Return the `flags` field.
+    pub fn flags(&self) -> Flags {
+        let data = self.buffer.as_ref();
+        Flags(NetworkEndian::read_u16(&data[field::FLAGS]))
+    }
+
+    /// Return the `meter_id` field.
+    pub fn meter_id(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[field::METER_ID])
+    }
+
+    /// Return the `bands` field.
+    pub fn bands(&self) -> &[u8] {
+        &self.buffer.as_ref()[field::BANDS]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the `command` field.
+    pub fn set_command(&mut self, value: Command) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::COMMAND], value.into())
+    }
+
+    /// Set the `flags` field.
+    pub fn set_flags(&mut self, value: Flags) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::FLAGS], value.0)
+    }
+
+    /// Set the `meter_id` field.
+    pub fn set_meter_id(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::METER_ID], value)
+    }
+
+    /// Return a mutable pointer to the `bands` field.
+    pub fn bands_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer.as_mut()[field::BANDS]
+    }
+}
+
+/// A high-level representation of a meter modification message.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PacketRepr {
+    pub command: Command,
+    pub flags: Flags,
+    /// Meter instance. See the `METER_XXX` constants for the special values.
+    pub meter_id: u32,
+    pub bands: Vec<BandRepr>,
+}
+
+impl Repr for PacketRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        Ok(PacketRepr {
+            command: packet.command(),
+            flags: packet.flags(),
+            meter_id: packet.meter_id(),
+            bands: list::parse(packet.bands())?,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        field::METER_ID.end + list::buffer_len(&self.bands)
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(buffer);
+        packet.set_command(self.command);
+        packet.set_flags(self.flags);
+        packet.set_meter_id(self.meter_id);
+        list::emit(&self.bands, packet.bands_mut())
+    }
+}
@@ -5,3 +5,15 @@ pub mod features_reply;
 pub mod set_config;
 pub mod get_config_reply;
 pub mod packet_in;
+pub mod multipart;
+pub mod controller_status;
+pub mod group_mod;
+pub mod meter_mod;
+pub mod property;
+pub mod action;
+pub mod instruction;
+pub mod framer;
+pub mod stream;
+#[cfg(feature = "tokio")]
+pub mod codec;
+mod list;
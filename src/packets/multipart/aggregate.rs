@@ -0,0 +1,205 @@
+//! Types to parse and emit the bodies of aggregate flow statistics (`OFPMP_AGGREGATE_STATS`)
+//! multipart messages.
+//!
+//! The reply body is a list of OXS fields (see [`oxs::Stats`](../../oxs/struct.Stats.html)), so
+//! only the request has a dedicated representation.
+use {Error, Repr, Result};
+use byteorder::{ByteOrder, NetworkEndian};
+use oxm::{ExperimenterOxm, FlowMatch};
+use port::PortNumber;
+
+/// A buffer representing the body of an aggregate flow statistics request.
+///
+/// ```no_rust
+/// +--------+--------+--------+--------+
+/// |table_id|         padding          |
+/// +--------+--------+--------+--------+
+/// |              out_port             |
+/// +--------+--------+--------+--------+
+/// |             out_group             |
+/// +--------+--------+--------+--------+
+/// |              padding              |
+/// +--------+--------+--------+--------+
+/// |               cookie              |
+/// |                                   |
+/// +--------+--------+--------+--------+
+/// |            cookie_mask            |
+/// |                                   |
+/// +--------+--------+--------+--------+
+/// |         Flow match fields         |
+/// | (variable length, 8 bytes aligned)|
+/// +--------+--------+--------+--------+
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Request<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod field {
+    use field::*;
+
+    pub const TABLE_ID: usize = 0;
+    pub const PADDING_1: Field = 1..4;
+    pub const OUT_PORT: Field = 4..8;
+    pub const OUT_GROUP: Field = 8..12;
+    pub const PADDING_2: Field = 12..16;
+    pub const COOKIE: Field = 16..24;
+    pub const COOKIE_MASK: Field = 24..32;
+    pub const FLOW_MATCH: Rest = 32..;
+}
+
+impl<T: AsRef<[u8]>> Request<T> {
+    pub fn new(buffer: T) -> Request<T> {
+        Request { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Request<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    pub fn check_len(&self) -> Result<()> {
+        if self.buffer.as_ref().len() < field::COOKIE_MASK.end {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `table_id` field.
+    pub fn table_id(&self) -> u8 {
+        self.buffer.as_ref()[field::TABLE_ID]
+    }
+
+    /// Return the `out_port` field.
+    pub fn out_port(&self) -> PortNumber {
+        let data = self.buffer.as_ref();
+        PortNumber::from(NetworkEndian::read_u32(&data[field::OUT_PORT]))
+    }
+
+    /// Return the `out_group` field.
+    pub fn out_group(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[field::OUT_GROUP])
+    }
+
+    /// Return the `cookie` field.
+    pub fn cookie(&self) -> u64 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u64(&data[field::COOKIE])
+    }
+
+    /// Return the `cookie_mask` field.
+    pub fn cookie_mask(&self) -> u64 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u64(&data[field::COOKIE_MASK])
+    }
+
+    /// Return the `match` field.
+    pub fn flow_match(&self) -> &[u8] {
+        &self.buffer.as_ref()[field::FLOW_MATCH]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Request<T> {
+    /// Set the `table_id` field.
+    pub fn set_table_id(&mut self, value: u8) {
+        self.buffer.as_mut()[field::TABLE_ID] = value;
+    }
+
+    /// Set the padding fields.
+    pub fn set_padding(&mut self) {
+        let data = self.buffer.as_mut();
+        for byte in &mut data[field::PADDING_1] {
+            *byte = 0;
+        }
+        NetworkEndian::write_u32(&mut data[field::PADDING_2], 0);
+    }
+
+    /// Set the `out_port` field.
+    pub fn set_out_port(&mut self, value: PortNumber) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::OUT_PORT], value.into())
+    }
+
+    /// Set the `out_group` field.
+    pub fn set_out_group(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::OUT_GROUP], value)
+    }
+
+    /// Set the `cookie` field.
+    pub fn set_cookie(&mut self, value: u64) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u64(&mut data[field::COOKIE], value)
+    }
+
+    /// Set the `cookie_mask` field.
+    pub fn set_cookie_mask(&mut self, value: u64) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u64(&mut data[field::COOKIE_MASK], value)
+    }
+
+    /// Return a mutable pointer to the `match` field.
+    pub fn flow_match_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer.as_mut()[field::FLOW_MATCH]
+    }
+}
+
+/// A high-level representation of the body of an aggregate flow statistics request.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RequestRepr<E = ExperimenterOxm> {
+    /// ID of table to read, or [`TABLE_ALL`](../constant.TABLE_ALL.html) for all tables.
+    pub table_id: u8,
+    /// Require matching entries to include this as an output port. `PortNumber::Any` indicates no
+    /// restriction.
+    pub out_port: PortNumber,
+    /// Require matching entries to include this as an output group.
+    /// [`GROUP_ANY`](../constant.GROUP_ANY.html) indicates no restriction.
+    pub out_group: u32,
+    /// Require matching entries to contain this cookie value
+    pub cookie: u64,
+    /// Mask used to restrict the cookie bits that must match. A value of 0 indicates no
+    /// restriction.
+    pub cookie_mask: u64,
+    /// Fields to match.
+    pub flow_match: FlowMatch<E>,
+}
+
+impl<E: Repr> Repr for RequestRepr<E> {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Request::new_checked(buffer)?;
+        Ok(RequestRepr {
+            table_id: packet.table_id(),
+            out_port: packet.out_port(),
+            out_group: packet.out_group(),
+            cookie: packet.cookie(),
+            cookie_mask: packet.cookie_mask(),
+            flow_match: FlowMatch::parse(packet.flow_match())?,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        field::COOKIE_MASK.end + self.flow_match.buffer_len()
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Request::new(buffer);
+        packet.set_table_id(self.table_id);
+        packet.set_padding();
+        packet.set_out_port(self.out_port);
+        packet.set_out_group(self.out_group);
+        packet.set_cookie(self.cookie);
+        packet.set_cookie_mask(self.cookie_mask);
+        self.flow_match.emit(packet.flow_match_mut())
+    }
+}
@@ -0,0 +1,423 @@
+//! Types to parse and emit the bodies of bundle features (`OFPMP_BUNDLE_FEATURES`) multipart
+//! messages.
+//!
+//! A controller uses this request to learn which bundle capabilities the switch supports, and how
+//! precisely it can schedule bundles, before actually using bundles.
+use {Error, Repr, Result};
+use byteorder::{ByteOrder, NetworkEndian};
+use property::{self, ExperimenterRepr, UnknownRepr};
+
+const PROPERTY_TIME_CAPABILITY: u16 = 1;
+
+/// Request the timestamp of the switch in the reply.
+const FLAG_TIMESTAMP: u32 = 1;
+/// Set the scheduling parameters of the switch from the request.
+const FLAG_TIME_SET_SCHED: u32 = 1 << 1;
+
+/// Flags of a bundle features request.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct RequestFlags(u32);
+
+impl RequestFlags {
+    pub fn new(bitmap: u32) -> Self {
+        RequestFlags(bitmap)
+    }
+
+    pub fn timestamp(&self) -> bool {
+        (self.0 & FLAG_TIMESTAMP) == FLAG_TIMESTAMP
+    }
+
+    pub fn set_timestamp(&mut self) {
+        self.0 |= FLAG_TIMESTAMP
+    }
+
+    pub fn time_set_sched(&self) -> bool {
+        (self.0 & FLAG_TIME_SET_SCHED) == FLAG_TIME_SET_SCHED
+    }
+
+    pub fn set_time_set_sched(&mut self) {
+        self.0 |= FLAG_TIME_SET_SCHED
+    }
+}
+
+/// Execute atomically.
+const CAP_ATOMIC: u16 = 1;
+/// Execute in the specified order.
+const CAP_ORDERED: u16 = 1 << 1;
+/// Execute at a specified time.
+const CAP_TIME: u16 = 1 << 2;
+
+/// Bundle capabilities of a switch.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Capabilities(u16);
+
+impl Capabilities {
+    pub fn new(bitmap: u16) -> Self {
+        Capabilities(bitmap)
+    }
+
+    pub fn atomic(&self) -> bool {
+        (self.0 & CAP_ATOMIC) == CAP_ATOMIC
+    }
+
+    pub fn set_atomic(&mut self) {
+        self.0 |= CAP_ATOMIC
+    }
+
+    pub fn ordered(&self) -> bool {
+        (self.0 & CAP_ORDERED) == CAP_ORDERED
+    }
+
+    pub fn set_ordered(&mut self) {
+        self.0 |= CAP_ORDERED
+    }
+
+    pub fn time(&self) -> bool {
+        (self.0 & CAP_TIME) == CAP_TIME
+    }
+
+    pub fn set_time(&mut self) {
+        self.0 |= CAP_TIME
+    }
+}
+
+/// A time, as represented in bundle messages.
+///
+/// ```no_rust
+/// +--------+--------+--------+--------+
+/// |              seconds              |
+/// |                                   |
+/// +--------+--------+--------+--------+
+/// |            nanoseconds            |
+/// +--------+--------+--------+--------+
+/// |              padding              |
+/// +--------+--------+--------+--------+
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Time {
+    pub seconds: u64,
+    pub nanoseconds: u32,
+}
+
+/// Length of a time structure.
+const TIME_LEN: usize = 16;
+
+impl Time {
+    fn parse(buffer: &[u8]) -> Self {
+        Time {
+            seconds: NetworkEndian::read_u64(&buffer[0..8]),
+            nanoseconds: NetworkEndian::read_u32(&buffer[8..12]),
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) {
+        NetworkEndian::write_u64(&mut buffer[0..8], self.seconds);
+        NetworkEndian::write_u32(&mut buffer[8..12], self.nanoseconds);
+        NetworkEndian::write_u32(&mut buffer[12..16], 0);
+    }
+}
+
+/// The time capability property, that describes the scheduling capabilities of the switch.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct TimeCapabilityRepr {
+    /// The scheduling accuracy, _i.e._ how accurately the switch can perform the scheduled
+    /// commit of a bundle.
+    pub sched_accuracy: Time,
+    /// Maximum time in the future a bundle can be scheduled.
+    pub sched_max_future: Time,
+    /// Maximum time in the past a bundle can be scheduled.
+    pub sched_max_past: Time,
+    /// The timestamp of the switch, when the reply was generated.
+    pub timestamp: Time,
+}
+
+/// A property of a bundle features request or reply.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PropertyRepr {
+    /// Scheduling capabilities of the switch.
+    TimeCapability(TimeCapabilityRepr),
+    /// Experimenter defined property.
+    Experimenter(ExperimenterRepr),
+    /// A property this library does not know about.
+    Unknown(UnknownRepr),
+}
+
+/// Length of the time capability property: header (4 bytes) + padding (4 bytes) + 4 times.
+const TIME_CAPABILITY_LEN: usize = 8 + 4 * TIME_LEN;
+
+impl Repr for PropertyRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = property::Packet::new_checked(buffer)?;
+        Ok(match packet.kind() {
+            PROPERTY_TIME_CAPABILITY => {
+                if packet.length() as usize != TIME_CAPABILITY_LEN {
+                    return Err(Error::Malformed);
+                }
+                let times = &packet.payload()[4..];
+                PropertyRepr::TimeCapability(TimeCapabilityRepr {
+                    sched_accuracy: Time::parse(&times[0..TIME_LEN]),
+                    sched_max_future: Time::parse(&times[TIME_LEN..2 * TIME_LEN]),
+                    sched_max_past: Time::parse(&times[2 * TIME_LEN..3 * TIME_LEN]),
+                    timestamp: Time::parse(&times[3 * TIME_LEN..4 * TIME_LEN]),
+                })
+            }
+            property::EXPERIMENTER => PropertyRepr::Experimenter(ExperimenterRepr::parse(buffer)?),
+            _ => PropertyRepr::Unknown(UnknownRepr::parse(buffer)?),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        match *self {
+            PropertyRepr::TimeCapability(_) => TIME_CAPABILITY_LEN,
+            PropertyRepr::Experimenter(ref repr) => repr.buffer_len(),
+            PropertyRepr::Unknown(ref repr) => repr.buffer_len(),
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        let repr = match *self {
+            PropertyRepr::TimeCapability(ref repr) => repr,
+            PropertyRepr::Experimenter(ref repr) => return repr.emit(buffer),
+            PropertyRepr::Unknown(ref repr) => return repr.emit(buffer),
+        };
+        if buffer.len() < TIME_CAPABILITY_LEN {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = property::Packet::new(&mut buffer[..TIME_CAPABILITY_LEN]);
+        packet.set_kind(PROPERTY_TIME_CAPABILITY);
+        packet.set_length(TIME_CAPABILITY_LEN as u16);
+        let payload = packet.payload_mut();
+        NetworkEndian::write_u32(&mut payload[0..4], 0);
+        let times = &mut payload[4..];
+        repr.sched_accuracy.emit(&mut times[0..TIME_LEN]);
+        repr.sched_max_future.emit(&mut times[TIME_LEN..2 * TIME_LEN]);
+        repr.sched_max_past.emit(&mut times[2 * TIME_LEN..3 * TIME_LEN]);
+        repr.timestamp.emit(&mut times[3 * TIME_LEN..4 * TIME_LEN]);
+        Ok(())
+    }
+}
+
+/// A buffer representing the body of a bundle features request.
+///
+/// ```no_rust
+/// +--------+--------+--------+--------+
+/// |       feature_request_flags       |
+/// +--------+--------+--------+--------+
+/// |              padding              |
+/// +--------+--------+--------+--------+
+/// |             properties            |
+/// |         (variable length)         |
+/// +--------+--------+--------+--------+
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Request<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod request_field {
+    use field::*;
+
+    pub const FLAGS: Field = 0..4;
+    pub const PADDING: Field = 4..8;
+    pub const PROPERTIES: Rest = 8..;
+}
+
+impl<T: AsRef<[u8]>> Request<T> {
+    pub fn new(buffer: T) -> Request<T> {
+        Request { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Request<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    pub fn check_len(&self) -> Result<()> {
+        if self.buffer.as_ref().len() < request_field::PADDING.end {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `feature_request_flags` field.
+    pub fn flags(&self) -> RequestFlags {
+        let data = self.buffer.as_ref();
+        RequestFlags(NetworkEndian::read_u32(&data[request_field::FLAGS]))
+    }
+
+    /// Return the `properties` field.
+    pub fn properties(&self) -> &[u8] {
+        &self.buffer.as_ref()[request_field::PROPERTIES]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Request<T> {
+    /// Set the `feature_request_flags` field.
+    pub fn set_flags(&mut self, value: RequestFlags) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[request_field::FLAGS], value.0)
+    }
+
+    /// Set the `padding` field.
+    pub fn set_padding(&mut self) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[request_field::PADDING], 0)
+    }
+
+    /// Return a mutable pointer to the `properties` field.
+    pub fn properties_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer.as_mut()[request_field::PROPERTIES]
+    }
+}
+
+/// A high-level representation of the body of a bundle features request.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct RequestRepr {
+    pub flags: RequestFlags,
+    /// The time capability property is expected when the `time_set_sched` flag is set.
+    pub properties: Vec<PropertyRepr>,
+}
+
+impl Repr for RequestRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Request::new_checked(buffer)?;
+        Ok(RequestRepr {
+            flags: packet.flags(),
+            properties: property::parse_list(packet.properties())?,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        request_field::PADDING.end + property::list_len(&self.properties)
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Request::new(buffer);
+        packet.set_flags(self.flags);
+        packet.set_padding();
+        property::emit_list(&self.properties, packet.properties_mut())
+    }
+}
+
+/// A buffer representing the body of a bundle features reply.
+///
+/// ```no_rust
+/// +--------+--------+--------+--------+
+/// |   capabilities  |                 |
+/// +--------+--------+                 +
+/// |              padding              |
+/// +--------+--------+--------+--------+
+/// |             properties            |
+/// |         (variable length)         |
+/// +--------+--------+--------+--------+
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Reply<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod reply_field {
+    use field::*;
+
+    pub const CAPABILITIES: Field = 0..2;
+    pub const PADDING: Field = 2..8;
+    pub const PROPERTIES: Rest = 8..;
+}
+
+impl<T: AsRef<[u8]>> Reply<T> {
+    pub fn new(buffer: T) -> Reply<T> {
+        Reply { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Reply<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    pub fn check_len(&self) -> Result<()> {
+        if self.buffer.as_ref().len() < reply_field::PADDING.end {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `capabilities` field.
+    pub fn capabilities(&self) -> Capabilities {
+        let data = self.buffer.as_ref();
+        Capabilities(NetworkEndian::read_u16(&data[reply_field::CAPABILITIES]))
+    }
+
+    /// Return the `properties` field.
+    pub fn properties(&self) -> &[u8] {
+        &self.buffer.as_ref()[reply_field::PROPERTIES]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Reply<T> {
+    /// Set the `capabilities` field.
+    pub fn set_capabilities(&mut self, value: Capabilities) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[reply_field::CAPABILITIES], value.0)
+    }
+
+    /// Set the `padding` field.
+    pub fn set_padding(&mut self) {
+        let data = self.buffer.as_mut();
+        for byte in &mut data[reply_field::PADDING] {
+            *byte = 0;
+        }
+    }
+
+    /// Return a mutable pointer to the `properties` field.
+    pub fn properties_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer.as_mut()[reply_field::PROPERTIES]
+    }
+}
+
+/// A high-level representation of the body of a bundle features reply.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ReplyRepr {
+    pub capabilities: Capabilities,
+    pub properties: Vec<PropertyRepr>,
+}
+
+impl Repr for ReplyRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Reply::new_checked(buffer)?;
+        Ok(ReplyRepr {
+            capabilities: packet.capabilities(),
+            properties: property::parse_list(packet.properties())?,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        reply_field::PADDING.end + property::list_len(&self.properties)
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Reply::new(buffer);
+        packet.set_capabilities(self.capabilities);
+        packet.set_padding();
+        property::emit_list(&self.properties, packet.properties_mut())
+    }
+}
@@ -0,0 +1,194 @@
+//! Types to parse and emit the body of switch description (`OFPMP_DESC`) multipart replies. The
+//! corresponding request has no body.
+//!
+//! ```no_rust
+//! +--------+--------+--------+--------+
+//! |         manufacturer_desc         |
+//! |            (256 bytes)            |
+//! +--------+--------+--------+--------+
+//! |           hardware_desc           |
+//! |            (256 bytes)            |
+//! +--------+--------+--------+--------+
+//! |           software_desc           |
+//! |            (256 bytes)            |
+//! +--------+--------+--------+--------+
+//! |           serial_number           |
+//! |             (32 bytes)            |
+//! +--------+--------+--------+--------+
+//! |           datapath_desc           |
+//! |            (256 bytes)            |
+//! +--------+--------+--------+--------+
+//! ```
+//!
+//! Each field is a NUL terminated ASCII string, padded with NUL bytes.
+use {Error, Repr, Result};
+
+/// Length of the description strings, including the NUL terminator.
+pub const DESC_STR_LEN: usize = 256;
+/// Length of the serial number string, including the NUL terminator.
+pub const SERIAL_NUM_LEN: usize = 32;
+
+mod field {
+    use field::*;
+
+    pub const MANUFACTURER_DESC: Field = 0..256;
+    pub const HARDWARE_DESC: Field = 256..512;
+    pub const SOFTWARE_DESC: Field = 512..768;
+    pub const SERIAL_NUMBER: Field = 768..800;
+    pub const DATAPATH_DESC: Field = 800..1056;
+}
+
+/// Read a NUL padded string, stopping at the first NUL byte.
+fn read_str(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// Write a string and pad it with NUL bytes. The string must leave room for the NUL terminator.
+fn write_str(bytes: &mut [u8], value: &str) -> Result<()> {
+    let value = value.as_bytes();
+    if value.len() >= bytes.len() {
+        return Err(Error::Malformed);
+    }
+    bytes[..value.len()].copy_from_slice(value);
+    for byte in &mut bytes[value.len()..] {
+        *byte = 0;
+    }
+    Ok(())
+}
+
+/// A buffer representing the body of a switch description reply.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub fn new(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < field::DATAPATH_DESC.end {
+            Err(Error::Truncated)
+        } else if len > field::DATAPATH_DESC.end {
+            Err(Error::Malformed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `mfr_desc` field, without the trailing NUL bytes.
+    pub fn manufacturer_desc(&self) -> String {
+        read_str(&self.buffer.as_ref()[field::MANUFACTURER_DESC])
+    }
+
+    /// Return the `hw_desc` field, without the trailing NUL bytes.
+    pub fn hardware_desc(&self) -> String {
+        read_str(&self.buffer.as_ref()[field::HARDWARE_DESC])
+    }
+
+    /// Return the `sw_desc` field, without the trailing NUL bytes.
+    pub fn software_desc(&self) -> String {
+        read_str(&self.buffer.as_ref()[field::SOFTWARE_DESC])
+    }
+
+    /// Return the `serial_num` field, without the trailing NUL bytes.
+    pub fn serial_number(&self) -> String {
+        read_str(&self.buffer.as_ref()[field::SERIAL_NUMBER])
+    }
+
+    /// Return the `dp_desc` field, without the trailing NUL bytes.
+    pub fn datapath_desc(&self) -> String {
+        read_str(&self.buffer.as_ref()[field::DATAPATH_DESC])
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the `mfr_desc` field. Return `Err(Error::Malformed)` if the value is longer than 255
+    /// bytes.
+    pub fn set_manufacturer_desc(&mut self, value: &str) -> Result<()> {
+        write_str(&mut self.buffer.as_mut()[field::MANUFACTURER_DESC], value)
+    }
+
+    /// Set the `hw_desc` field. Return `Err(Error::Malformed)` if the value is longer than 255
+    /// bytes.
+    pub fn set_hardware_desc(&mut self, value: &str) -> Result<()> {
+        write_str(&mut self.buffer.as_mut()[field::HARDWARE_DESC], value)
+    }
+
+    /// Set the `sw_desc` field. Return `Err(Error::Malformed)` if the value is longer than 255
+    /// bytes.
+    pub fn set_software_desc(&mut self, value: &str) -> Result<()> {
+        write_str(&mut self.buffer.as_mut()[field::SOFTWARE_DESC], value)
+    }
+
+    /// Set the `serial_num` field. Return `Err(Error::Malformed)` if the value is longer than 31
+    /// bytes.
+    pub fn set_serial_number(&mut self, value: &str) -> Result<()> {
+        write_str(&mut self.buffer.as_mut()[field::SERIAL_NUMBER], value)
+    }
+
+    /// Set the `dp_desc` field. Return `Err(Error::Malformed)` if the value is longer than 255
+    /// bytes.
+    pub fn set_datapath_desc(&mut self, value: &str) -> Result<()> {
+        write_str(&mut self.buffer.as_mut()[field::DATAPATH_DESC], value)
+    }
+}
+
+/// A high-level representation of the body of a switch description reply.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ReplyRepr {
+    /// Manufacturer description.
+    pub manufacturer_desc: String,
+    /// Hardware description.
+    pub hardware_desc: String,
+    /// Software description.
+    pub software_desc: String,
+    /// Serial number.
+    pub serial_number: String,
+    /// Human readable description of datapath.
+    pub datapath_desc: String,
+}
+
+impl Repr for ReplyRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        Ok(ReplyRepr {
+            manufacturer_desc: packet.manufacturer_desc(),
+            hardware_desc: packet.hardware_desc(),
+            software_desc: packet.software_desc(),
+            serial_number: packet.serial_number(),
+            datapath_desc: packet.datapath_desc(),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        field::DATAPATH_DESC.end
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(buffer);
+        packet.set_manufacturer_desc(&self.manufacturer_desc)?;
+        packet.set_hardware_desc(&self.hardware_desc)?;
+        packet.set_software_desc(&self.software_desc)?;
+        packet.set_serial_number(&self.serial_number)?;
+        packet.set_datapath_desc(&self.datapath_desc)?;
+        Ok(())
+    }
+}
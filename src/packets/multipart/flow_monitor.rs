@@ -0,0 +1,688 @@
+//! Types to parse and emit the bodies of flow monitor (`OFPMP_FLOW_MONITOR`) multipart messages.
+//!
+//! A flow monitor request lets a controller subscribe to the changes of a set of flow entries. The
+//! switch answers with a stream of updates: first the flow entries that currently match the
+//! monitor (if requested), then an update each time a matching flow entry is added, removed or
+//! modified.
+use {Error, Repr, Result};
+use byteorder::{ByteOrder, NetworkEndian};
+use oxm::{ExperimenterOxm, FlowMatch};
+use packets::instruction::Instruction;
+use packets::list;
+use port::PortNumber;
+
+/// Initially matching flows.
+const FLAG_INITIAL: u16 = 1;
+/// New matching flows as they are added.
+const FLAG_ADD: u16 = 1 << 1;
+/// Old matching flows as they are removed.
+const FLAG_REMOVED: u16 = 1 << 2;
+/// Matching flows as they are changed.
+const FLAG_MODIFY: u16 = 1 << 3;
+/// If set, instructions are included.
+const FLAG_INSTRUCTIONS: u16 = 1 << 4;
+/// If set, include own changes in full.
+const FLAG_NO_ABBREV: u16 = 1 << 5;
+/// If set, don't include other controllers.
+const FLAG_ONLY_OWN: u16 = 1 << 6;
+
+/// Flags of a flow monitor request, that select which updates are sent by the switch.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Flags(u16);
+
+impl Flags {
+    pub fn new(bitmap: u16) -> Self {
+        Flags(bitmap)
+    }
+
+    pub fn initial(&self) -> bool {
+        (self.0 & FLAG_INITIAL) == FLAG_INITIAL
+    }
+
+    pub fn set_initial(&mut self) {
+        self.0 |= FLAG_INITIAL
+    }
+
+    pub fn add(&self) -> bool {
+        (self.0 & FLAG_ADD) == FLAG_ADD
+    }
+
+    pub fn set_add(&mut self) {
+        self.0 |= FLAG_ADD
+    }
+
+    pub fn removed(&self) -> bool {
+        (self.0 & FLAG_REMOVED) == FLAG_REMOVED
+    }
+
+    pub fn set_removed(&mut self) {
+        self.0 |= FLAG_REMOVED
+    }
+
+    pub fn modify(&self) -> bool {
+        (self.0 & FLAG_MODIFY) == FLAG_MODIFY
+    }
+
+    pub fn set_modify(&mut self) {
+        self.0 |= FLAG_MODIFY
+    }
+
+    pub fn instructions(&self) -> bool {
+        (self.0 & FLAG_INSTRUCTIONS) == FLAG_INSTRUCTIONS
+    }
+
+    pub fn set_instructions(&mut self) {
+        self.0 |= FLAG_INSTRUCTIONS
+    }
+
+    pub fn no_abbrev(&self) -> bool {
+        (self.0 & FLAG_NO_ABBREV) == FLAG_NO_ABBREV
+    }
+
+    pub fn set_no_abbrev(&mut self) {
+        self.0 |= FLAG_NO_ABBREV
+    }
+
+    pub fn only_own(&self) -> bool {
+        (self.0 & FLAG_ONLY_OWN) == FLAG_ONLY_OWN
+    }
+
+    pub fn set_only_own(&mut self) {
+        self.0 |= FLAG_ONLY_OWN
+    }
+}
+
+enum_with_unknown! {
+    /// Command of a flow monitor request.
+    pub doc enum Command(u8) {
+        /// New monitor.
+        Add = 0,
+        /// Modify existing monitor.
+        Modify = 1,
+        /// Delete/cancel existing monitor.
+        Delete = 2
+    }
+}
+
+enum_with_unknown! {
+    /// Type of a flow update.
+    pub doc enum Event(u16) {
+        /// Flow present when flow monitor created.
+        Initial = 0,
+        /// Flow was added.
+        Added = 1,
+        /// Flow was removed.
+        Removed = 2,
+        /// Flow instructions were changed.
+        Modified = 3,
+        /// Abbreviated reply.
+        Abbrev = 4,
+        /// Monitoring paused (out of buffer space).
+        Paused = 5,
+        /// Monitoring resumed.
+        Resumed = 6
+    }
+}
+
+/// A buffer representing the body of a flow monitor request.
+///
+/// ```no_rust
+/// +--------+--------+--------+--------+
+/// |             monitor_id            |
+/// +--------+--------+--------+--------+
+/// |              out_port             |
+/// +--------+--------+--------+--------+
+/// |             out_group             |
+/// +--------+--------+--------+--------+
+/// |      flags      |table_id|command |
+/// +--------+--------+--------+--------+
+/// |         Flow match fields         |
+/// | (variable length, 8 bytes aligned)|
+/// +--------+--------+--------+--------+
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Request<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod field {
+    use field::*;
+
+    pub const MONITOR_ID: Field = 0..4;
+    pub const OUT_PORT: Field = 4..8;
+    pub const OUT_GROUP: Field = 8..12;
+    pub const FLAGS: Field = 12..14;
+    pub const TABLE_ID: usize = 14;
+    pub const COMMAND: usize = 15;
+    pub const FLOW_MATCH: Rest = 16..;
+}
+
+impl<T: AsRef<[u8]>> Request<T> {
+    pub fn new(buffer: T) -> Request<T> {
+        Request { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Request<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    pub fn check_len(&self) -> Result<()> {
+        if self.buffer.as_ref().len() < field::FLOW_MATCH.start {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `monitor_id` field.
+    pub fn monitor_id(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[field::MONITOR_ID])
+    }
+
+    /// Return the `out_port` field.
+    pub fn out_port(&self) -> PortNumber {
+        let data = self.buffer.as_ref();
+        PortNumber::from(NetworkEndian::read_u32(&data[field::OUT_PORT]))
+    }
+
+    /// Return the `out_group` field.
+    pub fn out_group(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[field::OUT_GROUP])
+    }
+
+    /// Return the `flags` field.
+    pub fn flags(&self) -> Flags {
+        let data = self.buffer.as_ref();
+        Flags(NetworkEndian::read_u16(&data[field::FLAGS]))
+    }
+
+    /// Return the `table_id` field.
+    pub fn table_id(&self) -> u8 {
+        self.buffer.as_ref()[field::TABLE_ID]
+    }
+
+    /// Return the `command` field.
+    pub fn command(&self) -> Command {
+        Command::from(self.buffer.as_ref()[field::COMMAND])
+    }
+
+    /// Return the `match` field.
+    pub fn flow_match(&self) -> &[u8] {
+        &self.buffer.as_ref()[field::FLOW_MATCH]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Request<T> {
+    /// Set the `monitor_id` field.
+    pub fn set_monitor_id(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::MONITOR_ID], value)
+    }
+
+    /// Set the `out_port` field.
+    pub fn set_out_port(&mut self, value: PortNumber) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::OUT_PORT], value.into())
+    }
+
+    /// Set the `out_group` field.
+    pub fn set_out_group(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::OUT_GROUP], value)
+    }
+
+    /// Set the `flags` field.
+    pub fn set_flags(&mut self, value: Flags) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::FLAGS], value.0)
+    }
+
+    /// Set the `table_id` field.
+    pub fn set_table_id(&mut self, value: u8) {
+        self.buffer.as_mut()[field::TABLE_ID] = value;
+    }
+
+    /// Set the `command` field.
+    pub fn set_command(&mut self, value: Command) {
+        self.buffer.as_mut()[field::COMMAND] = value.into();
+    }
+
+    /// Return a mutable pointer to the `match` field.
+    pub fn flow_match_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer.as_mut()[field::FLOW_MATCH]
+    }
+}
+
+/// A high-level representation of the body of a flow monitor request.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RequestRepr<E = ExperimenterOxm> {
+    /// Controller-assigned ID for this monitor.
+    pub monitor_id: u32,
+    /// Require matching entries to include this as an output port. `PortNumber::Any` indicates no
+    /// restriction.
+    pub out_port: PortNumber,
+    /// Require matching entries to include this as an output group.
+    /// [`GROUP_ANY`](../constant.GROUP_ANY.html) indicates no restriction.
+    pub out_group: u32,
+    /// Select the updates the switch should send.
+    pub flags: Flags,
+    /// ID of table to monitor, or [`TABLE_ALL`](../constant.TABLE_ALL.html) for all tables.
+    pub table_id: u8,
+    /// Whether the monitor is added, modified or deleted.
+    pub command: Command,
+    /// Fields to match.
+    pub flow_match: FlowMatch<E>,
+}
+
+impl<E: Repr> Repr for RequestRepr<E> {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Request::new_checked(buffer)?;
+        Ok(RequestRepr {
+            monitor_id: packet.monitor_id(),
+            out_port: packet.out_port(),
+            out_group: packet.out_group(),
+            flags: packet.flags(),
+            table_id: packet.table_id(),
+            command: packet.command(),
+            flow_match: FlowMatch::parse(packet.flow_match())?,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        field::FLOW_MATCH.start + self.flow_match.buffer_len()
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Request::new(buffer);
+        packet.set_monitor_id(self.monitor_id);
+        packet.set_out_port(self.out_port);
+        packet.set_out_group(self.out_group);
+        packet.set_flags(self.flags);
+        packet.set_table_id(self.table_id);
+        packet.set_command(self.command);
+        self.flow_match.emit(packet.flow_match_mut())
+    }
+}
+
+/// A buffer representing a flow update, as found in the body of flow monitor replies.
+///
+/// All the updates start with the same header:
+///
+/// ```no_rust
+/// +--------+--------+--------+--------+
+/// |      length     |      event      |
+/// +--------+--------+--------+--------+
+/// ```
+///
+/// Full updates (`Initial`, `Added`, `Removed` and `Modified` events) are followed by:
+///
+/// ```no_rust
+/// +--------+--------+--------+--------+
+/// |table_id| reason |   idle_timeout  |
+/// +--------+--------+--------+--------+
+/// |   hard_timeout  |     priority    |
+/// +--------+--------+--------+--------+
+/// |               zeros               |
+/// +--------+--------+--------+--------+
+/// |               cookie              |
+/// |                                   |
+/// +--------+--------+--------+--------+
+/// |         Flow match fields         |
+/// | (variable length, 8 bytes aligned)|
+/// +--------+--------+--------+--------+
+/// |            instructions           |
+/// |         (variable length)         |
+/// +--------+--------+--------+--------+
+/// ```
+///
+/// Abbreviated updates are followed by the `xid` of the message that caused the change, and
+/// `Paused` and `Resumed` updates are followed by 4 zero bytes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Update<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod update_field {
+    use field::*;
+
+    pub const LENGTH: Field = 0..2;
+    pub const EVENT: Field = 2..4;
+    pub const TABLE_ID: usize = 4;
+    pub const REASON: usize = 5;
+    pub const IDLE_TIMEOUT: Field = 6..8;
+    pub const HARD_TIMEOUT: Field = 8..10;
+    pub const PRIORITY: Field = 10..12;
+    pub const ZEROS: Field = 12..16;
+    pub const COOKIE: Field = 16..24;
+    pub const FLOW_MATCH: usize = 24;
+    pub const XID: Field = 4..8;
+    pub const SHORT_ZEROS: Field = 4..8;
+}
+
+impl<T: AsRef<[u8]>> Update<T> {
+    pub fn new(buffer: T) -> Update<T> {
+        Update { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Update<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called. Only the accessors that are relevant
+    /// for the type of update are checked.
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < update_field::EVENT.end {
+            return Err(Error::Truncated);
+        }
+        let length = self.length() as usize;
+        let min_length = match self.event() {
+            Event::Initial | Event::Added | Event::Removed | Event::Modified => {
+                update_field::FLOW_MATCH
+            }
+            _ => update_field::XID.end,
+        };
+        if length < min_length {
+            Err(Error::Malformed)
+        } else if len < length {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `length` field.
+    pub fn length(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[update_field::LENGTH])
+    }
+
+    /// Return the `event` field.
+    pub fn event(&self) -> Event {
+        let data = self.buffer.as_ref();
+        Event::from(NetworkEndian::read_u16(&data[update_field::EVENT]))
+    }
+
+    /// Return the `table_id` field of a full update.
+    pub fn table_id(&self) -> u8 {
+        self.buffer.as_ref()[update_field::TABLE_ID]
+    }
+
+    /// Return the `reason` field of a full update.
+    pub fn reason(&self) -> u8 {
+        self.buffer.as_ref()[update_field::REASON]
+    }
+
+    /// Return the `idle_timeout` field of a full update.
+    pub fn idle_timeout(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[update_field::IDLE_TIMEOUT])
+    }
+
+    /// Return the `hard_timeout` field of a full update.
+    pub fn hard_timeout(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[update_field::HARD_TIMEOUT])
+    }
+
+    /// Return the `priority` field of a full update.
+    pub fn priority(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[update_field::PRIORITY])
+    }
+
+    /// Return the `cookie` field of a full update.
+    pub fn cookie(&self) -> u64 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u64(&data[update_field::COOKIE])
+    }
+
+    /// Return the `xid` field of an abbreviated update.
+    pub fn xid(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[update_field::XID])
+    }
+
+    /// Return the match and the instructions of a full update.
+    pub fn flow_match_and_instructions(&self) -> &[u8] {
+        let length = self.length() as usize;
+        &self.buffer.as_ref()[update_field::FLOW_MATCH..length]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Update<T> {
+    /// Set the `length` field.
+    pub fn set_length(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[update_field::LENGTH], value)
+    }
+
+    /// Set the `event` field.
+    pub fn set_event(&mut self, value: Event) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[update_field::EVENT], value.into())
+    }
+
+    /// Set the `table_id` field of a full update.
+    pub fn set_table_id(&mut self, value: u8) {
+        self.buffer.as_mut()[update_field::TABLE_ID] = value;
+    }
+
+    /// Set the `reason` field of a full update.
+    pub fn set_reason(&mut self, value: u8) {
+        self.buffer.as_mut()[update_field::REASON] = value;
+    }
+
+    /// Set the `idle_timeout` field of a full update.
+    pub fn set_idle_timeout(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[update_field::IDLE_TIMEOUT], value)
+    }
+
+    /// Set the `hard_timeout` field of a full update.
+    pub fn set_hard_timeout(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[update_field::HARD_TIMEOUT], value)
+    }
+
+    /// Set the `priority` field of a full update.
+    pub fn set_priority(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[update_field::PRIORITY], value)
+    }
+
+    /// Set the `zeros` field of a full update.
+    pub fn set_zeros(&mut self) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[update_field::ZEROS], 0)
+    }
+
+    /// Set the `cookie` field of a full update.
+    pub fn set_cookie(&mut self, value: u64) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u64(&mut data[update_field::COOKIE], value)
+    }
+
+    /// Set the `xid` field of an abbreviated update.
+    pub fn set_xid(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[update_field::XID], value)
+    }
+
+    /// Set the `zeros` field of a paused or resumed update.
+    pub fn set_short_zeros(&mut self) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[update_field::SHORT_ZEROS], 0)
+    }
+
+    /// Return a mutable pointer to the match and the instructions of a full update. The `length`
+    /// field must be set already.
+    pub fn flow_match_and_instructions_mut(&mut self) -> &mut [u8] {
+        let length = self.length() as usize;
+        &mut self.buffer.as_mut()[update_field::FLOW_MATCH..length]
+    }
+}
+
+/// A high-level representation of a full flow update, that describes a flow entry.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FullUpdateRepr<E = ExperimenterOxm> {
+    /// ID of the table the flow entry is in.
+    pub table_id: u8,
+    /// Reason why the flow entry was removed (`OFPRR_*`). Only meaningful for `Removed` events.
+    pub reason: u8,
+    /// Idle timeout of the flow entry.
+    pub idle_timeout: u16,
+    /// Hard timeout of the flow entry.
+    pub hard_timeout: u16,
+    /// Priority of the flow entry.
+    pub priority: u16,
+    /// Opaque controller-issued identifier of the flow entry.
+    pub cookie: u64,
+    /// Fields matched by the flow entry.
+    pub flow_match: FlowMatch<E>,
+    /// Instructions of the flow entry. Empty unless the monitor was created with the
+    /// `instructions` flag.
+    pub instructions: Vec<Instruction<E>>,
+}
+
+/// A high-level representation of a flow update.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum UpdateRepr<E = ExperimenterOxm> {
+    /// A flow entry that was present when the monitor was created.
+    Initial(FullUpdateRepr<E>),
+    /// A flow entry that was added.
+    Added(FullUpdateRepr<E>),
+    /// A flow entry that was removed.
+    Removed(FullUpdateRepr<E>),
+    /// A flow entry whose instructions were changed.
+    Modified(FullUpdateRepr<E>),
+    /// A change caused by the controller itself. `xid` is the transaction ID of the message that
+    /// caused the change.
+    Abbrev { xid: u32 },
+    /// Monitoring has been paused because the switch ran out of buffer space.
+    Paused,
+    /// Monitoring has been resumed.
+    Resumed,
+}
+
+impl<E> UpdateRepr<E> {
+    /// Return the event of this update.
+    pub fn event(&self) -> Event {
+        match *self {
+            UpdateRepr::Initial(_) => Event::Initial,
+            UpdateRepr::Added(_) => Event::Added,
+            UpdateRepr::Removed(_) => Event::Removed,
+            UpdateRepr::Modified(_) => Event::Modified,
+            UpdateRepr::Abbrev { .. } => Event::Abbrev,
+            UpdateRepr::Paused => Event::Paused,
+            UpdateRepr::Resumed => Event::Resumed,
+        }
+    }
+}
+
+impl<E: Repr> FullUpdateRepr<E> {
+    fn parse<T: AsRef<[u8]>>(packet: &Update<T>) -> Result<Self> {
+        let buffer = packet.flow_match_and_instructions();
+        let flow_match = FlowMatch::parse(buffer)?;
+        let instructions = list::parse(&buffer[FlowMatch::<E>::wire_len(buffer)?..])?;
+        Ok(FullUpdateRepr {
+            table_id: packet.table_id(),
+            reason: packet.reason(),
+            idle_timeout: packet.idle_timeout(),
+            hard_timeout: packet.hard_timeout(),
+            priority: packet.priority(),
+            cookie: packet.cookie(),
+            flow_match,
+            instructions,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        update_field::FLOW_MATCH + self.flow_match.buffer_len()
+            + list::buffer_len(&self.instructions)
+    }
+
+    fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(&self, packet: &mut Update<T>) -> Result<()> {
+        packet.set_table_id(self.table_id);
+        packet.set_reason(self.reason);
+        packet.set_idle_timeout(self.idle_timeout);
+        packet.set_hard_timeout(self.hard_timeout);
+        packet.set_priority(self.priority);
+        packet.set_zeros();
+        packet.set_cookie(self.cookie);
+        let buffer = packet.flow_match_and_instructions_mut();
+        let flow_match_len = self.flow_match.buffer_len();
+        self.flow_match.emit(&mut buffer[..flow_match_len])?;
+        list::emit(&self.instructions, &mut buffer[flow_match_len..])
+    }
+}
+
+impl<E: Repr> list::Entry for UpdateRepr<E> {
+    const LENGTH_OFFSET: usize = update_field::LENGTH.start;
+}
+
+impl<E: Repr> Repr for UpdateRepr<E> {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Update::new_checked(buffer)?;
+        Ok(match packet.event() {
+            Event::Initial => UpdateRepr::Initial(FullUpdateRepr::parse(&packet)?),
+            Event::Added => UpdateRepr::Added(FullUpdateRepr::parse(&packet)?),
+            Event::Removed => UpdateRepr::Removed(FullUpdateRepr::parse(&packet)?),
+            Event::Modified => UpdateRepr::Modified(FullUpdateRepr::parse(&packet)?),
+            Event::Abbrev => UpdateRepr::Abbrev { xid: packet.xid() },
+            Event::Paused => UpdateRepr::Paused,
+            Event::Resumed => UpdateRepr::Resumed,
+            Event::_Unknown(_) => return Err(Error::Unrecognized),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        match *self {
+            UpdateRepr::Initial(ref repr)
+            | UpdateRepr::Added(ref repr)
+            | UpdateRepr::Removed(ref repr)
+            | UpdateRepr::Modified(ref repr) => repr.buffer_len(),
+            UpdateRepr::Abbrev { .. } | UpdateRepr::Paused | UpdateRepr::Resumed => {
+                update_field::XID.end
+            }
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        let length = self.buffer_len();
+        if buffer.len() < length {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Update::new(&mut buffer[..length]);
+        packet.set_length(length as u16);
+        packet.set_event(self.event());
+        match *self {
+            UpdateRepr::Initial(ref repr)
+            | UpdateRepr::Added(ref repr)
+            | UpdateRepr::Removed(ref repr)
+            | UpdateRepr::Modified(ref repr) => repr.emit(&mut packet)?,
+            UpdateRepr::Abbrev { xid } => packet.set_xid(xid),
+            UpdateRepr::Paused | UpdateRepr::Resumed => packet.set_short_zeros(),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,418 @@
+//! Types to parse and emit multipart messages.
+//!
+//! Multipart messages are used to encode requests or replies that potentially carry a large amount
+//! of data and would not always fit in a single OpenFlow message. Both multipart requests and
+//! multipart replies have the same structure:
+//!
+//! ```no_rust
+//! +--------+--------+--------+--------+
+//! |      type       |      flags      |
+//! +--------+--------+--------+--------+
+//! |              padding              |
+//! +--------+--------+--------+--------+
+//! |                body               |
+//! |         (variable length)         |
+//! +--------+--------+--------+--------+
+//! ```
+//!
+//! - The type determines how the body is interpreted. See [Kind] for the different types.
+//! - The flags indicate whether more messages follow this one. See [Flags].
+//!
+//! [Kind]: enum.Kind.html
+//! [Flags]: struct.Flags.html
+
+pub mod aggregate;
+pub mod bundle_features;
+pub mod desc;
+pub mod flow_monitor;
+pub mod queue;
+
+use {Error, Repr, Result};
+use byteorder::{ByteOrder, NetworkEndian};
+use oxm::ExperimenterOxm;
+use oxs;
+use controller_status;
+use packets::list;
+
+/// Special table id that designates all the tables.
+pub const TABLE_ALL: u8 = 0xff;
+
+/// Special group id used in requests when no group is specified (_i.e._ wildcarded).
+pub const GROUP_ANY: u32 = 0xffff_ffff;
+
+enum_with_unknown! {
+    /// Type of a multipart message.
+    pub doc enum Kind(u16) {
+        /// Description of this OpenFlow switch.
+        Desc = 0,
+        /// Individual flow descriptions.
+        FlowDesc = 1,
+        /// Aggregate flow statistics.
+        AggregateStats = 2,
+        /// Flow table statistics.
+        TableStats = 3,
+        /// Port statistics.
+        PortStats = 4,
+        /// Queue statistics for a port.
+        QueueStats = 5,
+        /// Group counter statistics.
+        GroupStats = 6,
+        /// Group description.
+        GroupDesc = 7,
+        /// Group features.
+        GroupFeatures = 8,
+        /// Meter statistics.
+        MeterStats = 9,
+        /// Meter configuration.
+        MeterDesc = 10,
+        /// Meter features.
+        MeterFeatures = 11,
+        /// Table features.
+        TableFeatures = 12,
+        /// Port description.
+        PortDesc = 13,
+        /// Table description.
+        TableDesc = 14,
+        /// Queue description.
+        QueueDesc = 15,
+        /// Flow monitors.
+        FlowMonitor = 16,
+        /// Individual flow statistics.
+        FlowStats = 17,
+        /// Controller status.
+        ControllerStatus = 18,
+        /// Bundle features.
+        BundleFeatures = 19,
+        /// Experimenter extension.
+        Experimenter = 0xffff
+    }
+}
+
+/// More requests or replies follow.
+const FLAG_MORE: u16 = 1;
+
+/// Flags of a multipart message
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Flags(u16);
+
+impl Flags {
+    pub fn new(bitmap: u16) -> Self {
+        Flags(bitmap)
+    }
+
+    /// Return `true` if more requests or replies follow this one.
+    pub fn more(&self) -> bool {
+        (self.0 & FLAG_MORE) == FLAG_MORE
+    }
+
+    pub fn set_more(&mut self) {
+        self.0 |= FLAG_MORE
+    }
+
+    pub fn unset_more(&mut self) {
+        self.0 &= !FLAG_MORE
+    }
+}
+
+/// A wrapper to read and write a buffer representing a multipart request or reply.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod field {
+    use field::*;
+
+    pub const KIND: Field = 0..2;
+    pub const FLAGS: Field = 2..4;
+    pub const PADDING: Field = 4..8;
+    pub const BODY: Rest = 8..;
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with a multipart message structure.
+    pub fn new(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new] and [check_len].
+    ///
+    /// [new]: #method.new
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error::Truncated)` if the buffer is too short.
+    pub fn check_len(&self) -> Result<()> {
+        if self.buffer.as_ref().len() < field::PADDING.end {
+            Err(Error::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `type` field.
+    pub fn kind(&self) -> Kind {
+        let data = self.buffer.as_ref();
+        Kind::from(NetworkEndian::read_u16(&data[field::KIND]))
+    }
+
+    /// Return the `flags` field.
+    pub fn flags(&self) -> Flags {
+        let data = self.buffer.as_ref();
+        Flags(NetworkEndian::read_u16(&data[field::FLAGS]))
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to the body.
+    pub fn body(&self) -> &'a [u8] {
+        &self.buffer.as_ref()[field::BODY]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the `type` field.
+    pub fn set_kind(&mut self, value: Kind) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::KIND], value.into())
+    }
+
+    /// Set the `flags` field.
+    pub fn set_flags(&mut self, value: Flags) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[field::FLAGS], value.0)
+    }
+
+    /// Set the `padding` field.
+    pub fn set_padding(&mut self) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[field::PADDING], 0)
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + AsMut<[u8]> + ?Sized> Packet<&'a mut T> {
+    /// Return a mutable pointer to the body.
+    pub fn body_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer.as_mut()[field::BODY]
+    }
+}
+
+/// The body of a multipart request.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RequestBody<E = ExperimenterOxm> {
+    /// Request the description of the switch.
+    Desc,
+    /// Request aggregate statistics over a set of flows.
+    AggregateStats(aggregate::RequestRepr<E>),
+    /// Request the statistics of one or all the queues of one or all the ports.
+    QueueStats(queue::RequestRepr),
+    /// Request the description of one or all the queues of one or all the ports.
+    QueueDesc(queue::RequestRepr),
+    /// Create, modify or delete a flow monitor.
+    FlowMonitor(flow_monitor::RequestRepr<E>),
+    /// Request the status of all the controllers connected to the switch.
+    ControllerStatus,
+    /// Request the bundle capabilities of the switch.
+    BundleFeatures(bundle_features::RequestRepr),
+}
+
+impl<E: Repr> RequestBody<E> {
+    fn kind(&self) -> Kind {
+        match *self {
+            RequestBody::Desc => Kind::Desc,
+            RequestBody::AggregateStats(_) => Kind::AggregateStats,
+            RequestBody::QueueStats(_) => Kind::QueueStats,
+            RequestBody::QueueDesc(_) => Kind::QueueDesc,
+            RequestBody::FlowMonitor(_) => Kind::FlowMonitor,
+            RequestBody::ControllerStatus => Kind::ControllerStatus,
+            RequestBody::BundleFeatures(_) => Kind::BundleFeatures,
+        }
+    }
+
+    fn parse(kind: Kind, buffer: &[u8]) -> Result<Self> {
+        use self::RequestBody::*;
+        Ok(match kind {
+            // the description and controller status requests have no body
+            Kind::Desc | Kind::ControllerStatus if !buffer.is_empty() => {
+                return Err(Error::Malformed)
+            }
+            Kind::Desc => Desc,
+            Kind::AggregateStats => AggregateStats(aggregate::RequestRepr::parse(buffer)?),
+            Kind::QueueStats => QueueStats(queue::RequestRepr::parse(buffer)?),
+            Kind::QueueDesc => QueueDesc(queue::RequestRepr::parse(buffer)?),
+            Kind::FlowMonitor => FlowMonitor(flow_monitor::RequestRepr::parse(buffer)?),
+            Kind::ControllerStatus => ControllerStatus,
+            Kind::BundleFeatures => BundleFeatures(bundle_features::RequestRepr::parse(buffer)?),
+            _ => return Err(Error::Unrecognized),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        use self::RequestBody::*;
+        match *self {
+            Desc | ControllerStatus => 0,
+            AggregateStats(ref repr) => repr.buffer_len(),
+            QueueStats(ref repr) | QueueDesc(ref repr) => repr.buffer_len(),
+            FlowMonitor(ref repr) => repr.buffer_len(),
+            BundleFeatures(ref repr) => repr.buffer_len(),
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        use self::RequestBody::*;
+        match *self {
+            Desc | ControllerStatus => Ok(()),
+            AggregateStats(ref repr) => repr.emit(buffer),
+            QueueStats(ref repr) | QueueDesc(ref repr) => repr.emit(buffer),
+            FlowMonitor(ref repr) => repr.emit(buffer),
+            BundleFeatures(ref repr) => repr.emit(buffer),
+        }
+    }
+}
+
+/// A high-level representation of a multipart request.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RequestRepr<E = ExperimenterOxm> {
+    pub flags: Flags,
+    pub body: RequestBody<E>,
+}
+
+impl<E: Repr> Repr for RequestRepr<E> {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        Ok(RequestRepr {
+            flags: packet.flags(),
+            body: RequestBody::parse(packet.kind(), packet.body())?,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        field::PADDING.end + self.body.buffer_len()
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(buffer);
+        packet.set_kind(self.body.kind());
+        packet.set_flags(self.flags);
+        packet.set_padding();
+        self.body.emit(packet.body_mut())
+    }
+}
+
+/// The body of a multipart reply.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ReplyBody<E = ExperimenterOxm> {
+    /// Description of the switch.
+    Desc(desc::ReplyRepr),
+    /// Aggregate statistics over a set of flows.
+    AggregateStats(oxs::Stats),
+    /// Statistics of a set of queues.
+    QueueStats(Vec<queue::StatsRepr>),
+    /// Description of a set of queues.
+    QueueDesc(Vec<queue::DescRepr>),
+    /// Flow updates of one or several flow monitors.
+    FlowMonitor(Vec<flow_monitor::UpdateRepr<E>>),
+    /// Status of the controllers connected to the switch.
+    ControllerStatus(Vec<controller_status::PacketRepr>),
+    /// Bundle capabilities of the switch.
+    BundleFeatures(bundle_features::ReplyRepr),
+}
+
+impl<E: Repr> ReplyBody<E> {
+    fn kind(&self) -> Kind {
+        match *self {
+            ReplyBody::Desc(_) => Kind::Desc,
+            ReplyBody::AggregateStats(_) => Kind::AggregateStats,
+            ReplyBody::QueueStats(_) => Kind::QueueStats,
+            ReplyBody::QueueDesc(_) => Kind::QueueDesc,
+            ReplyBody::FlowMonitor(_) => Kind::FlowMonitor,
+            ReplyBody::ControllerStatus(_) => Kind::ControllerStatus,
+            ReplyBody::BundleFeatures(_) => Kind::BundleFeatures,
+        }
+    }
+
+    fn parse(kind: Kind, buffer: &[u8]) -> Result<Self> {
+        use self::ReplyBody::*;
+        Ok(match kind {
+            Kind::Desc => Desc(desc::ReplyRepr::parse(buffer)?),
+            Kind::AggregateStats => AggregateStats(oxs::Stats::parse(buffer)?),
+            Kind::QueueStats => QueueStats(list::parse(buffer)?),
+            Kind::QueueDesc => QueueDesc(list::parse(buffer)?),
+            Kind::FlowMonitor => FlowMonitor(list::parse(buffer)?),
+            Kind::ControllerStatus => ControllerStatus(list::parse(buffer)?),
+            Kind::BundleFeatures => BundleFeatures(bundle_features::ReplyRepr::parse(buffer)?),
+            _ => return Err(Error::Unrecognized),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        use self::ReplyBody::*;
+        match *self {
+            Desc(ref repr) => repr.buffer_len(),
+            AggregateStats(ref repr) => repr.buffer_len(),
+            QueueStats(ref entries) => list::buffer_len(entries),
+            QueueDesc(ref entries) => list::buffer_len(entries),
+            FlowMonitor(ref entries) => list::buffer_len(entries),
+            ControllerStatus(ref entries) => list::buffer_len(entries),
+            BundleFeatures(ref repr) => repr.buffer_len(),
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        use self::ReplyBody::*;
+        match *self {
+            Desc(ref repr) => repr.emit(buffer),
+            AggregateStats(ref repr) => repr.emit(buffer),
+            QueueStats(ref entries) => list::emit(entries, buffer),
+            QueueDesc(ref entries) => list::emit(entries, buffer),
+            FlowMonitor(ref entries) => list::emit(entries, buffer),
+            ControllerStatus(ref entries) => list::emit(entries, buffer),
+            BundleFeatures(ref repr) => repr.emit(buffer),
+        }
+    }
+}
+
+/// A high-level representation of a multipart reply.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ReplyRepr<E = ExperimenterOxm> {
+    pub flags: Flags,
+    pub body: ReplyBody<E>,
+}
+
+impl<E: Repr> Repr for ReplyRepr<E> {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        Ok(ReplyRepr {
+            flags: packet.flags(),
+            body: ReplyBody::parse(packet.kind(), packet.body())?,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        field::PADDING.end + self.body.buffer_len()
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(buffer);
+        packet.set_kind(self.body.kind());
+        packet.set_flags(self.flags);
+        packet.set_padding();
+        self.body.emit(packet.body_mut())
+    }
+}
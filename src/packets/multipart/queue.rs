@@ -0,0 +1,659 @@
+//! Types to parse and emit the bodies of queue statistics (`OFPMP_QUEUE_STATS`) and queue
+//! description (`OFPMP_QUEUE_DESC`) multipart messages.
+use {Error, Repr, Result};
+use byteorder::{ByteOrder, NetworkEndian};
+use packets::list;
+use port::PortNumber;
+use property::{self, ExperimenterRepr, UnknownRepr};
+
+/// Special queue id that designates all the queues of a port.
+pub const ALL: u32 = 0xffff_ffff;
+
+/// Special rate used in `MinRate` and `MaxRate` properties when the rate is not configured.
+pub const RATE_UNCONFIGURED: u16 = 0xffff;
+
+const PROPERTY_MIN_RATE: u16 = 1;
+const PROPERTY_MAX_RATE: u16 = 2;
+
+/// A buffer representing the body of a queue statistics request or of a queue description
+/// request. Both requests have the same layout.
+///
+/// ```no_rust
+/// +--------+--------+--------+--------+
+/// |              port_no              |
+/// +--------+--------+--------+--------+
+/// |              queue_id             |
+/// +--------+--------+--------+--------+
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Request<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod request_field {
+    use field::*;
+
+    pub const PORT_NO: Field = 0..4;
+    pub const QUEUE_ID: Field = 4..8;
+}
+
+impl<T: AsRef<[u8]>> Request<T> {
+    pub fn new(buffer: T) -> Request<T> {
+        Request { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Request<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < request_field::QUEUE_ID.end {
+            Err(Error::Truncated)
+        } else if len > request_field::QUEUE_ID.end {
+            Err(Error::Malformed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `port_no` field.
+    pub fn port_no(&self) -> PortNumber {
+        let data = self.buffer.as_ref();
+        PortNumber::from(NetworkEndian::read_u32(&data[request_field::PORT_NO]))
+    }
+
+    /// Return the `queue_id` field.
+    pub fn queue_id(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[request_field::QUEUE_ID])
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Request<T> {
+    /// Set the `port_no` field.
+    pub fn set_port_no(&mut self, value: PortNumber) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[request_field::PORT_NO], value.into())
+    }
+
+    /// Set the `queue_id` field.
+    pub fn set_queue_id(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[request_field::QUEUE_ID], value)
+    }
+}
+
+/// A high-level representation of the body of a queue statistics request or of a queue
+/// description request.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RequestRepr {
+    /// The port the queues belong to. Use `PortNumber::Any` for all the ports.
+    pub port_no: PortNumber,
+    /// The queue to describe. Use [`ALL`](constant.ALL.html) for all the queues.
+    pub queue_id: u32,
+}
+
+impl Repr for RequestRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Request::new_checked(buffer)?;
+        Ok(RequestRepr {
+            port_no: packet.port_no(),
+            queue_id: packet.queue_id(),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        request_field::QUEUE_ID.end
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Request::new(buffer);
+        packet.set_port_no(self.port_no);
+        packet.set_queue_id(self.queue_id);
+        Ok(())
+    }
+}
+
+/// A property of a queue statistics entry.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum StatsPropertyRepr {
+    /// Experimenter defined property.
+    Experimenter(ExperimenterRepr),
+    /// A property this library does not know about.
+    Unknown(UnknownRepr),
+}
+
+impl Repr for StatsPropertyRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = property::Packet::new_checked(buffer)?;
+        Ok(match packet.kind() {
+            property::EXPERIMENTER => {
+                StatsPropertyRepr::Experimenter(ExperimenterRepr::parse(buffer)?)
+            }
+            _ => StatsPropertyRepr::Unknown(UnknownRepr::parse(buffer)?),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        match *self {
+            StatsPropertyRepr::Experimenter(ref repr) => repr.buffer_len(),
+            StatsPropertyRepr::Unknown(ref repr) => repr.buffer_len(),
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        match *self {
+            StatsPropertyRepr::Experimenter(ref repr) => repr.emit(buffer),
+            StatsPropertyRepr::Unknown(ref repr) => repr.emit(buffer),
+        }
+    }
+}
+
+/// A buffer representing a queue statistics entry, found in queue statistics replies.
+///
+/// ```no_rust
+/// +--------+--------+--------+--------+
+/// |      length     |                 |
+/// +--------+--------+                 +
+/// |              padding              |
+/// +--------+--------+--------+--------+
+/// |              port_no              |
+/// +--------+--------+--------+--------+
+/// |              queue_id             |
+/// +--------+--------+--------+--------+
+/// |              tx_bytes             |
+/// |                                   |
+/// +--------+--------+--------+--------+
+/// |             tx_packets            |
+/// |                                   |
+/// +--------+--------+--------+--------+
+/// |             tx_errors             |
+/// |                                   |
+/// +--------+--------+--------+--------+
+/// |            duration_sec           |
+/// +--------+--------+--------+--------+
+/// |           duration_nsec           |
+/// +--------+--------+--------+--------+
+/// |             properties            |
+/// |         (variable length)         |
+/// +--------+--------+--------+--------+
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Stats<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod stats_field {
+    #![allow(non_snake_case)]
+    use field::*;
+
+    pub const LENGTH: Field = 0..2;
+    pub const PADDING: Field = 2..8;
+    pub const PORT_NO: Field = 8..12;
+    pub const QUEUE_ID: Field = 12..16;
+    pub const TX_BYTES: Field = 16..24;
+    pub const TX_PACKETS: Field = 24..32;
+    pub const TX_ERRORS: Field = 32..40;
+    pub const DURATION_SEC: Field = 40..44;
+    pub const DURATION_NSEC: Field = 44..48;
+
+    pub fn PROPERTIES(length: usize) -> Field {
+        DURATION_NSEC.end..length
+    }
+}
+
+impl<T: AsRef<[u8]>> Stats<T> {
+    pub fn new(buffer: T) -> Stats<T> {
+        Stats { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Stats<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < stats_field::LENGTH.end || len < self.length() as usize {
+            Err(Error::Truncated)
+        } else if (self.length() as usize) < stats_field::DURATION_NSEC.end {
+            Err(Error::Malformed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `length` field.
+    pub fn length(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[stats_field::LENGTH])
+    }
+
+    /// Return the `port_no` field.
+    pub fn port_no(&self) -> PortNumber {
+        let data = self.buffer.as_ref();
+        PortNumber::from(NetworkEndian::read_u32(&data[stats_field::PORT_NO]))
+    }
+
+    /// Return the `queue_id` field.
+    pub fn queue_id(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[stats_field::QUEUE_ID])
+    }
+
+    /// Return the `tx_bytes` field.
+    pub fn tx_bytes(&self) -> u64 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u64(&data[stats_field::TX_BYTES])
+    }
+
+    /// Return the `tx_packets` field.
+    pub fn tx_packets(&self) -> u64 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u64(&data[stats_field::TX_PACKETS])
+    }
+
+    /// Return the `tx_errors` field.
+    pub fn tx_errors(&self) -> u64 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u64(&data[stats_field::TX_ERRORS])
+    }
+
+    /// Return the `duration_sec` field.
+    pub fn duration_sec(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[stats_field::DURATION_SEC])
+    }
+
+    /// Return the `duration_nsec` field.
+    pub fn duration_nsec(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[stats_field::DURATION_NSEC])
+    }
+
+    /// Return the `properties` field.
+    pub fn properties(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[stats_field::PROPERTIES(self.length() as usize)]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Stats<T> {
+    /// Set the `length` field.
+    pub fn set_length(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[stats_field::LENGTH], value)
+    }
+
+    /// Set the `padding` field.
+    pub fn set_padding(&mut self) {
+        let data = self.buffer.as_mut();
+        for byte in &mut data[stats_field::PADDING] {
+            *byte = 0;
+        }
+    }
+
+    /// Set the `port_no` field.
+    pub fn set_port_no(&mut self, value: PortNumber) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[stats_field::PORT_NO], value.into())
+    }
+
+    /// Set the `queue_id` field.
+    pub fn set_queue_id(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[stats_field::QUEUE_ID], value)
+    }
+
+    /// Set the `tx_bytes` field.
+    pub fn set_tx_bytes(&mut self, value: u64) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u64(&mut data[stats_field::TX_BYTES], value)
+    }
+
+    /// Set the `tx_packets` field.
+    pub fn set_tx_packets(&mut self, value: u64) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u64(&mut data[stats_field::TX_PACKETS], value)
+    }
+
+    /// Set the `tx_errors` field.
+    pub fn set_tx_errors(&mut self, value: u64) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u64(&mut data[stats_field::TX_ERRORS], value)
+    }
+
+    /// Set the `duration_sec` field.
+    pub fn set_duration_sec(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[stats_field::DURATION_SEC], value)
+    }
+
+    /// Set the `duration_nsec` field.
+    pub fn set_duration_nsec(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[stats_field::DURATION_NSEC], value)
+    }
+
+    /// Return a mutable pointer to the `properties` field. Note that the length field must be set
+    /// already.
+    pub fn properties_mut(&mut self) -> &mut [u8] {
+        let length = self.length() as usize;
+        let data = self.buffer.as_mut();
+        &mut data[stats_field::PROPERTIES(length)]
+    }
+}
+
+/// A high-level representation of a queue statistics entry.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StatsRepr {
+    pub port_no: PortNumber,
+    pub queue_id: u32,
+    /// Number of transmitted bytes.
+    pub tx_bytes: u64,
+    /// Number of transmitted packets.
+    pub tx_packets: u64,
+    /// Number of packets dropped due to overrun.
+    pub tx_errors: u64,
+    /// Time queue has been alive in seconds.
+    pub duration_sec: u32,
+    /// Time queue has been alive in nanoseconds beyond `duration_sec`.
+    pub duration_nsec: u32,
+    pub properties: Vec<StatsPropertyRepr>,
+}
+
+impl list::Entry for StatsRepr {
+    const LENGTH_OFFSET: usize = stats_field::LENGTH.start;
+}
+
+impl Repr for StatsRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Stats::new_checked(buffer)?;
+        Ok(StatsRepr {
+            port_no: packet.port_no(),
+            queue_id: packet.queue_id(),
+            tx_bytes: packet.tx_bytes(),
+            tx_packets: packet.tx_packets(),
+            tx_errors: packet.tx_errors(),
+            duration_sec: packet.duration_sec(),
+            duration_nsec: packet.duration_nsec(),
+            properties: property::parse_list(packet.properties())?,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        stats_field::DURATION_NSEC.end + property::list_len(&self.properties)
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if self.buffer_len() > 0xffff {
+            return Err(Error::Malformed);
+        }
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Stats::new(buffer);
+        packet.set_length(self.buffer_len() as u16);
+        packet.set_padding();
+        packet.set_port_no(self.port_no);
+        packet.set_queue_id(self.queue_id);
+        packet.set_tx_bytes(self.tx_bytes);
+        packet.set_tx_packets(self.tx_packets);
+        packet.set_tx_errors(self.tx_errors);
+        packet.set_duration_sec(self.duration_sec);
+        packet.set_duration_nsec(self.duration_nsec);
+        property::emit_list(&self.properties, packet.properties_mut())
+    }
+}
+
+/// A property of a queue description entry.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DescPropertyRepr {
+    /// Minimum datarate guaranteed, in 1/10 of a percent. A value above 1000 means the minimum
+    /// rate is disabled. [`RATE_UNCONFIGURED`](constant.RATE_UNCONFIGURED.html) means it is not
+    /// configured.
+    MinRate(u16),
+    /// Maximum datarate, in 1/10 of a percent. A value above 1000 means the maximum rate is
+    /// disabled. [`RATE_UNCONFIGURED`](constant.RATE_UNCONFIGURED.html) means it is not
+    /// configured.
+    MaxRate(u16),
+    /// Experimenter defined property.
+    Experimenter(ExperimenterRepr),
+    /// A property this library does not know about.
+    Unknown(UnknownRepr),
+}
+
+impl Repr for DescPropertyRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = property::Packet::new_checked(buffer)?;
+        Ok(match packet.kind() {
+            PROPERTY_MIN_RATE | PROPERTY_MAX_RATE => {
+                // rate (2 bytes) + padding (2 bytes)
+                if packet.length() != 8 {
+                    return Err(Error::Malformed);
+                }
+                let rate = NetworkEndian::read_u16(&packet.payload()[0..2]);
+                if packet.kind() == PROPERTY_MIN_RATE {
+                    DescPropertyRepr::MinRate(rate)
+                } else {
+                    DescPropertyRepr::MaxRate(rate)
+                }
+            }
+            property::EXPERIMENTER => {
+                DescPropertyRepr::Experimenter(ExperimenterRepr::parse(buffer)?)
+            }
+            _ => DescPropertyRepr::Unknown(UnknownRepr::parse(buffer)?),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        match *self {
+            DescPropertyRepr::MinRate(_) | DescPropertyRepr::MaxRate(_) => 8,
+            DescPropertyRepr::Experimenter(ref repr) => repr.buffer_len(),
+            DescPropertyRepr::Unknown(ref repr) => repr.buffer_len(),
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        let (kind, rate) = match *self {
+            DescPropertyRepr::MinRate(rate) => (PROPERTY_MIN_RATE, rate),
+            DescPropertyRepr::MaxRate(rate) => (PROPERTY_MAX_RATE, rate),
+            DescPropertyRepr::Experimenter(ref repr) => return repr.emit(buffer),
+            DescPropertyRepr::Unknown(ref repr) => return repr.emit(buffer),
+        };
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = property::Packet::new(buffer);
+        packet.set_kind(kind);
+        packet.set_length(8);
+        let payload = packet.payload_mut();
+        NetworkEndian::write_u16(&mut payload[0..2], rate);
+        NetworkEndian::write_u16(&mut payload[2..4], 0);
+        Ok(())
+    }
+}
+
+/// A buffer representing a queue description entry, found in queue description replies.
+///
+/// ```no_rust
+/// +--------+--------+--------+--------+
+/// |              port_no              |
+/// +--------+--------+--------+--------+
+/// |              queue_id             |
+/// +--------+--------+--------+--------+
+/// |      length     |                 |
+/// +--------+--------+                 +
+/// |              padding              |
+/// +--------+--------+--------+--------+
+/// |             properties            |
+/// |         (variable length)         |
+/// +--------+--------+--------+--------+
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Desc<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+mod desc_field {
+    #![allow(non_snake_case)]
+    use field::*;
+
+    pub const PORT_NO: Field = 0..4;
+    pub const QUEUE_ID: Field = 4..8;
+    pub const LENGTH: Field = 8..10;
+    pub const PADDING: Field = 10..16;
+
+    pub fn PROPERTIES(length: usize) -> Field {
+        PADDING.end..length
+    }
+}
+
+impl<T: AsRef<[u8]>> Desc<T> {
+    pub fn new(buffer: T) -> Desc<T> {
+        Desc { buffer }
+    }
+
+    pub fn new_checked(buffer: T) -> Result<Desc<T>> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < desc_field::LENGTH.end || len < self.length() as usize {
+            Err(Error::Truncated)
+        } else if (self.length() as usize) < desc_field::PADDING.end {
+            Err(Error::Malformed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the `port_no` field.
+    pub fn port_no(&self) -> PortNumber {
+        let data = self.buffer.as_ref();
+        PortNumber::from(NetworkEndian::read_u32(&data[desc_field::PORT_NO]))
+    }
+
+    /// Return the `queue_id` field.
+    pub fn queue_id(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u32(&data[desc_field::QUEUE_ID])
+    }
+
+    /// Return the `length` field.
+    pub fn length(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_u16(&data[desc_field::LENGTH])
+    }
+
+    /// Return the `properties` field.
+    pub fn properties(&self) -> &[u8] {
+        let data = self.buffer.as_ref();
+        &data[desc_field::PROPERTIES(self.length() as usize)]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Desc<T> {
+    /// Set the `port_no` field.
+    pub fn set_port_no(&mut self, value: PortNumber) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[desc_field::PORT_NO], value.into())
+    }
+
+    /// Set the `queue_id` field.
+    pub fn set_queue_id(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[desc_field::QUEUE_ID], value)
+    }
+
+    /// Set the `length` field.
+    pub fn set_length(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[desc_field::LENGTH], value)
+    }
+
+    /// Set the `padding` field.
+    pub fn set_padding(&mut self) {
+        let data = self.buffer.as_mut();
+        for byte in &mut data[desc_field::PADDING] {
+            *byte = 0;
+        }
+    }
+
+    /// Return a mutable pointer to the `properties` field. Note that the length field must be set
+    /// already.
+    pub fn properties_mut(&mut self) -> &mut [u8] {
+        let length = self.length() as usize;
+        let data = self.buffer.as_mut();
+        &mut data[desc_field::PROPERTIES(length)]
+    }
+}
+
+/// A high-level representation of a queue description entry.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DescRepr {
+    /// Port this queue is attached to.
+    pub port_no: PortNumber,
+    /// Id for the specific queue.
+    pub queue_id: u32,
+    pub properties: Vec<DescPropertyRepr>,
+}
+
+impl list::Entry for DescRepr {
+    const LENGTH_OFFSET: usize = desc_field::LENGTH.start;
+}
+
+impl Repr for DescRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Desc::new_checked(buffer)?;
+        Ok(DescRepr {
+            port_no: packet.port_no(),
+            queue_id: packet.queue_id(),
+            properties: property::parse_list(packet.properties())?,
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        desc_field::PADDING.end + property::list_len(&self.properties)
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if self.buffer_len() > 0xffff {
+            return Err(Error::Malformed);
+        }
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Desc::new(buffer);
+        packet.set_port_no(self.port_no);
+        packet.set_queue_id(self.queue_id);
+        packet.set_length(self.buffer_len() as u16);
+        packet.set_padding();
+        property::emit_list(&self.properties, packet.properties_mut())
+    }
+}
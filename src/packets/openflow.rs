@@ -22,6 +22,11 @@ use features_reply;
 use set_config;
 use packet_in;
 use get_config_reply;
+use multipart;
+use controller_status;
+use group_mod;
+use meter_mod;
+use oxm::ExperimenterOxm;
 
 enum_with_unknown! {
     /// OpenFlow version
@@ -124,7 +129,10 @@ enum_with_unknown! {
         ///
         BundleControler     = 33,
         ///
-        BundleAddMessage    = 34
+        BundleAddMessage    = 34,
+
+        ///
+        ControllerStatus    = 35
     }
 }
 
@@ -144,6 +152,9 @@ mod field {
     pub const XID: Field = 4..8;
 }
 
+/// Length of the OpenFlow header.
+pub const HEADER_LEN: usize = field::XID.end;
+
 impl<T: AsRef<[u8]>> Packet<T> {
     /// Imbue a raw octet buffer with OpenFlow packet structure.
     pub fn new(buffer: T) -> Packet<T> {
@@ -214,7 +225,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
 
     /// Return the header length.
     pub fn header_len(&self) -> usize {
-        field::XID.end
+        HEADER_LEN
     }
 }
 
@@ -285,7 +296,7 @@ impl<'a, T: AsRef<[u8]> + AsMut<[u8]> + ?Sized> Packet<&'a mut T> {
 
 /// A high-level representation of an OpenFlow packet header.
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct PacketRepr<E> {
+pub struct PacketRepr<E = ExperimenterOxm> {
     pub version: Version,
     pub length: u16,
     pub kind: Kind,
@@ -296,13 +307,21 @@ pub struct PacketRepr<E> {
 impl<E: Repr> PacketRepr<E> {
     /// Set the length field automatically based on the payload
     pub fn set_length_auto(&mut self) {
+        if let PayloadRepr::RequestForward(ref mut inner) = self.payload {
+            inner.set_length_auto();
+        }
         // FIXME: this might panic. In practice I don't think it will happen but still.
         self.length = self.buffer_len() as u16;
     }
 }
 
+/// Return `true` if a message of this kind can be forwarded in a request forward message.
+fn is_forwardable(kind: Kind) -> bool {
+    kind == Kind::GroupMod || kind == Kind::MeterMod
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum PayloadRepr<E> {
+pub enum PayloadRepr<E = ExperimenterOxm> {
     EchoRequest(Vec<u8>),
     EchoReply(Vec<u8>),
     Error(error::PacketRepr),
@@ -313,6 +332,14 @@ pub enum PayloadRepr<E> {
     GetConfigReply(get_config_reply::PacketRepr),
     SetConfig(set_config::PacketRepr),
     PacketIn(packet_in::PacketRepr<E>),
+    GroupMod(group_mod::PacketRepr<E>),
+    MultipartRequest(multipart::RequestRepr<E>),
+    MultipartReply(multipart::ReplyRepr<E>),
+    MeterMod(meter_mod::PacketRepr),
+    /// A group modification or meter modification request, issued by another controller and
+    /// forwarded by the switch. The inner message is a complete OpenFlow message.
+    RequestForward(Box<PacketRepr<E>>),
+    ControllerStatus(controller_status::PacketRepr),
 }
 
 impl<E: Repr> PayloadRepr<E> {
@@ -329,6 +356,25 @@ impl<E: Repr> PayloadRepr<E> {
             Kind::GetConfigReply => GetConfigReply(get_config_reply::PacketRepr::parse(buffer)?),
             Kind::SetConfig => SetConfig(set_config::PacketRepr::parse(buffer)?),
             Kind::PacketIn => PacketIn(packet_in::PacketRepr::parse(buffer)?),
+            Kind::GroupMod => GroupMod(group_mod::PacketRepr::parse(buffer)?),
+            Kind::MultipartRequest => MultipartRequest(multipart::RequestRepr::parse(buffer)?),
+            Kind::MultipartReply => MultipartReply(multipart::ReplyRepr::parse(buffer)?),
+            Kind::MeterMod => MeterMod(meter_mod::PacketRepr::parse(buffer)?),
+            Kind::RequestForward => {
+                // Check the header of the inner message before parsing it, so that it cannot
+                // overflow the buffer or be another request forward message.
+                let inner = Packet::new_checked(buffer)?;
+                let length = inner.length() as usize;
+                if !is_forwardable(inner.kind()) || length < inner.header_len()
+                    || length > buffer.len()
+                {
+                    return Err(self::Error::Malformed);
+                }
+                RequestForward(Box::new(PacketRepr::parse(&buffer[..length])?))
+            }
+            Kind::ControllerStatus => {
+                ControllerStatus(controller_status::PacketRepr::parse(buffer)?)
+            }
             _ => return Err(self::Error::Unrecognized),
         })
     }
@@ -343,6 +389,12 @@ impl<E: Repr> PayloadRepr<E> {
             GetConfigReply(ref repr) => repr.buffer_len(),
             SetConfig(ref repr) => repr.buffer_len(),
             PacketIn(ref repr) => repr.buffer_len(),
+            GroupMod(ref repr) => repr.buffer_len(),
+            MultipartRequest(ref repr) => repr.buffer_len(),
+            MultipartReply(ref repr) => repr.buffer_len(),
+            MeterMod(ref repr) => repr.buffer_len(),
+            RequestForward(ref repr) => repr.buffer_len(),
+            ControllerStatus(ref repr) => repr.buffer_len(),
         }
     }
     fn emit(&self, buffer: &mut [u8]) -> Result<()> {
@@ -360,6 +412,16 @@ impl<E: Repr> PayloadRepr<E> {
             GetConfigReply(ref repr) => repr.emit(buffer),
             SetConfig(ref repr) => repr.emit(buffer),
             PacketIn(ref repr) => repr.emit(buffer),
+            GroupMod(ref repr) => repr.emit(buffer),
+            MultipartRequest(ref repr) => repr.emit(buffer),
+            MultipartReply(ref repr) => repr.emit(buffer),
+            MeterMod(ref repr) => repr.emit(buffer),
+            RequestForward(ref repr) => if is_forwardable(repr.kind) {
+                repr.emit(buffer)
+            } else {
+                Err(self::Error::Malformed)
+            },
+            ControllerStatus(ref repr) => repr.emit(buffer),
         }
     }
 }
@@ -405,3 +467,122 @@ impl<E: Repr> Repr for PacketRepr<E> {
         Ok(())
     }
 }
+
+/// An OpenFlow message that borrows its payload from the buffer it was parsed from, instead of
+/// copying it. Only the messages that are typically received at a high rate (echo requests and
+/// replies, errors and packet in messages) are borrowed, the other ones are parsed into their
+/// [owned representation](enum.PayloadRepr.html).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PacketReprRef<'a, E = ExperimenterOxm> {
+    pub version: Version,
+    pub length: u16,
+    pub kind: Kind,
+    pub xid: u32,
+    pub payload: PayloadReprRef<'a, E>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PayloadReprRef<'a, E = ExperimenterOxm> {
+    EchoRequest(&'a [u8]),
+    EchoReply(&'a [u8]),
+    Error(error::PacketReprRef<'a>),
+    PacketIn(packet_in::PacketReprRef<'a, E>),
+    /// Any other message, parsed into its owned representation.
+    Owned(PayloadRepr<E>),
+}
+
+impl<'a, E: Repr> PayloadReprRef<'a, E> {
+    fn parse(kind: Kind, buffer: &'a [u8]) -> Result<Self> {
+        use self::PayloadReprRef::*;
+        Ok(match kind {
+            Kind::Error => Error(error::PacketReprRef::parse(buffer)?),
+            Kind::EchoRequest => EchoRequest(buffer),
+            Kind::EchoReply => EchoReply(buffer),
+            Kind::PacketIn => PacketIn(packet_in::PacketReprRef::parse(buffer)?),
+            _ => Owned(PayloadRepr::parse(kind, buffer)?),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        use self::PayloadReprRef::*;
+        match *self {
+            EchoRequest(data) | EchoReply(data) => data.len(),
+            Error(ref repr) => repr.buffer_len(),
+            PacketIn(ref repr) => repr.buffer_len(),
+            Owned(ref repr) => repr.buffer_len(),
+        }
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        use self::PayloadReprRef::*;
+        match *self {
+            EchoRequest(data) | EchoReply(data) => {
+                if buffer.len() < data.len() {
+                    return Err(self::Error::Exhausted);
+                }
+                buffer[..data.len()].copy_from_slice(data);
+                Ok(())
+            }
+            Error(ref repr) => repr.emit(buffer),
+            PacketIn(ref repr) => repr.emit(buffer),
+            Owned(ref repr) => repr.emit(buffer),
+        }
+    }
+
+    /// Copy the borrowed payload into an owned representation.
+    pub fn to_repr(&self) -> PayloadRepr<E>
+    where
+        E: Clone,
+    {
+        match *self {
+            PayloadReprRef::EchoRequest(data) => PayloadRepr::EchoRequest(data.to_vec()),
+            PayloadReprRef::EchoReply(data) => PayloadRepr::EchoReply(data.to_vec()),
+            PayloadReprRef::Error(ref repr) => PayloadRepr::Error(repr.to_repr()),
+            PayloadReprRef::PacketIn(ref repr) => PayloadRepr::PacketIn(repr.to_repr()),
+            PayloadReprRef::Owned(ref repr) => repr.clone(),
+        }
+    }
+}
+
+impl<'a, E: Repr> PacketReprRef<'a, E> {
+    /// Parse an OpenFlow packet, borrowing its payload from `buffer` when possible.
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        Ok(PacketReprRef {
+            version: packet.version(),
+            kind: packet.kind(),
+            length: packet.length(),
+            xid: packet.xid(),
+            payload: PayloadReprRef::parse(packet.kind(), packet.payload())?,
+        })
+    }
+
+    /// Return the length of a packet that will be emitted from this representation.
+    pub fn buffer_len(&self) -> usize {
+        field::XID.end + self.payload.buffer_len()
+    }
+
+    /// Emit this representation into a buffer.
+    pub fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        let mut packet = Packet::new_checked(buffer)?;
+        packet.set_version(self.version);
+        packet.set_kind(self.kind);
+        packet.set_length(self.length);
+        packet.set_xid(self.xid);
+        self.payload.emit(packet.payload_mut())
+    }
+
+    /// Copy this message into an owned representation.
+    pub fn to_repr(&self) -> PacketRepr<E>
+    where
+        E: Clone,
+    {
+        PacketRepr {
+            version: self.version,
+            length: self.length,
+            kind: self.kind,
+            xid: self.xid,
+            payload: self.payload.to_repr(),
+        }
+    }
+}
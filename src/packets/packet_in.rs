@@ -19,7 +19,7 @@
 //! |       (variable length)           |
 //! +--------+--------+--------+--------+
 //! ```
-use oxm::FlowMatch;
+use oxm::{ExperimenterOxm, FlowMatch, FlowMatchRef, ParseOptions};
 use {Error, Repr, Result};
 use byteorder::{ByteOrder, NetworkEndian};
 
@@ -169,6 +169,11 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         NetworkEndian::write_u64(&mut data[field::COOKIE], value)
     }
 
+    /// Return a mutable pointer to the whole buffer after the `cookie` field.
+    pub fn flow_match_and_after_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer.as_mut()[field::FLOW_MATCH_AND_AFTER]
+    }
+
     pub fn set_flow_match(&mut self, value: &[u8]) {
         self.buffer.as_mut()[field::FLOW_MATCH(value.len())].copy_from_slice(value)
     }
@@ -186,7 +191,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
 
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct PacketRepr<E> {
+pub struct PacketRepr<E = ExperimenterOxm> {
     /// The `buffer_id` is an opaque value used by the datapath to identify a buffered packet. If
     /// the packet associated with the packet-in message is buffered, the `buffer_id` must be an
     /// identifier unique on the current connection referring to that packet on the switch. If the
@@ -229,16 +234,18 @@ pub struct PacketRepr<E> {
     pub frame: Vec<u8>,
 }
 
-impl<E: Repr> Repr for PacketRepr<E> {
-    fn parse(buffer: &[u8]) -> Result<Self> {
+impl<E: Repr> PacketRepr<E> {
+    /// Parse a packet in message, with the given options for its flow match.
+    /// [`parse()`](#method.parse) uses the [default](../oxm/struct.ParseOptions.html) options.
+    pub fn parse_with(buffer: &[u8], options: &ParseOptions) -> Result<Self> {
         // the buffer must be at least big enough for a message with an empty `flow_match` field
         // and an empty `frame` field.
         if buffer.len() < field::PADDING(0).end {
             return Err(Error::Exhausted);
         }
         let packet = Packet::new(buffer);
-        let flow_match = FlowMatch::parse(packet.flow_match_and_after())?;
-        let flow_match_len = flow_match.buffer_len();
+        let flow_match = FlowMatch::parse_with(packet.flow_match_and_after(), options)?;
+        let flow_match_len = FlowMatch::<E>::wire_len(packet.flow_match_and_after())?;
         packet.check_len(flow_match_len)?;
         Ok(PacketRepr {
             buffer_id: packet.buffer_id(),
@@ -250,6 +257,12 @@ impl<E: Repr> Repr for PacketRepr<E> {
             frame: packet.frame(flow_match_len).to_vec(),
         })
     }
+}
+
+impl<E: Repr> Repr for PacketRepr<E> {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        PacketRepr::parse_with(buffer, &ParseOptions::default())
+    }
 
     fn buffer_len(&self) -> usize {
         field::COOKIE.end + self.flow_match.buffer_len() + 2 + self.frame.len()
@@ -264,9 +277,93 @@ impl<E: Repr> Repr for PacketRepr<E> {
         packet.set_frame_length(self.frame_length);
         packet.set_reason(self.reason);
         packet.set_table_id(self.table_id);
+        packet.set_cookie(self.cookie);
+        self.flow_match.emit(packet.flow_match_and_after_mut())?;
         let flow_match_len = self.flow_match.buffer_len();
         packet.set_padding(flow_match_len);
         packet.set_frame(flow_match_len, &self.frame);
         Ok(())
     }
 }
+
+/// A packet in message that borrows its flow match and its frame from the buffer it was parsed
+/// from. See [`PacketRepr`](struct.PacketRepr.html) for the meaning of the fields.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PacketReprRef<'a, E = ExperimenterOxm> {
+    pub buffer_id: u32,
+    pub frame_length: u16,
+    pub reason: Reason,
+    pub table_id: u8,
+    pub cookie: u64,
+    pub flow_match: FlowMatchRef<'a, E>,
+    pub frame: &'a [u8],
+}
+
+impl<'a, E> Clone for PacketReprRef<'a, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, E> Copy for PacketReprRef<'a, E> {}
+
+impl<'a, E: Repr> PacketReprRef<'a, E> {
+    /// Parse a packet in message with the [default](../oxm/struct.ParseOptions.html) options.
+    pub fn parse(buffer: &'a [u8]) -> Result<Self> {
+        PacketReprRef::parse_with(buffer, &ParseOptions::default())
+    }
+
+    /// Parse a packet in message, with the given options for its flow match.
+    pub fn parse_with(buffer: &'a [u8], options: &ParseOptions) -> Result<Self> {
+        if buffer.len() < field::PADDING(0).end {
+            return Err(Error::Exhausted);
+        }
+        let packet = Packet::new(buffer);
+        let flow_match = FlowMatchRef::parse_with(&buffer[field::FLOW_MATCH_AND_AFTER], options)?;
+        let flow_match_len = FlowMatch::<E>::wire_len(&buffer[field::FLOW_MATCH_AND_AFTER])?;
+        packet.check_len(flow_match_len)?;
+        Ok(PacketReprRef {
+            buffer_id: packet.buffer_id(),
+            frame_length: packet.frame_length(),
+            table_id: packet.table_id(),
+            cookie: packet.cookie(),
+            reason: packet.reason(),
+            flow_match,
+            frame: &buffer[field::FRAME(flow_match_len)],
+        })
+    }
+
+    pub fn buffer_len(&self) -> usize {
+        field::COOKIE.end + self.flow_match.buffer_len() + 2 + self.frame.len()
+    }
+
+    pub fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() < self.buffer_len() {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(buffer);
+        packet.set_buffer_id(self.buffer_id);
+        packet.set_frame_length(self.frame_length);
+        packet.set_reason(self.reason);
+        packet.set_table_id(self.table_id);
+        packet.set_cookie(self.cookie);
+        self.flow_match.emit(packet.flow_match_and_after_mut())?;
+        let flow_match_len = self.flow_match.buffer_len();
+        packet.set_padding(flow_match_len);
+        packet.set_frame(flow_match_len, self.frame);
+        Ok(())
+    }
+
+    /// Copy the flow match and the frame into an owned packet in message.
+    pub fn to_repr(&self) -> PacketRepr<E> {
+        PacketRepr {
+            buffer_id: self.buffer_id,
+            frame_length: self.frame_length,
+            reason: self.reason,
+            table_id: self.table_id,
+            cookie: self.cookie,
+            flow_match: self.flow_match.to_repr(),
+            frame: self.frame.to_vec(),
+        }
+    }
+}
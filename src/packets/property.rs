@@ -0,0 +1,260 @@
+//! Types to parse and emit the TLV properties found in many OpenFlow 1.5 messages.
+//!
+//! ```no_rust
+//! +--------+--------+--------+--------+
+//! |     type        |      length     |
+//! +--------+--------+--------+--------+
+//! |              payload              |
+//! |         (variable length)         |
+//! +--------+--------+--------+--------+
+//! ```
+//!
+//! - The meaning of the type field depends on the message the property belongs to, except for
+//!   [`EXPERIMENTER`](constant.EXPERIMENTER.html) which is common to all the property lists.
+//! - The length is the length of the property, including the header but excluding the padding
+//!   that follows the property to make it 8 bytes aligned.
+use {Error, Repr, Result};
+use byteorder::{ByteOrder, NetworkEndian};
+
+/// Property type shared by all the experimenter properties
+pub const EXPERIMENTER: u16 = 0xffff;
+
+mod field {
+    #![allow(non_snake_case)]
+    use field::*;
+
+    pub const KIND: Field = 0..2;
+    pub const LENGTH: Field = 2..4;
+
+    pub fn PAYLOAD(length: usize) -> Field {
+        LENGTH.end..length
+    }
+
+    pub const EXPERIMENTER: Field = 4..8;
+    pub const EXP_TYPE: Field = 8..12;
+
+    pub fn EXPERIMENTER_DATA(length: usize) -> Field {
+        EXP_TYPE.end..length
+    }
+}
+
+/// A wrapper to read and write a buffer representing a property.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Packet<T: AsRef<[u8]>> {
+    inner: T,
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with a property structure.
+    pub fn new(buffer: T) -> Self {
+        Packet { inner: buffer }
+    }
+
+    /// Shorthand for a combination of [new] and [check_len].
+    ///
+    /// [new]: #method.new
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Self> {
+        let packet = Self::new(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error::Truncated)` if the buffer is too short, and `Err(Error::Malformed)` if
+    /// the length field is smaller than the property header.
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.inner.as_ref().len();
+        if len < field::LENGTH.end || len < self.length() as usize {
+            Err(Error::Truncated)
+        } else if (self.length() as usize) < field::LENGTH.end {
+            Err(Error::Malformed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Return the `type` field.
+    pub fn kind(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[field::KIND])
+    }
+
+    /// Return the `length` field.
+    pub fn length(&self) -> u16 {
+        NetworkEndian::read_u16(&self.inner.as_ref()[field::LENGTH])
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to the payload, which does not include the padding.
+    pub fn payload(&self) -> &'a [u8] {
+        &self.inner.as_ref()[field::PAYLOAD(self.length() as usize)]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the `type` field.
+    pub fn set_kind(&mut self, value: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[field::KIND], value)
+    }
+
+    /// Set the `length` field.
+    pub fn set_length(&mut self, value: u16) {
+        NetworkEndian::write_u16(&mut self.inner.as_mut()[field::LENGTH], value)
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + AsMut<[u8]> + ?Sized> Packet<&'a mut T> {
+    /// Return a mutable pointer to the payload. Note that the length field must be set already.
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let length = self.length() as usize;
+        &mut self.inner.as_mut()[field::PAYLOAD(length)]
+    }
+}
+
+/// Return the length of a property once padded for 8 bytes alignment.
+pub fn padded_len(length: usize) -> usize {
+    ((length + 7) / 8) * 8
+}
+
+/// Parse a list of properties that fills the whole buffer.
+pub fn parse_list<P: Repr>(buffer: &[u8]) -> Result<Vec<P>> {
+    let mut properties = Vec::new();
+    let mut offset = 0;
+    while offset < buffer.len() {
+        let length = Packet::new_checked(&buffer[offset..])?.length() as usize;
+        properties.push(P::parse(&buffer[offset..offset + length])?);
+        offset += padded_len(length);
+    }
+    Ok(properties)
+}
+
+/// Return the length of a list of properties, including the padding of each property.
+pub fn list_len<P: Repr>(properties: &[P]) -> usize {
+    properties
+        .iter()
+        .fold(0, |acc, property| acc + padded_len(property.buffer_len()))
+}
+
+/// Emit a list of properties, padding each of them for 8 bytes alignment.
+pub fn emit_list<P: Repr>(properties: &[P], buffer: &mut [u8]) -> Result<()> {
+    if buffer.len() < list_len(properties) {
+        return Err(Error::Exhausted);
+    }
+    let mut offset = 0;
+    for property in properties {
+        let length = property.buffer_len();
+        property.emit(&mut buffer[offset..offset + length])?;
+        for byte in &mut buffer[offset + length..offset + padded_len(length)] {
+            *byte = 0;
+        }
+        offset += padded_len(length);
+    }
+    Ok(())
+}
+
+/// An experimenter property. Experimenter properties can be found in all the property lists.
+///
+/// ```no_rust
+/// +--------+--------+--------+--------+
+/// |  type (0xffff)  |      length     |
+/// +--------+--------+--------+--------+
+/// |           experimenter            |
+/// +--------+--------+--------+--------+
+/// |          experimenter type        |
+/// +--------+--------+--------+--------+
+/// |        experimenter data          |
+/// |        (variable length)          |
+/// +--------+--------+--------+--------+
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ExperimenterRepr {
+    /// Experimenter ID which takes the same form as in `ofp_experimenter_header`.
+    pub experimenter: u32,
+    /// Experimenter defined type.
+    pub exp_type: u32,
+    /// Experimenter defined data.
+    pub data: Vec<u8>,
+}
+
+impl Repr for ExperimenterRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        let length = packet.length() as usize;
+        if packet.kind() != EXPERIMENTER || length < field::EXP_TYPE.end {
+            return Err(Error::Malformed);
+        }
+        let data = packet.into_inner();
+        Ok(ExperimenterRepr {
+            experimenter: NetworkEndian::read_u32(&data[field::EXPERIMENTER]),
+            exp_type: NetworkEndian::read_u32(&data[field::EXP_TYPE]),
+            data: data[field::EXPERIMENTER_DATA(length)].to_vec(),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        field::EXP_TYPE.end + self.data.len()
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        let length = self.buffer_len();
+        if length > 0xffff {
+            return Err(Error::Malformed);
+        }
+        if buffer.len() < length {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(buffer);
+        packet.set_kind(EXPERIMENTER);
+        packet.set_length(length as u16);
+        let data = packet.into_inner();
+        NetworkEndian::write_u32(&mut data[field::EXPERIMENTER], self.experimenter);
+        NetworkEndian::write_u32(&mut data[field::EXP_TYPE], self.exp_type);
+        data[field::EXPERIMENTER_DATA(length)].copy_from_slice(&self.data);
+        Ok(())
+    }
+}
+
+/// A property whose type is not known by this library. It is kept as is so that it can be emitted
+/// back.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct UnknownRepr {
+    /// The property type.
+    pub kind: u16,
+    /// The property payload, without the header nor the padding.
+    pub data: Vec<u8>,
+}
+
+impl Repr for UnknownRepr {
+    fn parse(buffer: &[u8]) -> Result<Self> {
+        let packet = Packet::new_checked(buffer)?;
+        Ok(UnknownRepr {
+            kind: packet.kind(),
+            data: packet.payload().to_vec(),
+        })
+    }
+
+    fn buffer_len(&self) -> usize {
+        field::LENGTH.end + self.data.len()
+    }
+
+    fn emit(&self, buffer: &mut [u8]) -> Result<()> {
+        let length = self.buffer_len();
+        if length > 0xffff {
+            return Err(Error::Malformed);
+        }
+        if buffer.len() < length {
+            return Err(Error::Exhausted);
+        }
+        let mut packet = Packet::new(buffer);
+        packet.set_kind(self.kind);
+        packet.set_length(length as u16);
+        packet.payload_mut().copy_from_slice(&self.data);
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+//! Read and write OpenFlow messages on blocking streams, such as a `std::net::TcpStream`.
+//!
+//! ```rust,ignore
+//! let mut stream = TcpStream::connect("127.0.0.1:6653")?;
+//! stream::write_message(&mut stream, &hello)?;
+//! let reply: PacketRepr = stream::read_message(&mut stream)?;
+//! ```
+
+use std::io::{Read, Write};
+
+use {Error, Repr, Result};
+use framer::{message_len, DEFAULT_MAX_LEN};
+use openflow::{PacketRepr, HEADER_LEN};
+
+/// Read exactly one message from `reader`: its header first, and then the rest of the message
+/// as announced by the header's `length` field.
+///
+/// I/O errors are returned as [`Error::Io`](../enum.Error.html#variant.Io). In particular, a
+/// stream that ends before a complete message is read returns an
+/// `io::ErrorKind::UnexpectedEof` error. If the `length` field is shorter than the header,
+/// [`Error::Malformed`](../enum.Error.html#variant.Malformed) is returned, and the stream cannot
+/// be read anymore.
+pub fn read_message<R: Read, E: Repr>(reader: &mut R) -> Result<PacketRepr<E>> {
+    let mut buffer = vec![0; HEADER_LEN];
+    reader.read_exact(&mut buffer)?;
+    let len = message_len(&buffer, DEFAULT_MAX_LEN)?.ok_or(Error::Truncated)?;
+    buffer.resize(len, 0);
+    reader.read_exact(&mut buffer[HEADER_LEN..])?;
+    PacketRepr::parse(&buffer)
+}
+
+/// Emit `repr` and write it to `writer`.
+///
+/// The `length` field is written as is, so it must be
+/// [set](../openflow/struct.PacketRepr.html#method.set_length_auto) beforehand:
+/// [`Error::Malformed`](../enum.Error.html#variant.Malformed) is returned if it is not the
+/// length of the emitted message. I/O errors are returned as
+/// [`Error::Io`](../enum.Error.html#variant.Io).
+pub fn write_message<W: Write, E: Repr>(writer: &mut W, repr: &PacketRepr<E>) -> Result<()> {
+    let len = repr.buffer_len();
+    if repr.length as usize != len {
+        return Err(Error::Malformed);
+    }
+    let mut buffer = vec![0; len];
+    repr.emit(&mut buffer)?;
+    writer.write_all(&buffer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Cursor};
+
+    use super::*;
+    use openflow::{Kind, PayloadRepr, Version};
+    use oxm::ExperimenterOxm;
+
+    static BYTES: [u8; 20] = [
+        // echo request, xid = 1, payload = "ping"
+        0x06, 0x02, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x01, 0x70, 0x69, 0x6e, 0x67,
+        // features request, xid = 2
+        0x06, 0x05, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02,
+    ];
+
+    fn echo_request() -> PacketRepr {
+        PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 12,
+            kind: Kind::EchoRequest,
+            xid: 1,
+            payload: PayloadRepr::EchoRequest(b"ping".to_vec()),
+        }
+    }
+
+    #[test]
+    fn test_read_message() {
+        let mut reader = Cursor::new(&BYTES[..]);
+        assert_eq!(read_message(&mut reader), Ok(echo_request()));
+        let repr: PacketRepr = read_message(&mut reader).unwrap();
+        assert_eq!(repr.kind, Kind::FeaturesRequest);
+        assert_eq!(repr.xid, 2);
+        assert_eq!(
+            read_message::<_, ExperimenterOxm>(&mut reader),
+            Err(Error::Io(io::ErrorKind::UnexpectedEof))
+        );
+
+        let mut reader = Cursor::new(&BYTES[..10]);
+        assert_eq!(
+            read_message::<_, ExperimenterOxm>(&mut reader),
+            Err(Error::Io(io::ErrorKind::UnexpectedEof))
+        );
+
+        let mut bytes = BYTES;
+        bytes[3] = 0x04;
+        assert_eq!(
+            read_message::<_, ExperimenterOxm>(&mut Cursor::new(&bytes[..])),
+            Err(Error::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_write_message() {
+        let mut writer = Vec::new();
+        write_message(&mut writer, &echo_request()).unwrap();
+        assert_eq!(&writer[..], &BYTES[..12]);
+
+        let mut repr = echo_request();
+        repr.length = 8;
+        assert_eq!(write_message(&mut writer, &repr), Err(Error::Malformed));
+    }
+}
@@ -0,0 +1,273 @@
+//! Helpers for the textual representation of flow matches, actions and instructions, which
+//! follows the syntax of `ovs-ofctl`.
+
+use core::fmt;
+use std::error::Error as StdError;
+
+use port::PortNumber;
+
+/// Why a textual representation could not be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The name of a field, an action or an instruction is unknown.
+    UnknownName,
+    /// A value is missing or invalid.
+    BadValue,
+    /// The protocol of a `tp_src` or `tp_dst` field cannot be determined, because no IP protocol
+    /// precedes it.
+    AmbiguousField,
+    /// A shorthand like `tcp`, or a `dl_type` or `nw_proto` field, contradicts a preceding one.
+    ConflictingField,
+}
+
+/// The error returned when parsing the textual representation of a flow match, an action or an
+/// instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The offending part of the input.
+    pub token: String,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, token: &str) -> Self {
+        ParseError {
+            kind,
+            token: token.to_string(),
+        }
+    }
+
+    pub fn unknown_name(token: &str) -> Self {
+        ParseError::new(ParseErrorKind::UnknownName, token)
+    }
+
+    pub fn bad_value(token: &str) -> Self {
+        ParseError::new(ParseErrorKind::BadValue, token)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ParseErrorKind::UnknownName => write!(f, "unknown name `{}`", self.token),
+            ParseErrorKind::BadValue => write!(f, "invalid value `{}`", self.token),
+            ParseErrorKind::AmbiguousField => write!(
+                f,
+                "`{}` requires a preceding `tcp`, `udp` or `sctp`",
+                self.token
+            ),
+            ParseErrorKind::ConflictingField => {
+                write!(f, "`{}` conflicts with a preceding field", self.token)
+            }
+        }
+    }
+}
+
+impl StdError for ParseError {}
+
+/// Split `s` on the commas that are not enclosed in parentheses, and trim the parts. Empty parts
+/// are dropped.
+pub fn split(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+    parts.into_iter().filter(|part| !part.is_empty()).collect()
+}
+
+/// If `s` is of the form `name(args)`, return `args`.
+pub fn strip_call<'a>(s: &'a str, name: &str) -> Option<&'a str> {
+    if s.starts_with(name) && s[name.len()..].starts_with('(') && s.ends_with(')') {
+        Some(&s[name.len() + 1..s.len() - 1])
+    } else {
+        None
+    }
+}
+
+/// Parse an unsigned integer, in decimal or in hexadecimal with a `0x` prefix.
+pub fn parse_u64(s: &str) -> Result<u64, ParseError> {
+    let s = s.trim();
+    let result = if s.starts_with("0x") || s.starts_with("0X") {
+        u64::from_str_radix(&s[2..], 16)
+    } else {
+        s.parse()
+    };
+    result.map_err(|_| ParseError::bad_value(s))
+}
+
+/// Parse an unsigned integer that fits in `len` bytes, and return its big endian representation.
+pub fn parse_uint(s: &str, len: usize) -> Result<Vec<u8>, ParseError> {
+    let value = parse_u64(s)?;
+    if len < 8 && value >> (8 * len) != 0 {
+        return Err(ParseError::bad_value(s));
+    }
+    Ok(to_bytes(value, len))
+}
+
+/// Return the big endian representation of an integer, on `len` bytes.
+pub fn to_bytes(value: u64, len: usize) -> Vec<u8> {
+    (0..len)
+        .map(|i| (value >> (8 * (len - 1 - i))) as u8)
+        .collect()
+}
+
+pub fn parse_u32(s: &str) -> Result<u32, ParseError> {
+    parse_uint(s, 4).map(|bytes| to_u64(&bytes) as u32)
+}
+
+pub fn parse_u16(s: &str) -> Result<u16, ParseError> {
+    parse_uint(s, 2).map(|bytes| to_u64(&bytes) as u16)
+}
+
+pub fn parse_u8(s: &str) -> Result<u8, ParseError> {
+    parse_uint(s, 1).map(|bytes| bytes[0])
+}
+
+/// Return the value of a big endian integer of at most 8 bytes.
+pub fn to_u64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |acc, &b| (acc << 8) | u64::from(b))
+}
+
+/// Parse bytes written in hexadecimal with a `0x` prefix. An empty string is empty data.
+pub fn parse_hex(s: &str) -> Result<Vec<u8>, ParseError> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !(s.starts_with("0x") || s.starts_with("0X")) {
+        return Err(ParseError::bad_value(s));
+    }
+    s.as_bytes()[2..]
+        .chunks(2)
+        .map(|digits| {
+            let byte = if digits.len() == 2 {
+                ::core::str::from_utf8(digits)
+                    .ok()
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            } else {
+                None
+            };
+            byte.ok_or_else(|| ParseError::bad_value(s))
+        })
+        .collect()
+}
+
+/// Format bytes in hexadecimal with a `0x` prefix. Empty data is formatted as an empty string.
+pub fn format_hex(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return String::new();
+    }
+    let mut s = String::from("0x");
+    for byte in bytes {
+        s.push_str(&format!("{:02x}", byte));
+    }
+    s
+}
+
+/// Parse an Ethernet address of the form `00:11:22:33:44:55`.
+pub fn parse_mac(s: &str) -> Result<Vec<u8>, ParseError> {
+    let bytes: Vec<u8> = s.split(':')
+        .map(|byte| {
+            if byte.len() == 2 {
+                u8::from_str_radix(byte, 16).ok()
+            } else {
+                None
+            }
+        })
+        .collect::<Option<_>>()
+        .ok_or_else(|| ParseError::bad_value(s))?;
+    if bytes.len() != 6 {
+        return Err(ParseError::bad_value(s));
+    }
+    Ok(bytes)
+}
+
+/// Format an Ethernet address as `00:11:22:33:44:55`.
+pub fn format_mac(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Parse a port number, either numeric or one of the reserved port names.
+pub fn parse_port(s: &str) -> Result<PortNumber, ParseError> {
+    Ok(match s.trim() {
+        "IN_PORT" | "in_port" => PortNumber::InPort,
+        "TABLE" | "table" => PortNumber::Table,
+        "NORMAL" | "normal" => PortNumber::Normal,
+        "FLOOD" | "flood" => PortNumber::Flood,
+        "ALL" | "all" => PortNumber::All,
+        "CONTROLLER" | "controller" => PortNumber::Controller,
+        "LOCAL" | "local" => PortNumber::Local,
+        "ANY" | "any" | "NONE" | "none" => PortNumber::Any,
+        "UNSET" | "unset" => PortNumber::Unset,
+        port => PortNumber::from(parse_u32(port)?),
+    })
+}
+
+/// Format a port number, using the reserved port names when possible.
+pub fn format_port(port: PortNumber) -> String {
+    match port {
+        PortNumber::InPort => "IN_PORT".to_string(),
+        PortNumber::Table => "TABLE".to_string(),
+        PortNumber::Normal => "NORMAL".to_string(),
+        PortNumber::Flood => "FLOOD".to_string(),
+        PortNumber::All => "ALL".to_string(),
+        PortNumber::Controller => "CONTROLLER".to_string(),
+        PortNumber::Local => "LOCAL".to_string(),
+        PortNumber::Any => "ANY".to_string(),
+        PortNumber::Unset => "UNSET".to_string(),
+        port => u32::from(port).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            split("ip, nw_src=10.0.0.1,packet_type=(1,0x800),,"),
+            vec!["ip", "nw_src=10.0.0.1", "packet_type=(1,0x800)"]
+        );
+        assert!(split(" ").is_empty());
+    }
+
+    #[test]
+    fn test_parse_uint() {
+        assert_eq!(parse_uint("0x0800", 2), Ok(vec![0x08, 0x00]));
+        assert_eq!(parse_uint("300", 2), Ok(vec![0x01, 0x2c]));
+        assert_eq!(parse_uint("300", 1), Err(ParseError::bad_value("300")));
+        assert_eq!(parse_uint("foo", 1), Err(ParseError::bad_value("foo")));
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(parse_hex("0x00ab"), Ok(vec![0x00, 0xab]));
+        assert_eq!(parse_hex(""), Ok(vec![]));
+        assert!(parse_hex("0x0").is_err());
+        assert_eq!(format_hex(&[0x00, 0xab]), "0x00ab");
+    }
+
+    #[test]
+    fn test_port() {
+        assert_eq!(parse_port("LOCAL"), Ok(PortNumber::Local));
+        assert_eq!(parse_port("7"), Ok(PortNumber::Regular(7)));
+        assert_eq!(format_port(PortNumber::Regular(7)), "7");
+        assert_eq!(format_port(PortNumber::Max), "4294967040");
+    }
+}
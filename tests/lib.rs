@@ -1,30 +1,13 @@
 extern crate ofpkt;
+extern crate smoltcp;
 
 use std::fs::File;
 use std::io::Read;
 use ofpkt::openflow;
 use ofpkt::oxm;
-use ofpkt::{Repr, Result};
+use ofpkt::Repr;
 
-
-// dummy OxmExperimenter field, since openflow::PacketRepr is generic over it.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct OxmExperimenter;
-
-impl Repr for OxmExperimenter {
-    fn parse(_buffer: &[u8]) -> Result<Self> {
-        unreachable!()
-    }
-
-    fn buffer_len(&self) -> usize {
-        unreachable!()
-    }
-    fn emit(&self, _buffer: &mut [u8]) -> Result<()> {
-        unreachable!()
-    }
-}
-
-type PacketRepr = openflow::PacketRepr<OxmExperimenter>;
+type PacketRepr = openflow::PacketRepr;
 
 fn load_packet(name: &str) -> Vec<u8> {
     let mut file = File::open(format!("./tests/data/{}", name)).unwrap();
@@ -178,6 +161,7 @@ mod parse {
     #[test]
     fn packet_in() {
         use ofpkt::packet_in;
+        use smoltcp::wire::Ipv4Address;
 
         let pkt = load_packet("packet_in");
         let repr = PacketRepr {
@@ -190,19 +174,17 @@ mod parse {
                 table_id: 100,
                 cookie: 0,
                 reason: packet_in::Reason::TableMiss,
-                flow_match: oxm::FlowMatch::<OxmExperimenter>(vec![
+                flow_match: oxm::FlowMatch(vec![
                     oxm::Oxm::FlowMatchField(oxm::FlowMatchField::InPort(oxm::InPort::new(43_981))),
                     oxm::Oxm::FlowMatchField(oxm::FlowMatchField::TunnelId(
                         oxm::TunnelId::new(50_000, None),
                     )),
-                    oxm::Oxm::PacketRegisters(oxm::PacketRegisters {
-                        field: 31, // corresponds to tun_ipv4_src in ryu
-                        value: (192 << 24) + (168 << 16) + (2 << 8) + 3,
+                    oxm::Oxm::Nxm(oxm::NxmField::TunnelIpv4Source {
+                        value: Ipv4Address([192, 168, 2, 3]),
                         mask: None,
                     }),
-                    oxm::Oxm::PacketRegisters(oxm::PacketRegisters {
-                        field: 32, // corresponds to tun_ipv4_dst in ryu
-                        value: (192 << 24) + (168 << 16) + (2 << 8) + 4,
+                    oxm::Oxm::Nxm(oxm::NxmField::TunnelIpv4Destination {
+                        value: Ipv4Address([192, 168, 2, 4]),
                         mask: None,
                     }),
                 ]),
@@ -212,6 +194,737 @@ mod parse {
         };
         assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
     }
+
+    #[test]
+    fn packet_in_truncated_field() {
+        use ofpkt::packet_in;
+        use ofpkt::Error;
+
+        // the flow match ends in the middle of its last field, but the frame is still found
+        // after the padding.
+        let mut pkt = load_packet("packet_in");
+        pkt[0x1b] = 0x26;
+        assert_eq!(PacketRepr::parse(&pkt).unwrap_err(), Error::Truncated);
+
+        let options = oxm::ParseOptions {
+            reject_truncated: false,
+            ..oxm::ParseOptions::lenient()
+        };
+        let repr =
+            packet_in::PacketRepr::<oxm::ExperimenterOxm>::parse_with(&pkt[8..], &options).unwrap();
+        assert_eq!(repr.flow_match.0.len(), 3);
+        assert_eq!(repr.frame, b"hoge".to_vec());
+        let borrowed =
+            packet_in::PacketReprRef::<oxm::ExperimenterOxm>::parse_with(&pkt[8..], &options)
+                .unwrap();
+        assert_eq!(borrowed.frame, b"hoge");
+    }
+
+    #[test]
+    fn borrowed() {
+        for name in &["echo_request", "echo_reply", "error_msg", "packet_in", "features_reply"] {
+            let pkt = load_packet(name);
+            let borrowed = openflow::PacketReprRef::parse(&pkt).unwrap();
+            let owned = PacketRepr::parse(&pkt).unwrap();
+            assert_eq!(borrowed.to_repr(), owned, "{}", name);
+
+            let mut buf = vec![0; borrowed.buffer_len()];
+            borrowed.emit(&mut buf).unwrap();
+            assert_eq!(buf, pkt, "{}", name);
+            let mut buf = vec![0; owned.buffer_len()];
+            owned.emit(&mut buf).unwrap();
+            assert_eq!(buf, pkt, "{}", name);
+        }
+
+        let pkt = load_packet("packet_in");
+        match openflow::PacketReprRef::<oxm::ExperimenterOxm>::parse(&pkt).unwrap().payload {
+            openflow::PayloadReprRef::PacketIn(repr) => {
+                assert_eq!(repr.frame, &[0x68, 0x6f, 0x67, 0x65]);
+                assert_eq!(repr.flow_match.iter().count(), 4);
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn queue_stats_request() {
+        use ofpkt::multipart;
+        use ofpkt::PortNumber;
+
+        let pkt = load_packet("queue_stats_request");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::QueueStats(multipart::queue::RequestRepr {
+                    port_no: PortNumber::Regular(43_981),
+                    queue_id: multipart::queue::ALL,
+                }),
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+    #[test]
+    fn queue_stats_reply() {
+        use ofpkt::multipart;
+        use ofpkt::multipart::queue;
+        use ofpkt::property::ExperimenterRepr;
+        use ofpkt::PortNumber;
+
+        let pkt = load_packet("queue_stats_reply");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::QueueStats(vec![
+                    queue::StatsRepr {
+                        port_no: PortNumber::Regular(7),
+                        queue_id: 1,
+                        tx_bytes: 0,
+                        tx_packets: 0,
+                        tx_errors: 0,
+                        duration_sec: 0,
+                        duration_nsec: 0,
+                        properties: vec![
+                            queue::StatsPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 0,
+                                data: vec![],
+                            }),
+                            queue::StatsPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 1,
+                                data: vec![0, 0, 0, 1],
+                            }),
+                            queue::StatsPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 2,
+                                data: vec![0, 0, 0, 1, 0, 0, 0, 2],
+                            }),
+                        ],
+                    },
+                    queue::StatsRepr {
+                        port_no: PortNumber::Regular(6),
+                        queue_id: 1,
+                        tx_bytes: 0,
+                        tx_packets: 0,
+                        tx_errors: 0,
+                        duration_sec: 0,
+                        duration_nsec: 0,
+                        properties: vec![],
+                    },
+                    queue::StatsRepr {
+                        port_no: PortNumber::Regular(7),
+                        queue_id: 2,
+                        tx_bytes: 0,
+                        tx_packets: 0,
+                        tx_errors: 0,
+                        duration_sec: 0,
+                        duration_nsec: 0,
+                        properties: vec![],
+                    },
+                ]),
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+    #[test]
+    fn queue_desc_request() {
+        use ofpkt::multipart;
+        use ofpkt::PortNumber;
+
+        let pkt = load_packet("queue_desc_request");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::QueueDesc(multipart::queue::RequestRepr {
+                    port_no: PortNumber::Regular(52_651),
+                    queue_id: 57_020,
+                }),
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+    #[test]
+    fn queue_desc_reply() {
+        use ofpkt::multipart;
+        use ofpkt::multipart::queue;
+        use ofpkt::property::ExperimenterRepr;
+        use ofpkt::PortNumber;
+
+        let pkt = load_packet("queue_desc_reply");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::QueueDesc(vec![
+                    queue::DescRepr {
+                        port_no: PortNumber::Regular(7),
+                        queue_id: 0,
+                        properties: vec![
+                            queue::DescPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 0,
+                                data: vec![],
+                            }),
+                        ],
+                    },
+                    queue::DescRepr {
+                        port_no: PortNumber::Regular(8),
+                        queue_id: 1,
+                        properties: vec![
+                            queue::DescPropertyRepr::MinRate(300),
+                            queue::DescPropertyRepr::MaxRate(900),
+                            queue::DescPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 0,
+                                data: vec![],
+                            }),
+                            queue::DescPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 1,
+                                data: vec![0, 0, 0, 1],
+                            }),
+                            queue::DescPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 2,
+                                data: vec![0, 0, 0, 1, 0, 0, 0, 2],
+                            }),
+                        ],
+                    },
+                ]),
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+
+    #[test]
+    fn desc_request() {
+        use ofpkt::multipart;
+        use ofpkt::Error;
+
+        let pkt = load_packet("desc_request");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::Desc,
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+
+        // the request has no body
+        let mut pkt = pkt;
+        pkt.extend_from_slice(&[0; 8]);
+        let length = pkt.len() as u8;
+        pkt[3] = length;
+        assert_eq!(PacketRepr::parse(&pkt), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn desc_reply() {
+        use ofpkt::multipart;
+
+        let pkt = load_packet("desc_reply");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::Desc(multipart::desc::ReplyRepr {
+                    manufacturer_desc: "mfr".into(),
+                    hardware_desc: "hw".into(),
+                    software_desc: "sw".into(),
+                    serial_number: "serial".into(),
+                    datapath_desc: "dp".into(),
+                }),
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+    #[test]
+    fn aggregate_stats_request() {
+        use ofpkt::multipart;
+        use ofpkt::PortNumber;
+
+        let pkt = load_packet("aggregate_stats_request");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::AggregateStats(multipart::aggregate::RequestRepr {
+                    table_id: multipart::TABLE_ALL,
+                    out_port: PortNumber::Any,
+                    out_group: multipart::GROUP_ANY,
+                    cookie: 0,
+                    cookie_mask: 0,
+                    flow_match: oxm::FlowMatch(vec![]),
+                }),
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+    #[test]
+    fn aggregate_stats_reply() {
+        use ofpkt::multipart;
+        use ofpkt::oxs;
+
+        let pkt = load_packet("aggregate_stats_reply");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::AggregateStats(oxs::Stats(vec![oxs::Stat::FlowCount(1)])),
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+    #[test]
+    fn flow_monitor_request() {
+        use ofpkt::multipart;
+        use ofpkt::multipart::flow_monitor;
+        use ofpkt::PortNumber;
+        use smoltcp::wire::EthernetAddress;
+
+        let pkt = load_packet("flow_monitor_request");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::FlowMonitor(flow_monitor::RequestRepr {
+                    monitor_id: 100_000_000,
+                    out_port: PortNumber::Regular(22),
+                    out_group: multipart::GROUP_ANY,
+                    flags: flow_monitor::Flags::new(0x0f),
+                    table_id: 33,
+                    command: flow_monitor::Command::Add,
+                    flow_match: oxm::FlowMatch(vec![
+                        oxm::Oxm::FlowMatchField(oxm::FlowMatchField::EthernetDestination(
+                            oxm::EthernetDestination::new(
+                                EthernetAddress([0xf2, 0x0b, 0xa4, 0x7d, 0xf8, 0xea]),
+                                None,
+                            ),
+                        )),
+                    ]),
+                }),
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+    #[test]
+    fn controller_status() {
+        use ofpkt::controller_status::{self, ChannelStatus, PropertyRepr, Reason, Role};
+
+        let pkt = load_packet("controller_status");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::ControllerStatus,
+            xid: 0,
+            payload: PayloadRepr::ControllerStatus(controller_status::PacketRepr {
+                short_id: 0xffff,
+                role: Role::Equal,
+                reason: Reason::ChannelStatus,
+                channel_status: ChannelStatus::Down,
+                properties: vec![PropertyRepr::Uri("tls:192.168.34.23:6653".into())],
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+    #[test]
+    fn controller_status_bad_uri() {
+        use ofpkt::Error;
+
+        // the URI is not valid UTF-8
+        let mut pkt = load_packet("controller_status");
+        pkt[0x1c] = 0xff;
+        assert_eq!(PacketRepr::parse(&pkt), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn controller_status_trailing_bytes() {
+        use ofpkt::Error;
+
+        // the message is longer than the controller status and its properties
+        let mut pkt = load_packet("controller_status");
+        pkt.extend_from_slice(&[0; 8]);
+        let length = pkt.len() as u8;
+        pkt[3] = length;
+        assert_eq!(PacketRepr::parse(&pkt), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn controller_status_request() {
+        use ofpkt::multipart;
+        use ofpkt::Error;
+
+        let pkt = load_packet("controller_status_request");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::ControllerStatus,
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+
+        // the request has no body
+        let mut pkt = pkt;
+        pkt.extend_from_slice(&[0; 8]);
+        let length = pkt.len() as u8;
+        pkt[3] = length;
+        assert_eq!(PacketRepr::parse(&pkt), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn controller_status_reply() {
+        use ofpkt::controller_status::{self, ChannelStatus, PropertyRepr, Reason, Role};
+        use ofpkt::multipart;
+
+        let pkt = load_packet("controller_status_reply");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::ControllerStatus(vec![
+                    controller_status::PacketRepr {
+                        short_id: 0xffff,
+                        role: Role::Equal,
+                        reason: Reason::ChannelStatus,
+                        channel_status: ChannelStatus::Down,
+                        properties: vec![PropertyRepr::Uri("tls:192.168.34.23:6653".into())],
+                    },
+                ]),
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+    #[test]
+    fn group_mod() {
+        use ofpkt::action::Action;
+        use ofpkt::group_mod::{self, BucketPropertyRepr, BucketRepr, Command, GroupType};
+        use smoltcp::wire::Ipv4Address;
+
+        let pkt = load_packet("group_mod");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::GroupMod,
+            xid: 0,
+            payload: PayloadRepr::GroupMod(group_mod::PacketRepr {
+                command: Command::InsertBucket,
+                group_type: GroupType::Select,
+                group_id: 0xaaaa_aaaa,
+                command_bucket_id: 0xbbbb_bbbb,
+                buckets: vec![
+                    BucketRepr {
+                        bucket_id: 0x1234_5678,
+                        actions: vec![
+                            Action::PopVlan,
+                            Action::SetField(oxm::Oxm::FlowMatchField(
+                                oxm::FlowMatchField::Ipv4Destination(oxm::Ipv4Destination::new(
+                                    Ipv4Address([192, 168, 2, 9]),
+                                    None,
+                                )),
+                            )),
+                        ],
+                        properties: vec![
+                            BucketPropertyRepr::Weight(0xcccc),
+                            BucketPropertyRepr::WatchPort(0xdddd),
+                            BucketPropertyRepr::WatchGroup(0xeeee_eeee),
+                        ],
+                    },
+                ],
+                properties: vec![],
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+    #[test]
+    fn meter_mod() {
+        use ofpkt::meter_mod::{self, BandKindRepr, BandRepr, Command, Flags};
+
+        let pkt = load_packet("meter_mod");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MeterMod,
+            xid: 0,
+            payload: PayloadRepr::MeterMod(meter_mod::PacketRepr {
+                command: Command::Add,
+                flags: Flags::new(0x0e),
+                meter_id: 100,
+                bands: vec![
+                    BandRepr {
+                        rate: 1000,
+                        burst_size: 10,
+                        band: BandKindRepr::Drop,
+                    },
+                    BandRepr {
+                        rate: 1000,
+                        burst_size: 10,
+                        band: BandKindRepr::DscpRemark { prec_level: 1 },
+                    },
+                ],
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+    #[test]
+    fn requestforward() {
+        use ofpkt::action::Action;
+        use ofpkt::group_mod::{self, BucketPropertyRepr, BucketRepr, Command, GroupType};
+        use smoltcp::wire::Ipv4Address;
+
+        let pkt = load_packet("requestforward");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::RequestForward,
+            xid: 0,
+            payload: PayloadRepr::RequestForward(Box::new(PacketRepr {
+                version: Version::OpenFlow1Dot5,
+                length: 80,
+                kind: Kind::GroupMod,
+                xid: 0,
+                payload: PayloadRepr::GroupMod(group_mod::PacketRepr {
+                    command: Command::InsertBucket,
+                    group_type: GroupType::Select,
+                    group_id: 0xaaaa_aaaa,
+                    command_bucket_id: 0xbbbb_bbbb,
+                    buckets: vec![
+                        BucketRepr {
+                            bucket_id: 0x1234_5678,
+                            actions: vec![
+                                Action::PopVlan,
+                                Action::SetField(oxm::Oxm::FlowMatchField(
+                                    oxm::FlowMatchField::Ipv4Destination(
+                                        oxm::Ipv4Destination::new(
+                                            Ipv4Address([192, 168, 2, 9]),
+                                            None,
+                                        ),
+                                    ),
+                                )),
+                            ],
+                            properties: vec![
+                                BucketPropertyRepr::Weight(0xcccc),
+                                BucketPropertyRepr::WatchPort(0xdddd),
+                                BucketPropertyRepr::WatchGroup(0xeeee_eeee),
+                            ],
+                        },
+                    ],
+                    properties: vec![],
+                }),
+            })),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+    #[test]
+    fn requestforward_bad_inner_header() {
+        use ofpkt::Error;
+
+        // inner message longer than the outer payload
+        let mut pkt = load_packet("requestforward");
+        let inner_len = pkt.len() as u16 - 8 + 1;
+        pkt[10] = (inner_len >> 8) as u8;
+        pkt[11] = inner_len as u8;
+        assert_eq!(PacketRepr::parse(&pkt), Err(Error::Malformed));
+
+        // inner message shorter than its header
+        pkt[10] = 0;
+        pkt[11] = 4;
+        assert_eq!(PacketRepr::parse(&pkt), Err(Error::Malformed));
+
+        // deeply nested request forward messages
+        let mut pkt = vec![0; 8000 * 8];
+        for (i, header) in pkt.chunks_mut(8).enumerate() {
+            let length = (8000 - i) * 8;
+            header[0] = 0x06;
+            header[1] = 32;
+            header[2] = (length >> 8) as u8;
+            header[3] = length as u8;
+        }
+        assert_eq!(PacketRepr::parse(&pkt), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn bundle_features_request() {
+        use ofpkt::multipart;
+        use ofpkt::multipart::bundle_features::{self, PropertyRepr, RequestFlags, Time,
+                                               TimeCapabilityRepr};
+
+        let pkt = load_packet("bundle_features_request");
+        let mut flags = RequestFlags::default();
+        flags.set_timestamp();
+        flags.set_time_set_sched();
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::BundleFeatures(bundle_features::RequestRepr {
+                    flags,
+                    properties: vec![PropertyRepr::TimeCapability(TimeCapabilityRepr {
+                        sched_accuracy: Time {
+                            seconds: 0x5555_5555_5555_5555,
+                            nanoseconds: 0x6666_6666,
+                        },
+                        sched_max_future: Time {
+                            seconds: 0x7777_7777_7777_7777,
+                            nanoseconds: 0x8888_8888,
+                        },
+                        sched_max_past: Time {
+                            seconds: 0x9999_9999_9999_9999,
+                            nanoseconds: 0xaaaa_aaaa,
+                        },
+                        timestamp: Time {
+                            seconds: 0xbbbb_bbbb_bbbb_bbbb,
+                            nanoseconds: 0xcccc_cccc,
+                        },
+                    })],
+                }),
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+
+    #[test]
+    fn bundle_features_reply() {
+        use ofpkt::multipart;
+        use ofpkt::multipart::bundle_features::{self, Capabilities, PropertyRepr, Time,
+                                               TimeCapabilityRepr};
+
+        let pkt = load_packet("bundle_features_reply");
+        let mut capabilities = Capabilities::default();
+        capabilities.set_atomic();
+        capabilities.set_ordered();
+        capabilities.set_time();
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::BundleFeatures(bundle_features::ReplyRepr {
+                    capabilities,
+                    properties: vec![PropertyRepr::TimeCapability(TimeCapabilityRepr {
+                        sched_accuracy: Time {
+                            seconds: 0x5555_5555_5555_5555,
+                            nanoseconds: 0x6666_6666,
+                        },
+                        sched_max_future: Time {
+                            seconds: 0x7777_7777_7777_7777,
+                            nanoseconds: 0x8888_8888,
+                        },
+                        sched_max_past: Time {
+                            seconds: 0x9999_9999_9999_9999,
+                            nanoseconds: 0xaaaa_aaaa,
+                        },
+                        timestamp: Time {
+                            seconds: 0xbbbb_bbbb_bbbb_bbbb,
+                            nanoseconds: 0xcccc_cccc,
+                        },
+                    })],
+                }),
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
+    #[test]
+    fn flow_monitor_reply() {
+        use ofpkt::action::Action;
+        use ofpkt::instruction::Instruction;
+        use ofpkt::multipart;
+        use ofpkt::multipart::flow_monitor::{FullUpdateRepr, UpdateRepr};
+        use ofpkt::PortNumber;
+        use smoltcp::wire::EthernetProtocol;
+
+        let pkt = load_packet("flow_monitor_reply");
+        let repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: pkt.len() as u16,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::FlowMonitor(vec![
+                    UpdateRepr::Initial(FullUpdateRepr {
+                        table_id: 0,
+                        reason: 0,
+                        idle_timeout: 600,
+                        hard_timeout: 700,
+                        priority: 3,
+                        cookie: 0,
+                        flow_match: oxm::FlowMatch(vec![
+                            oxm::Oxm::FlowMatchField(oxm::FlowMatchField::EthernetType(
+                                oxm::EthernetType::new(EthernetProtocol::Arp),
+                            )),
+                        ]),
+                        instructions: vec![
+                            Instruction::ApplyActions(vec![
+                                Action::Output {
+                                    port: PortNumber::Normal,
+                                    max_len: 0,
+                                },
+                            ]),
+                        ],
+                    }),
+                    UpdateRepr::Abbrev { xid: 1234 },
+                    UpdateRepr::Paused,
+                ]),
+            }),
+        };
+        assert_eq!(PacketRepr::parse(&pkt).unwrap(), repr);
+    }
 }
 
 mod encode {
@@ -295,6 +1008,12 @@ mod encode {
         emit_and_compare(&repr, "error_msg");
     }
 
+    #[test]
+    fn packet_in() {
+        let repr = PacketRepr::parse(&load_packet("packet_in")).unwrap();
+        emit_and_compare(&repr, "packet_in");
+    }
+
     #[test]
     fn features_reply() {
         use ofpkt::features_reply;
@@ -365,4 +1084,887 @@ mod encode {
         repr.set_length_auto();
         emit_and_compare(&repr, "set_config");
     }
+
+    #[test]
+    fn queue_stats_request() {
+        use ofpkt::multipart;
+        use ofpkt::PortNumber;
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::QueueStats(multipart::queue::RequestRepr {
+                    port_no: PortNumber::Regular(43_981),
+                    queue_id: multipart::queue::ALL,
+                }),
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "queue_stats_request");
+    }
+
+    #[test]
+    fn queue_stats_reply() {
+        use ofpkt::multipart;
+        use ofpkt::multipart::queue;
+        use ofpkt::property::ExperimenterRepr;
+        use ofpkt::PortNumber;
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::QueueStats(vec![
+                    queue::StatsRepr {
+                        port_no: PortNumber::Regular(7),
+                        queue_id: 1,
+                        tx_bytes: 0,
+                        tx_packets: 0,
+                        tx_errors: 0,
+                        duration_sec: 0,
+                        duration_nsec: 0,
+                        properties: vec![
+                            queue::StatsPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 0,
+                                data: vec![],
+                            }),
+                            queue::StatsPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 1,
+                                data: vec![0, 0, 0, 1],
+                            }),
+                            queue::StatsPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 2,
+                                data: vec![0, 0, 0, 1, 0, 0, 0, 2],
+                            }),
+                        ],
+                    },
+                    queue::StatsRepr {
+                        port_no: PortNumber::Regular(6),
+                        queue_id: 1,
+                        tx_bytes: 0,
+                        tx_packets: 0,
+                        tx_errors: 0,
+                        duration_sec: 0,
+                        duration_nsec: 0,
+                        properties: vec![],
+                    },
+                    queue::StatsRepr {
+                        port_no: PortNumber::Regular(7),
+                        queue_id: 2,
+                        tx_bytes: 0,
+                        tx_packets: 0,
+                        tx_errors: 0,
+                        duration_sec: 0,
+                        duration_nsec: 0,
+                        properties: vec![],
+                    },
+                ]),
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "queue_stats_reply");
+    }
+
+    #[test]
+    fn queue_desc_request() {
+        use ofpkt::multipart;
+        use ofpkt::PortNumber;
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::QueueDesc(multipart::queue::RequestRepr {
+                    port_no: PortNumber::Regular(52_651),
+                    queue_id: 57_020,
+                }),
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "queue_desc_request");
+    }
+
+    #[test]
+    fn queue_desc_reply() {
+        use ofpkt::multipart;
+        use ofpkt::multipart::queue;
+        use ofpkt::property::ExperimenterRepr;
+        use ofpkt::PortNumber;
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::QueueDesc(vec![
+                    queue::DescRepr {
+                        port_no: PortNumber::Regular(7),
+                        queue_id: 0,
+                        properties: vec![
+                            queue::DescPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 0,
+                                data: vec![],
+                            }),
+                        ],
+                    },
+                    queue::DescRepr {
+                        port_no: PortNumber::Regular(8),
+                        queue_id: 1,
+                        properties: vec![
+                            queue::DescPropertyRepr::MinRate(300),
+                            queue::DescPropertyRepr::MaxRate(900),
+                            queue::DescPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 0,
+                                data: vec![],
+                            }),
+                            queue::DescPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 1,
+                                data: vec![0, 0, 0, 1],
+                            }),
+                            queue::DescPropertyRepr::Experimenter(ExperimenterRepr {
+                                experimenter: 101,
+                                exp_type: 2,
+                                data: vec![0, 0, 0, 1, 0, 0, 0, 2],
+                            }),
+                        ],
+                    },
+                ]),
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "queue_desc_reply");
+    }
+
+    #[test]
+    fn queue_desc_too_long() {
+        use ofpkt::multipart::queue;
+        use ofpkt::property::{ExperimenterRepr, UnknownRepr};
+        use ofpkt::{Error, PortNumber};
+
+        let mut buffer = vec![0; 0x1_0010];
+        assert_eq!(
+            queue::RequestRepr {
+                port_no: PortNumber::Any,
+                queue_id: queue::ALL,
+            }.emit(&mut buffer[..7]),
+            Err(Error::Exhausted)
+        );
+
+        // the length of a property does not fit in its 16 bits `length` field
+        let property = UnknownRepr {
+            kind: 42,
+            data: vec![0; 0xffff],
+        };
+        assert_eq!(property.emit(&mut buffer), Err(Error::Malformed));
+        let property = ExperimenterRepr {
+            experimenter: 101,
+            exp_type: 0,
+            data: vec![0; 0xffff],
+        };
+        assert_eq!(property.emit(&mut buffer), Err(Error::Malformed));
+
+        // neither does the length of a queue with many properties
+        let repr = queue::DescRepr {
+            port_no: PortNumber::Regular(1),
+            queue_id: 0,
+            properties: vec![queue::DescPropertyRepr::MinRate(300); 0x2000],
+        };
+        let mut buffer = vec![0; repr.buffer_len()];
+        assert_eq!(repr.emit(&mut buffer), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn desc_request() {
+        use ofpkt::multipart;
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::Desc,
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "desc_request");
+    }
+
+    #[test]
+    fn desc_reply() {
+        use ofpkt::multipart;
+        use ofpkt::Error;
+
+        let desc = multipart::desc::ReplyRepr {
+            manufacturer_desc: "mfr".into(),
+            hardware_desc: "hw".into(),
+            software_desc: "sw".into(),
+            serial_number: "serial".into(),
+            datapath_desc: "dp".into(),
+        };
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::Desc(desc.clone()),
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "desc_reply");
+
+        let mut buffer = vec![0; desc.buffer_len() - 1];
+        assert_eq!(desc.emit(&mut buffer), Err(Error::Exhausted));
+    }
+
+    #[test]
+    fn aggregate_stats_request() {
+        use ofpkt::multipart;
+        use ofpkt::PortNumber;
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::AggregateStats(multipart::aggregate::RequestRepr {
+                    table_id: multipart::TABLE_ALL,
+                    out_port: PortNumber::Any,
+                    out_group: multipart::GROUP_ANY,
+                    cookie: 0,
+                    cookie_mask: 0,
+                    flow_match: oxm::FlowMatch(vec![]),
+                }),
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "aggregate_stats_request");
+    }
+
+    #[test]
+    fn aggregate_stats_reply() {
+        use ofpkt::multipart;
+        use ofpkt::oxs;
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::AggregateStats(oxs::Stats(vec![oxs::Stat::FlowCount(1)])),
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "aggregate_stats_reply");
+    }
+
+    #[test]
+    fn oxs_value_too_long() {
+        use ofpkt::oxs;
+        use ofpkt::Error;
+
+        // the length of an OXS field does not fit in its 8 bits `oxs_length` field
+        let stat = oxs::Stat::Unknown {
+            class: 0x8002,
+            field: 42,
+            value: vec![0; 256],
+        };
+        let mut bytes = vec![0; stat.buffer_len()];
+        assert_eq!(stat.emit(&mut bytes), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn flow_monitor_request() {
+        use ofpkt::multipart;
+        use ofpkt::multipart::flow_monitor;
+        use ofpkt::PortNumber;
+        use smoltcp::wire::EthernetAddress;
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::FlowMonitor(flow_monitor::RequestRepr {
+                    monitor_id: 100_000_000,
+                    out_port: PortNumber::Regular(22),
+                    out_group: multipart::GROUP_ANY,
+                    flags: flow_monitor::Flags::new(0x0f),
+                    table_id: 33,
+                    command: flow_monitor::Command::Add,
+                    flow_match: oxm::FlowMatch(vec![
+                        oxm::Oxm::FlowMatchField(oxm::FlowMatchField::EthernetDestination(
+                            oxm::EthernetDestination::new(
+                                EthernetAddress([0xf2, 0x0b, 0xa4, 0x7d, 0xf8, 0xea]),
+                                None,
+                            ),
+                        )),
+                    ]),
+                }),
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "flow_monitor_request");
+    }
+
+    #[test]
+    fn controller_status() {
+        use ofpkt::controller_status::{self, ChannelStatus, PropertyRepr, Reason, Role};
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::ControllerStatus,
+            xid: 0,
+            payload: PayloadRepr::ControllerStatus(controller_status::PacketRepr {
+                short_id: 0xffff,
+                role: Role::Equal,
+                reason: Reason::ChannelStatus,
+                channel_status: ChannelStatus::Down,
+                properties: vec![PropertyRepr::Uri("tls:192.168.34.23:6653".into())],
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "controller_status");
+    }
+
+    #[test]
+    fn controller_status_request() {
+        use ofpkt::multipart;
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::ControllerStatus,
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "controller_status_request");
+    }
+
+    #[test]
+    fn controller_status_reply() {
+        use ofpkt::controller_status::{self, ChannelStatus, PropertyRepr, Reason, Role};
+        use ofpkt::multipart;
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::ControllerStatus(vec![
+                    controller_status::PacketRepr {
+                        short_id: 0xffff,
+                        role: Role::Equal,
+                        reason: Reason::ChannelStatus,
+                        channel_status: ChannelStatus::Down,
+                        properties: vec![PropertyRepr::Uri("tls:192.168.34.23:6653".into())],
+                    },
+                ]),
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "controller_status_reply");
+    }
+
+    #[test]
+    fn group_mod() {
+        use ofpkt::action::Action;
+        use ofpkt::group_mod::{self, BucketPropertyRepr, BucketRepr, Command, GroupType};
+        use smoltcp::wire::Ipv4Address;
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::GroupMod,
+            xid: 0,
+            payload: PayloadRepr::GroupMod(group_mod::PacketRepr {
+                command: Command::InsertBucket,
+                group_type: GroupType::Select,
+                group_id: 0xaaaa_aaaa,
+                command_bucket_id: 0xbbbb_bbbb,
+                buckets: vec![
+                    BucketRepr {
+                        bucket_id: 0x1234_5678,
+                        actions: vec![
+                            Action::PopVlan,
+                            Action::SetField(oxm::Oxm::FlowMatchField(
+                                oxm::FlowMatchField::Ipv4Destination(oxm::Ipv4Destination::new(
+                                    Ipv4Address([192, 168, 2, 9]),
+                                    None,
+                                )),
+                            )),
+                        ],
+                        properties: vec![
+                            BucketPropertyRepr::Weight(0xcccc),
+                            BucketPropertyRepr::WatchPort(0xdddd),
+                            BucketPropertyRepr::WatchGroup(0xeeee_eeee),
+                        ],
+                    },
+                ],
+                properties: vec![],
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "group_mod");
+    }
+
+    #[test]
+    fn meter_mod() {
+        use ofpkt::meter_mod::{self, BandKindRepr, BandRepr, Command, Flags};
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MeterMod,
+            xid: 0,
+            payload: PayloadRepr::MeterMod(meter_mod::PacketRepr {
+                command: Command::Add,
+                flags: Flags::new(0x0e),
+                meter_id: 100,
+                bands: vec![
+                    BandRepr {
+                        rate: 1000,
+                        burst_size: 10,
+                        band: BandKindRepr::Drop,
+                    },
+                    BandRepr {
+                        rate: 1000,
+                        burst_size: 10,
+                        band: BandKindRepr::DscpRemark { prec_level: 1 },
+                    },
+                ],
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "meter_mod");
+    }
+
+    #[test]
+    fn meter_band_unknown_kind() {
+        use ofpkt::meter_mod::{BandKindRepr, BandRepr};
+        use ofpkt::Error;
+
+        // the unknown band would be parsed back as a drop band
+        let band = BandRepr {
+            rate: 1000,
+            burst_size: 10,
+            band: BandKindRepr::Unknown {
+                kind: 1,
+                data: vec![0; 4],
+            },
+        };
+        let mut buffer = vec![0; band.buffer_len()];
+        assert_eq!(band.emit(&mut buffer), Err(Error::Malformed));
+
+        // the length does not fit in 16 bits
+        let band = BandRepr {
+            rate: 1000,
+            burst_size: 10,
+            band: BandKindRepr::Unknown {
+                kind: 42,
+                data: vec![0; 0xffff],
+            },
+        };
+        let mut buffer = vec![0; band.buffer_len()];
+        assert_eq!(band.emit(&mut buffer), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn unknown_kinds() {
+        use ofpkt::action::Action;
+        use ofpkt::instruction::Instruction;
+        use ofpkt::Error;
+
+        let mut buffer = vec![0; 0x1_0000];
+
+        // the unknown entries would be parsed back as known ones
+        let action = Action::<oxm::ExperimenterOxm>::Unknown {
+            kind: 0,
+            data: vec![0; 12],
+        };
+        assert_eq!(action.emit(&mut buffer), Err(Error::Malformed));
+        let instruction = Instruction::<oxm::ExperimenterOxm>::Unknown {
+            kind: 1,
+            data: vec![0; 4],
+        };
+        assert_eq!(instruction.emit(&mut buffer), Err(Error::Malformed));
+
+        // the types known but not supported by this library are emitted as is
+        let action = Action::<oxm::ExperimenterOxm>::Unknown {
+            kind: 28,
+            data: vec![0; 4],
+        };
+        action.emit(&mut buffer).unwrap();
+        assert_eq!(Action::parse(&buffer[..action.buffer_len()]), Ok(action));
+
+        // the length does not fit in 16 bits
+        let action = Action::<oxm::ExperimenterOxm>::Unknown {
+            kind: 42,
+            data: vec![0; 0xffff],
+        };
+        let mut buffer = vec![0; action.buffer_len()];
+        assert_eq!(action.emit(&mut buffer), Err(Error::Malformed));
+        let instruction = Instruction::ApplyActions(vec![action]);
+        let mut buffer = vec![0; instruction.buffer_len()];
+        assert_eq!(instruction.emit(&mut buffer), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn requestforward() {
+        use ofpkt::action::Action;
+        use ofpkt::group_mod::{self, BucketPropertyRepr, BucketRepr, Command, GroupType};
+        use smoltcp::wire::Ipv4Address;
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::RequestForward,
+            xid: 0,
+            payload: PayloadRepr::RequestForward(Box::new(PacketRepr {
+                version: Version::OpenFlow1Dot5,
+                length: 0,
+                kind: Kind::GroupMod,
+                xid: 0,
+                payload: PayloadRepr::GroupMod(group_mod::PacketRepr {
+                    command: Command::InsertBucket,
+                    group_type: GroupType::Select,
+                    group_id: 0xaaaa_aaaa,
+                    command_bucket_id: 0xbbbb_bbbb,
+                    buckets: vec![
+                        BucketRepr {
+                            bucket_id: 0x1234_5678,
+                            actions: vec![
+                                Action::PopVlan,
+                                Action::SetField(oxm::Oxm::FlowMatchField(
+                                    oxm::FlowMatchField::Ipv4Destination(
+                                        oxm::Ipv4Destination::new(
+                                            Ipv4Address([192, 168, 2, 9]),
+                                            None,
+                                        ),
+                                    ),
+                                )),
+                            ],
+                            properties: vec![
+                                BucketPropertyRepr::Weight(0xcccc),
+                                BucketPropertyRepr::WatchPort(0xdddd),
+                                BucketPropertyRepr::WatchGroup(0xeeee_eeee),
+                            ],
+                        },
+                    ],
+                    properties: vec![],
+                }),
+            })),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "requestforward");
+    }
+
+    #[test]
+    fn bundle_features_request() {
+        use ofpkt::multipart;
+        use ofpkt::multipart::bundle_features::{self, PropertyRepr, RequestFlags, Time,
+                                               TimeCapabilityRepr};
+
+        let mut flags = RequestFlags::default();
+        flags.set_timestamp();
+        flags.set_time_set_sched();
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartRequest,
+            xid: 0,
+            payload: PayloadRepr::MultipartRequest(multipart::RequestRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::RequestBody::BundleFeatures(bundle_features::RequestRepr {
+                    flags,
+                    properties: vec![PropertyRepr::TimeCapability(TimeCapabilityRepr {
+                        sched_accuracy: Time {
+                            seconds: 0x5555_5555_5555_5555,
+                            nanoseconds: 0x6666_6666,
+                        },
+                        sched_max_future: Time {
+                            seconds: 0x7777_7777_7777_7777,
+                            nanoseconds: 0x8888_8888,
+                        },
+                        sched_max_past: Time {
+                            seconds: 0x9999_9999_9999_9999,
+                            nanoseconds: 0xaaaa_aaaa,
+                        },
+                        timestamp: Time {
+                            seconds: 0xbbbb_bbbb_bbbb_bbbb,
+                            nanoseconds: 0xcccc_cccc,
+                        },
+                    })],
+                }),
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "bundle_features_request");
+    }
+
+    #[test]
+    fn bundle_features_reply() {
+        use ofpkt::multipart;
+        use ofpkt::multipart::bundle_features::{self, Capabilities, PropertyRepr, Time,
+                                               TimeCapabilityRepr};
+
+        let mut capabilities = Capabilities::default();
+        capabilities.set_atomic();
+        capabilities.set_ordered();
+        capabilities.set_time();
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::BundleFeatures(bundle_features::ReplyRepr {
+                    capabilities,
+                    properties: vec![PropertyRepr::TimeCapability(TimeCapabilityRepr {
+                        sched_accuracy: Time {
+                            seconds: 0x5555_5555_5555_5555,
+                            nanoseconds: 0x6666_6666,
+                        },
+                        sched_max_future: Time {
+                            seconds: 0x7777_7777_7777_7777,
+                            nanoseconds: 0x8888_8888,
+                        },
+                        sched_max_past: Time {
+                            seconds: 0x9999_9999_9999_9999,
+                            nanoseconds: 0xaaaa_aaaa,
+                        },
+                        timestamp: Time {
+                            seconds: 0xbbbb_bbbb_bbbb_bbbb,
+                            nanoseconds: 0xcccc_cccc,
+                        },
+                    })],
+                }),
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "bundle_features_reply");
+    }
+
+    #[test]
+    fn flow_monitor_reply() {
+        use ofpkt::action::Action;
+        use ofpkt::instruction::Instruction;
+        use ofpkt::multipart;
+        use ofpkt::multipart::flow_monitor::{FullUpdateRepr, UpdateRepr};
+        use ofpkt::PortNumber;
+        use smoltcp::wire::EthernetProtocol;
+
+        let mut repr = PacketRepr {
+            version: Version::OpenFlow1Dot5,
+            length: 0,
+            kind: Kind::MultipartReply,
+            xid: 0,
+            payload: PayloadRepr::MultipartReply(multipart::ReplyRepr {
+                flags: multipart::Flags::new(0),
+                body: multipart::ReplyBody::FlowMonitor(vec![
+                    UpdateRepr::Initial(FullUpdateRepr {
+                        table_id: 0,
+                        reason: 0,
+                        idle_timeout: 600,
+                        hard_timeout: 700,
+                        priority: 3,
+                        cookie: 0,
+                        flow_match: oxm::FlowMatch(vec![
+                            oxm::Oxm::FlowMatchField(oxm::FlowMatchField::EthernetType(
+                                oxm::EthernetType::new(EthernetProtocol::Arp),
+                            )),
+                        ]),
+                        instructions: vec![
+                            Instruction::ApplyActions(vec![
+                                Action::Output {
+                                    port: PortNumber::Normal,
+                                    max_len: 0,
+                                },
+                            ]),
+                        ],
+                    }),
+                    UpdateRepr::Abbrev { xid: 1234 },
+                    UpdateRepr::Paused,
+                ]),
+            }),
+        };
+        repr.set_length_auto();
+        emit_and_compare(&repr, "flow_monitor_reply");
+    }
+}
+
+mod text {
+    use super::*;
+    use ofpkt::action::Action;
+    use ofpkt::instruction::Instruction;
+    use ofpkt::{ParseError, ParseErrorKind, PortNumber};
+    use smoltcp::wire::{EthernetProtocol, Ipv4Address};
+
+    type FlowMatch = oxm::FlowMatch;
+
+    fn round_trip<T>(s: &str) -> T
+    where
+        T: ::std::str::FromStr<Err = ParseError> + ::std::fmt::Display + ::std::fmt::Debug,
+    {
+        let parsed: T = s.parse().unwrap();
+        assert_eq!(parsed.to_string(), s);
+        parsed
+    }
+
+    #[test]
+    fn flow_match() {
+        let flow_match: FlowMatch = round_trip("in_port=1,tcp,nw_src=10.0.0.0/8,tcp_dst=80");
+        assert_eq!(flow_match.0.len(), 5);
+
+        let flow_match: FlowMatch = "in_port=1,tcp,nw_src=10.0.0.0/8,tp_dst=80".parse().unwrap();
+        assert_eq!(flow_match.to_string(), "in_port=1,tcp,nw_src=10.0.0.0/8,tcp_dst=80");
+
+        let error = "tp_dst=80".parse::<FlowMatch>().unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::AmbiguousField);
+        let error = "foo=1".parse::<FlowMatch>().unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::UnknownName);
+    }
+
+    #[test]
+    fn action() {
+        assert_eq!(
+            round_trip::<Action>("output:2"),
+            Action::Output {
+                port: PortNumber::Regular(2),
+                max_len: 0,
+            }
+        );
+        assert_eq!(
+            round_trip::<Action>("CONTROLLER:65535"),
+            Action::Output {
+                port: PortNumber::Controller,
+                max_len: 0xffff,
+            }
+        );
+        // the whole packet is sent to the controller unless a max_len is given
+        for s in &["CONTROLLER", "output:CONTROLLER", "output(port=CONTROLLER)"] {
+            assert_eq!(
+                s.parse::<Action>(),
+                Ok(Action::Output {
+                    port: PortNumber::Controller,
+                    max_len: 0xffff,
+                })
+            );
+        }
+        assert_eq!(
+            round_trip::<Action>("set_field:10.0.0.1->nw_src"),
+            Action::SetField(oxm::Oxm::FlowMatchField(oxm::FlowMatchField::Ipv4Source(
+                oxm::Ipv4Source::new(Ipv4Address([10, 0, 0, 1]), None),
+            )))
+        );
+        // set the most significant half of xreg1, i.e. reg2
+        let action = round_trip::<Action>("set_field:0x2a00000000/0xffffffff00000000->xreg1");
+        assert_eq!(
+            action,
+            Action::SetField(oxm::PacketRegisters::from_reg32(2, 0x2a, None).into())
+        );
+        let mut bytes = vec![0; action.buffer_len()];
+        action.emit(&mut bytes).unwrap();
+        assert_eq!(&bytes[4..8], &[0x80, 0x01, 0x03, 0x10]);
+        assert_eq!(Action::parse(&bytes), Ok(action));
+        round_trip::<Action>("NORMAL");
+        round_trip::<Action>("push_vlan:0x8100");
+        round_trip::<Action>("dec_ttl");
+        assert!("frobnicate".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn instruction() {
+        assert_eq!(
+            round_trip::<Instruction>("apply_actions(pop_vlan,output:2)"),
+            Instruction::ApplyActions(vec![
+                Action::PopVlan,
+                Action::Output {
+                    port: PortNumber::Regular(2),
+                    max_len: 0,
+                },
+            ])
+        );
+        round_trip::<Instruction>("write_metadata:0x1/0xff");
+        round_trip::<Instruction>("goto_table:1");
+        round_trip::<Instruction>("clear_actions");
+        assert_eq!(
+            "write_actions(set_field:0x0806->dl_type)".parse::<Instruction>(),
+            Ok(Instruction::WriteActions(vec![
+                Action::SetField(oxm::Oxm::FlowMatchField(oxm::FlowMatchField::EthernetType(
+                    oxm::EthernetType::new(EthernetProtocol::Arp),
+                ))),
+            ]))
+        );
+    }
+}
+
+mod stream {
+    use super::*;
+    use ofpkt::stream::{read_message, write_message};
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    static NAMES: [&str; 4] = ["hello", "features_reply", "packet_in", "echo_request"];
+
+    #[test]
+    fn tcp_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // echo every message back to the client
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            for _ in NAMES.iter() {
+                let repr: PacketRepr = read_message(&mut stream).unwrap();
+                write_message(&mut stream, &repr).unwrap();
+            }
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let bytes: Vec<u8> = NAMES.iter().flat_map(|name| load_packet(name)).collect();
+        for chunk in bytes.chunks(7) {
+            stream.write_all(chunk).unwrap();
+        }
+        for name in NAMES.iter() {
+            let repr: PacketRepr = read_message(&mut stream).unwrap();
+            assert_eq!(repr, PacketRepr::parse(&load_packet(name)).unwrap());
+        }
+        server.join().unwrap();
+    }
 }